@@ -0,0 +1,306 @@
+// `motts build`: bundles a program with the interpreter into a single executable.
+//
+// The executable is a copy of motts with the parsed program appended to it:
+//
+//   <motts binary> <program (JSON)> <length of the program: u64, little endian> <BUNDLE_MAGIC>
+//
+// At startup motts looks at the end of its own file. If a program is appended, it is run
+// (every commandline argument is passed on to the program) instead of motts' usual commands.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use colored::*;
+use serde_json::{json, Value};
+
+use crate::console::StdConsole;
+use crate::dialect::{operation_from_name, Dialect};
+use crate::modules::{load_program, LoadedProgram};
+use crate::numerals::Language;
+use crate::{
+    check_program_args, create_labels, execute_code_tokens, print_runtime_error, ExecutionConfig, Operation, Token,
+};
+
+// Marks the end of an executable with a bundled program
+static BUNDLE_MAGIC: &[u8; 8] = b"MOTTPROG";
+// Length + magic
+const TRAILER_LEN: usize = 16;
+// Changed whenever the bundled format changes, older bundles are refused
+const BUNDLE_VERSION: u64 = 1;
+
+pub struct BuildOptions {
+    pub dialect: Option<String>,
+    pub numbers: Language,
+    pub input_retries: usize,
+    // Where to write the executable (the name of the file without `.mt` if not given)
+    pub output: Option<String>,
+    pub file: String,
+}
+
+// Everything needed to run the program, without the source files
+struct Bundle {
+    tokens: Vec<Token>,
+    numbers: Language,
+    input_retries: usize,
+    // The text of the dialect file, if the program isn't written in the standard dialect
+    dialect: Option<String>,
+}
+
+fn encode(bundle: &Bundle) -> Vec<u8> {
+    let tokens: Vec<Value> = bundle
+        .tokens
+        .iter()
+        .map(|t| json!({"op": format!("{:?}", t.op), "name": t.name, "args": t.args}))
+        .collect();
+    let value: Value = json!({
+        "version": BUNDLE_VERSION,
+        "numbers": format!("{:?}", bundle.numbers),
+        "input_retries": bundle.input_retries,
+        "dialect": bundle.dialect,
+        "tokens": tokens,
+    });
+    value.to_string().into_bytes()
+}
+
+fn decode_token(value: &Value) -> Option<Token> {
+    let op: Operation = operation_from_name(value["op"].as_str()?)?;
+    let name: String = value["name"].as_str()?.to_string();
+    let args: Vec<String> = value["args"]
+        .as_array()?
+        .iter()
+        .map(|n| n.as_str().map(String::from))
+        .collect::<Option<Vec<String>>>()?;
+    Some(Token {
+        op,
+        nargs: args.len(),
+        name,
+        args,
+    })
+}
+
+fn decode(bytes: &[u8]) -> Result<Bundle, String> {
+    let invalid = || String::from("The bundled program is damaged.");
+    let value: Value = serde_json::from_slice(bytes).map_err(|_| invalid())?;
+    if value["version"].as_u64() != Some(BUNDLE_VERSION) {
+        return Err(String::from("The bundled program was made by another version of motts."));
+    }
+
+    let tokens: Vec<Token> = value["tokens"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(decode_token)
+        .collect::<Option<Vec<Token>>>()
+        .ok_or_else(invalid)?;
+    let numbers: Language = value["numbers"]
+        .as_str()
+        .and_then(Language::from_name)
+        .ok_or_else(invalid)?;
+    let input_retries: usize = value["input_retries"].as_u64().ok_or_else(invalid)? as usize;
+    let dialect: Option<String> = match &value["dialect"] {
+        Value::Null => None,
+        n => Some(n.as_str().ok_or_else(invalid)?.to_string()),
+    };
+    Ok(Bundle {
+        tokens,
+        numbers,
+        input_retries,
+        dialect,
+    })
+}
+
+// The executable with the program appended
+fn append_bundle(mut executable: Vec<u8>, payload: &[u8]) -> Vec<u8> {
+    executable.extend_from_slice(payload);
+    executable.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    executable.extend_from_slice(BUNDLE_MAGIC);
+    executable
+}
+
+// The length of the bundled program, if the trailer (the last bytes of a file) belongs to one
+fn bundle_length(trailer: &[u8; TRAILER_LEN]) -> Option<u64> {
+    if &trailer[8..] != BUNDLE_MAGIC {
+        return None;
+    }
+    let mut length: [u8; 8] = [0; 8];
+    length.copy_from_slice(&trailer[..8]);
+    Some(u64::from_le_bytes(length))
+}
+
+// Reads the program appended to the file at `path`, if there is one
+fn read_bundle(path: &Path) -> Option<Vec<u8>> {
+    let mut file = fs::File::open(path).ok()?;
+    let file_len: u64 = file.metadata().ok()?.len();
+    if file_len < TRAILER_LEN as u64 {
+        return None;
+    }
+    let mut trailer: [u8; TRAILER_LEN] = [0; TRAILER_LEN];
+    file.seek(SeekFrom::End(-(TRAILER_LEN as i64))).ok()?;
+    file.read_exact(&mut trailer).ok()?;
+
+    let length: u64 = bundle_length(&trailer)?;
+    let start: u64 = (file_len - TRAILER_LEN as u64).checked_sub(length)?;
+    let mut payload: Vec<u8> = vec![0; length as usize];
+    file.seek(SeekFrom::Start(start)).ok()?;
+    file.read_exact(&mut payload).ok()?;
+    Some(payload)
+}
+
+// The program bundled with the running executable (`None` for a plain motts)
+pub fn embedded_program() -> Option<Vec<u8>> {
+    read_bundle(&std::env::current_exe().ok()?)
+}
+
+// Runs the bundled program, returns the exit status
+pub fn run_bundle(payload: &[u8], program_args: Vec<String>) -> i32 {
+    if let Err(e) = check_program_args(&program_args) {
+        println!("{} {}", "Error:".red(), e);
+        return 1;
+    }
+    let bundle: Bundle = match decode(payload) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+    let dialect: Dialect = match &bundle.dialect {
+        Some(text) => match Dialect::parse(text) {
+            Ok(n) => n,
+            Err(e) => {
+                println!("{} {}", "Error:".red(), e);
+                return 1;
+            }
+        },
+        None => Dialect::standard(),
+    };
+
+    let labels: HashMap<String, usize> = create_labels(bundle.tokens.clone());
+    let config = ExecutionConfig {
+        input_retries: bundle.input_retries,
+        program_args,
+        numbers: bundle.numbers,
+        dialect,
+        ..Default::default()
+    };
+    match execute_code_tokens(bundle.tokens, labels, &config, &mut StdConsole) {
+        Ok(status) => status,
+        Err(e) => {
+            print_runtime_error(&e);
+            1
+        }
+    }
+}
+
+fn default_output(file: &str) -> String {
+    let stem: String = Path::new(file)
+        .file_stem()
+        .map_or_else(|| String::from("program"), |n| n.to_string_lossy().to_string());
+    format!("{}{}", stem, std::env::consts::EXE_SUFFIX)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+// Writes the executable, returns the exit status
+pub fn run_build(options: &BuildOptions) -> i32 {
+    match build(options) {
+        Ok(output) => {
+            println!("{}", format!("Built `{}`.", output).green());
+            0
+        }
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            1
+        }
+    }
+}
+
+fn build(options: &BuildOptions) -> Result<String, String> {
+    let dialect_text: Option<String> = match &options.dialect {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(n) => Some(n),
+            Err(e) => return Err(format!("Couldn't read dialect `{}`: {}", path, e)),
+        },
+        None => None,
+    };
+    let dialect: Dialect = match &dialect_text {
+        Some(text) => Dialect::parse(text)?,
+        None => Dialect::standard(),
+    };
+
+    let LoadedProgram { tokens, is_valid_code, header, .. } = load_program(&options.file, &dialect)?;
+    if !is_valid_code {
+        return Err(String::from("Code can't be built as a result of the above errors."));
+    }
+    let bundle = Bundle {
+        tokens,
+        numbers: header.numbers.unwrap_or(options.numbers),
+        input_retries: options.input_retries,
+        dialect: dialect_text,
+    };
+
+    let motts_path = std::env::current_exe().map_err(|e| format!("Couldn't find the motts executable: {}", e))?;
+    let motts: Vec<u8> = fs::read(&motts_path).map_err(|e| format!("Couldn't read the motts executable: {}", e))?;
+    let output: String = options.output.clone().unwrap_or_else(|| default_output(&options.file));
+    fs::write(&output, append_bundle(motts, &encode(&bundle)))
+        .and_then(|_| make_executable(&output))
+        .map_err(|e| format!("Couldn't write `{}`: {}", output, e))?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+
+    #[test]
+    fn bundled_programs_run_like_the_source() {
+        let dialect = Dialect::standard();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/add_1.mt");
+        let program = load_program(path.to_str().unwrap(), &dialect).unwrap();
+        let bundle = Bundle {
+            tokens: program.tokens,
+            numbers: Language::French,
+            input_retries: 2,
+            dialect: Some(String::from("base standard\nvar answ number 42\n")),
+        };
+
+        // The bundle is found behind any executable, but not in files without one
+        let executable = append_bundle(b"\x7fELF not really".to_vec(), &encode(&bundle));
+        let file = std::env::temp_dir().join(format!("motts-bundle-{}", std::process::id()));
+        fs::write(&file, &executable).unwrap();
+        let payload = read_bundle(&file).unwrap();
+        fs::write(&file, b"\x7fELF not really").unwrap();
+        assert!(read_bundle(&file).is_none());
+        fs::remove_file(&file).unwrap();
+
+        let decoded = decode(&payload).unwrap();
+        assert_eq!(decoded.numbers, Language::French);
+        assert_eq!(decoded.input_retries, 2);
+        assert_eq!(decoded.dialect, bundle.dialect);
+        assert_eq!(decoded.tokens.len(), bundle.tokens.len());
+        for (a, b) in decoded.tokens.iter().zip(&bundle.tokens) {
+            assert_eq!((a.op, &a.name, &a.args, a.nargs), (b.op, &b.name, &b.args, b.nargs));
+        }
+
+        let labels = create_labels(decoded.tokens.clone());
+        let mut console = BufferConsole::new("41\n");
+        let status = execute_code_tokens(decoded.tokens, labels, &ExecutionConfig::default(), &mut console);
+        assert_eq!(status.unwrap(), 0);
+        assert_eq!(console.output, "input a number: \nyour number +1 is: 42\n");
+
+        assert!(decode(b"{\"version\": 1}").is_err());
+        assert!(decode(b"not json").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+// The control-flow graph of a program: its basic blocks and the ways to get from one to another.
+//
+// A block starts at the beginning of the program, at every label and after every statement
+// which can jump (branches and reading lines, which jump at the end of the file) or stops the
+// program (exits). Blocks are connected by two kinds of edges:
+//
+//   fall-through   the next statement runs (after a branch: the condition wasn't met)
+//   jump           a branch whose condition was met, or a line read at the end of the file
+//
+// `motts cfg prog.mt` prints the graph in the DOT format of Graphviz:
+//
+//   motts cfg prog.mt | dot -Tsvg > prog.svg
+
+use std::collections::HashMap;
+use std::fs;
+
+use colored::*;
+
+use crate::dialect::Dialect;
+use crate::disasm::decode;
+use crate::modules::{load_program, LoadedProgram};
+use crate::numerals::{Language, Numerals};
+use crate::{create_labels, Operation, Token};
+
+pub struct CfgOptions {
+    pub dialect: Option<String>,
+    pub numbers: Language,
+    // Where to write the graph (stdout if not given)
+    pub output: Option<String>,
+    pub file: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    // The token indices of the statements, `end` is excluded
+    pub start: usize,
+    pub end: usize,
+    // The label the block starts with
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    FallThrough,
+    Jump,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    // Indices of the blocks
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    pub edges: Vec<Edge>,
+}
+
+// Where the statement jumps to (if it can): the label of a branch or a line read
+fn jump_label(t: &Token) -> Option<&String> {
+    match t.op {
+        Operation::Branch => t.args.get(2),
+        Operation::File if t.name.to_lowercase().starts_with('l') => t.args.get(2),
+        _ => None,
+    }
+}
+
+// Whether the next statement can run after this one
+fn falls_through(t: &Token) -> bool {
+    t.op != Operation::Exit
+}
+
+impl Cfg {
+    // Builds the graph of the tokens, `labels` from `create_labels`.
+    // Jumps to labels which don't exist have no edge (they stop the program with an error).
+    pub fn build(tokens: &[Token], labels: &HashMap<String, usize>) -> Cfg {
+        let mut starts: Vec<bool> = vec![false; tokens.len()];
+        for (ip, t) in tokens.iter().enumerate() {
+            if ip == 0 || t.op == Operation::Label {
+                starts[ip] = true;
+            }
+            let ends_block: bool = jump_label(t).is_some() || !falls_through(t);
+            if ends_block && ip + 1 < tokens.len() {
+                starts[ip + 1] = true;
+            }
+        }
+
+        let mut blocks: Vec<BasicBlock> = vec![];
+        let mut block_of: Vec<usize> = Vec::with_capacity(tokens.len());
+        for (ip, t) in tokens.iter().enumerate() {
+            if starts[ip] {
+                if let Some(last) = blocks.last_mut() {
+                    last.end = ip;
+                }
+                blocks.push(BasicBlock {
+                    start: ip,
+                    end: tokens.len(),
+                    label: (t.op == Operation::Label).then(|| t.name.clone()),
+                });
+            }
+            block_of.push(blocks.len() - 1);
+        }
+
+        let mut edges: Vec<Edge> = vec![];
+        for (index, block) in blocks.iter().enumerate() {
+            let last: &Token = &tokens[block.end - 1];
+            // A jump goes to the label, which is the start of a block
+            if let Some(target) = jump_label(last).and_then(|n| labels.get(n)) {
+                edges.push(Edge {
+                    from: index,
+                    to: block_of[*target],
+                    kind: EdgeKind::Jump,
+                });
+            }
+            if falls_through(last) && block.end < tokens.len() {
+                edges.push(Edge {
+                    from: index,
+                    to: index + 1,
+                    kind: EdgeKind::FallThrough,
+                });
+            }
+        }
+        Cfg { blocks, edges }
+    }
+
+    // The block containing the token at `ip`
+    #[cfg(test)]
+    pub fn block_of(&self, ip: usize) -> usize {
+        self.blocks.iter().position(|b| b.start <= ip && ip < b.end).unwrap()
+    }
+
+    pub fn successors(&self, block: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |e| e.from == block)
+    }
+
+    pub fn predecessors(&self, block: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |e| e.to == block)
+    }
+
+    // Whether every block can be reached from the start of the program
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable: Vec<bool> = vec![false; self.blocks.len()];
+        let mut todo: Vec<usize> = if self.blocks.is_empty() { vec![] } else { vec![0] };
+        while let Some(block) = todo.pop() {
+            if reachable[block] {
+                continue;
+            }
+            reachable[block] = true;
+            todo.extend(self.successors(block).map(|e| e.to));
+        }
+        reachable
+    }
+
+    // The graph in the DOT format, every block lists its statements.
+    // Blocks which can't be reached are grey.
+    pub fn to_dot(
+        &self,
+        name: &str,
+        tokens: &[Token],
+        labels: &HashMap<String, usize>,
+        dialect: &Dialect,
+        numerals: &Numerals,
+    ) -> String {
+        let mut out: String = format!("digraph \"{}\" {{\n", escape(name));
+        out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        let reachable: Vec<bool> = self.reachable();
+        for (index, block) in self.blocks.iter().enumerate() {
+            // `\l` ends a left aligned line
+            let statements: String = (block.start..block.end)
+                .map(|ip| format!("{:>4}  {}\\l", ip, escape(&decode(ip, &tokens[ip], labels, dialect, numerals))))
+                .collect();
+            let style: &str = if reachable[index] { "" } else { ", color=\"grey\", fontcolor=\"grey\"" };
+            out.push_str(&format!("    b{} [label=\"{}\"{}];\n", index, statements, style));
+        }
+        for edge in &self.edges {
+            let style: &str = match edge.kind {
+                EdgeKind::FallThrough => "",
+                EdgeKind::Jump => " [label=\"jump\", color=\"blue\", fontcolor=\"blue\"]",
+            };
+            out.push_str(&format!("    b{} -> b{}{};\n", edge.from, edge.to, style));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Prints or writes the graph, returns the exit status
+pub fn run_cfg(options: &CfgOptions) -> i32 {
+    match cfg(options) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            1
+        }
+    }
+}
+
+fn cfg(options: &CfgOptions) -> Result<(), String> {
+    let dialect: Dialect = Dialect::load_or_standard(options.dialect.as_deref())?;
+    let LoadedProgram { tokens, is_valid_code, header, .. } = load_program(&options.file, &dialect)?;
+    if !is_valid_code {
+        return Err(String::from("The graph can't be built as a result of the above errors."));
+    }
+    let numerals: &Numerals = header.numbers.unwrap_or(options.numbers).numerals();
+    let labels: HashMap<String, usize> = create_labels(tokens.clone());
+    let name: String = std::path::Path::new(&options.file)
+        .file_stem()
+        .map_or_else(|| options.file.clone(), |n| n.to_string_lossy().to_string());
+    let dot: String = Cfg::build(&tokens, &labels).to_dot(&name, &tokens, &labels, &dialect, numerals);
+    match &options.output {
+        Some(path) => fs::write(path, dot).map_err(|e| format!("Couldn't write `{}`: {}", path, e)),
+        None => {
+            print!("{}", dot);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_mt_code;
+
+    fn build(code: &str) -> (Vec<Token>, HashMap<String, usize>, Cfg) {
+        let (tokens, _) = compile_mt_code(code, &Dialect::standard());
+        let labels = create_labels(tokens.clone());
+        let cfg = Cfg::build(&tokens, &labels);
+        (tokens, labels, cfg)
+    }
+
+    #[test]
+    fn blocks_and_edges() {
+        // 0-2 start, 3-5 loop, 6 after the loop, 7 exit, 8 the implicit exit
+        let (_, _, cfg) = build(
+            "summ Zero. ones One. maxi Four. labels. Ad summ ones summ. Lower summ maxi labels. P summ. Goodbye.",
+        );
+        let ranges: Vec<(usize, usize, Option<&str>)> =
+            cfg.blocks.iter().map(|b| (b.start, b.end, b.label.as_deref())).collect();
+        assert_eq!(ranges, vec![(0, 3, None), (3, 6, Some("labels")), (6, 8, None), (8, 9, None)]);
+        assert_eq!(
+            cfg.edges,
+            vec![
+                Edge { from: 0, to: 1, kind: EdgeKind::FallThrough },
+                Edge { from: 1, to: 1, kind: EdgeKind::Jump },
+                Edge { from: 1, to: 2, kind: EdgeKind::FallThrough },
+            ]
+        );
+        assert_eq!(cfg.block_of(4), 1);
+        assert_eq!(cfg.predecessors(1).count(), 2);
+        // The implicit exit after `Goodbye` can't be reached
+        assert_eq!(cfg.reachable(), vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn line_reads_jump_at_the_end_of_the_file() {
+        let (tokens, labels, cfg) = build("path data. readln. linefile path line finish. P line. Equal ones ones readln. finish.");
+        let edges: Vec<(usize, usize, EdgeKind)> = cfg.edges.iter().map(|e| (e.from, e.to, e.kind)).collect();
+        assert_eq!(
+            edges,
+            vec![
+                (0, 1, EdgeKind::FallThrough),
+                (1, 3, EdgeKind::Jump),
+                (1, 2, EdgeKind::FallThrough),
+                (2, 1, EdgeKind::Jump),
+                (2, 3, EdgeKind::FallThrough),
+            ]
+        );
+
+        let numerals = Language::English.numerals();
+        let dot = cfg.to_dot("lines", &tokens, &labels, &Dialect::standard(), numerals);
+        assert!(dot.starts_with("digraph \"lines\" {\n"), "{}", dot);
+        assert!(dot.contains("    b2 [label=\"   3  PRINT line\\l   4  BRANCH_EQ ones ones -> readln@1\\l\"];\n"), "{}", dot);
+        assert!(dot.contains("    b1 -> b3 [label=\"jump\", color=\"blue\", fontcolor=\"blue\"];\n"), "{}", dot);
+        assert!(dot.ends_with("    b2 -> b3;\n}\n"), "{}", dot);
+    }
+}
@@ -0,0 +1,146 @@
+// The interpreter talks to the outside world only through a `Console`.
+// This way `execute_code_tokens` can run in a terminal, inside tests or embedded in
+// some other program (a GUI, a server, ...).
+
+#[cfg(test)]
+use std::collections::VecDeque;
+use std::io::prelude::*;
+
+use crate::InputError;
+
+pub trait Console {
+    // Writes `text` as is (no newline is added).
+    fn write(&mut self, text: &str);
+
+    // Reads one line without the trailing newline.
+    // Returns `None` once there is nothing left to read.
+    fn read_line(&mut self) -> Result<Option<String>, InputError>;
+}
+
+// Strips "\n" or "\r\n" from the end of a line.
+fn strip_newline(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    line
+}
+
+// Reads from stdin and writes to stdout.
+pub struct StdConsole;
+
+impl Console for StdConsole {
+    fn write(&mut self, text: &str) {
+        print!("{}", text);
+        // Prompts don't end in a newline, so they have to be flushed by hand
+        let _ = std::io::stdout().flush();
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, InputError> {
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(strip_newline(line))),
+            Err(_) => Err(InputError::ReadFailed),
+        }
+    }
+}
+
+// Reads from a fixed input string and collects everything written into `output`.
+#[cfg(test)]
+#[derive(Default)]
+pub struct BufferConsole {
+    input: VecDeque<String>,
+    pub output: String,
+}
+
+#[cfg(test)]
+impl BufferConsole {
+    pub fn new(input: &str) -> BufferConsole {
+        BufferConsole {
+            input: input.lines().map(String::from).collect(),
+            output: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Console for BufferConsole {
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, InputError> {
+        Ok(self.input.pop_front())
+    }
+}
+
+// Answers every read by calling `respond` with everything that was written since the
+// previous read, so the answer can depend on what the program just printed.
+// Returning `None` from `respond` means end of input.
+#[cfg(test)]
+pub struct ScriptedConsole<F: FnMut(&str) -> Option<String>> {
+    respond: F,
+    pending: String,
+    pub output: String,
+}
+
+#[cfg(test)]
+impl<F: FnMut(&str) -> Option<String>> ScriptedConsole<F> {
+    pub fn new(respond: F) -> ScriptedConsole<F> {
+        ScriptedConsole {
+            respond,
+            pending: String::new(),
+            output: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl<F: FnMut(&str) -> Option<String>> Console for ScriptedConsole<F> {
+    fn write(&mut self, text: &str) {
+        self.pending.push_str(text);
+        self.output.push_str(text);
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, InputError> {
+        let answer = (self.respond)(&self.pending);
+        self.pending.clear();
+        Ok(answer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_console() {
+        let mut console = BufferConsole::new("first\r\nsecond\n");
+        assert_eq!(console.read_line().unwrap(), Some(String::from("first")));
+        assert_eq!(console.read_line().unwrap(), Some(String::from("second")));
+        assert_eq!(console.read_line().unwrap(), None);
+
+        console.write("a");
+        console.write("b\n");
+        assert_eq!(console.output, "ab\n");
+    }
+
+    #[test]
+    fn scripted_console() {
+        let mut console = ScriptedConsole::new(|written: &str| {
+            if written.is_empty() {
+                None
+            } else {
+                Some(written.len().to_string())
+            }
+        });
+        console.write("four");
+        assert_eq!(console.read_line().unwrap(), Some(String::from("4")));
+        // Nothing written since the last read
+        assert_eq!(console.read_line().unwrap(), None);
+        assert_eq!(console.output, "four");
+    }
+}
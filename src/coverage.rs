@@ -0,0 +1,286 @@
+// `motts --coverage prog.mt`: which statements ran and which way the branches went.
+//
+// After the program, a summary is printed to stderr with every statement which never ran and
+// every branch which was never taken (or never fell through). `--coverage-lcov <file>` writes
+// the same in the lcov format, which tools like `genhtml` or editor plugins show in the source.
+//
+// Labels and the implicit exit at the end aren't counted: a jump goes past its label, so labels
+// which are only jumped to would never look covered.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use colored::*;
+
+use crate::console::StdConsole;
+use crate::dialect::Dialect;
+use crate::disasm::decode;
+use crate::modules::SourceLocation;
+use crate::numerals::Numerals;
+use crate::{execute_observed, print_runtime_error, ExecutionConfig, Observer, Operation, Token, Variable};
+
+pub struct Coverage {
+    counts: Vec<u64>,
+    // Per branch statement: how often it jumped and how often it didn't
+    taken: Vec<u64>,
+    not_taken: Vec<u64>,
+    // The branch which ran last, its outcome is known once the next statement starts
+    pending_branch: Option<usize>,
+    is_branch: Vec<bool>,
+}
+
+// What the coverage is about
+pub struct Program<'a> {
+    pub tokens: &'a [Token],
+    pub locations: &'a [Option<SourceLocation>],
+    pub labels: &'a HashMap<String, usize>,
+    pub dialect: &'a Dialect,
+    pub numerals: &'a Numerals,
+}
+
+impl Program<'_> {
+    // Statements which are counted
+    fn counted(&self) -> impl Iterator<Item = (usize, &Token, &SourceLocation)> {
+        self.tokens
+            .iter()
+            .zip(self.locations)
+            .enumerate()
+            .filter(|(_, (t, _))| t.op != Operation::Label)
+            .filter_map(|(ip, (t, location))| Some((ip, t, location.as_ref()?)))
+    }
+
+    fn describe(&self, ip: usize, location: &SourceLocation) -> String {
+        format!(
+            "{}:{}  {}",
+            location.file.display(),
+            location.line,
+            decode(ip, &self.tokens[ip], self.labels, self.dialect, self.numerals)
+        )
+    }
+}
+
+impl Coverage {
+    pub fn new(tokens: &[Token]) -> Coverage {
+        Coverage {
+            counts: vec![0; tokens.len()],
+            taken: vec![0; tokens.len()],
+            not_taken: vec![0; tokens.len()],
+            pending_branch: None,
+            is_branch: tokens.iter().map(|t| t.op == Operation::Branch).collect(),
+        }
+    }
+
+    fn percent(part: usize, total: usize) -> String {
+        if total == 0 {
+            String::from("100.0%")
+        } else {
+            format!("{:.1}%", part as f64 / total as f64 * 100.0)
+        }
+    }
+
+    // The summary printed after the program
+    pub fn report(&self, program: &Program) -> String {
+        let mut statements: usize = 0;
+        let mut covered: usize = 0;
+        let mut outcomes: usize = 0;
+        let mut covered_outcomes: usize = 0;
+        let mut missing = String::new();
+        for (ip, t, location) in program.counted() {
+            statements += 1;
+            if self.counts[ip] == 0 {
+                missing.push_str(&format!("  {:<20}{}\n", "never ran:", program.describe(ip, location)));
+                continue;
+            }
+            covered += 1;
+            if t.op != Operation::Branch {
+                continue;
+            }
+            outcomes += 2;
+            covered_outcomes += (self.taken[ip] > 0) as usize + (self.not_taken[ip] > 0) as usize;
+            if self.taken[ip] == 0 {
+                missing.push_str(&format!("  {:<20}{}\n", "never jumped:", program.describe(ip, location)));
+            }
+            if self.not_taken[ip] == 0 {
+                missing.push_str(&format!("  {:<20}{}\n", "never fell through:", program.describe(ip, location)));
+            }
+        }
+        // Branches which never ran have two uncovered outcomes as well
+        outcomes += program
+            .counted()
+            .filter(|(ip, t, _)| t.op == Operation::Branch && self.counts[*ip] == 0)
+            .count()
+            * 2;
+
+        format!(
+            "Coverage: {} of {} statements ({}), {} of {} branch outcomes ({})\n{}",
+            covered,
+            statements,
+            Coverage::percent(covered, statements),
+            covered_outcomes,
+            outcomes,
+            Coverage::percent(covered_outcomes, outcomes),
+            missing
+        )
+    }
+
+    // The coverage in the lcov tracefile format, one record per source file
+    pub fn lcov(&self, program: &Program) -> String {
+        // Per file and line: how often the line ran and its branches (statement index, counts)
+        type Branches = Vec<(usize, Option<(u64, u64)>)>;
+        let mut files: BTreeMap<PathBuf, BTreeMap<usize, (u64, Branches)>> = BTreeMap::new();
+        for (ip, t, location) in program.counted() {
+            let line = files
+                .entry(location.file.clone())
+                .or_default()
+                .entry(location.line)
+                .or_insert((u64::MAX, vec![]));
+            // A line only counts as covered if all of its statements ran
+            line.0 = line.0.min(self.counts[ip]);
+            if t.op == Operation::Branch {
+                let outcome = (self.counts[ip] > 0).then_some((self.taken[ip], self.not_taken[ip]));
+                line.1.push((ip, outcome));
+            }
+        }
+
+        let mut out = String::new();
+        for (file, lines) in files {
+            out.push_str(&format!("TN:\nSF:{}\n", file.display()));
+            let (mut branches, mut branches_hit) = (0, 0);
+            for (line, (_, line_branches)) in &lines {
+                for (ip, outcome) in line_branches {
+                    // Branch 0 is the jump, branch 1 falling through, `-` if the branch never ran
+                    let counts: [String; 2] = match outcome {
+                        Some((taken, not_taken)) => [taken.to_string(), not_taken.to_string()],
+                        None => [String::from("-"), String::from("-")],
+                    };
+                    for (branch, count) in counts.iter().enumerate() {
+                        out.push_str(&format!("BRDA:{},{},{},{}\n", line, ip, branch, count));
+                        branches += 1;
+                        branches_hit += (count != "-" && count != "0") as usize;
+                    }
+                }
+            }
+            out.push_str(&format!("BRF:{}\nBRH:{}\n", branches, branches_hit));
+            for (line, (count, _)) in &lines {
+                out.push_str(&format!("DA:{},{}\n", line, count));
+            }
+            let lines_hit: usize = lines.values().filter(|(count, _)| *count > 0).count();
+            out.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), lines_hit));
+        }
+        out
+    }
+}
+
+impl Observer for Coverage {
+    fn enter(&mut self, ip: usize, _vars: &HashMap<String, Variable>) -> Result<(), String> {
+        if let Some(branch) = self.pending_branch.take() {
+            // A jump continues after the label, which can't be the statement after the branch
+            if ip == branch + 1 {
+                self.not_taken[branch] += 1;
+            } else {
+                self.taken[branch] += 1;
+            }
+        }
+        if ip < self.counts.len() {
+            self.counts[ip] += 1;
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self, ip: usize, _vars: &HashMap<String, Variable>) -> Result<(), String> {
+        if self.is_branch.get(ip) == Some(&true) {
+            self.pending_branch = Some(ip);
+        }
+        Ok(())
+    }
+}
+
+// Runs the program and prints the summary and/or writes the lcov file, returns the exit status
+pub fn run_with_coverage(program: &Program, config: &ExecutionConfig, summary: bool, lcov: Option<&str>) -> i32 {
+    let mut coverage = Coverage::new(program.tokens);
+    let result = execute_observed(
+        program.tokens.to_vec(),
+        program.labels.clone(),
+        config,
+        &mut StdConsole,
+        &mut coverage,
+    );
+    let mut status: i32 = match result {
+        Ok(status) => status,
+        Err(e) => {
+            print_runtime_error(&e);
+            1
+        }
+    };
+    // Like the profile, the summary goes to stderr
+    if summary {
+        eprint!("{}", coverage.report(program));
+    }
+    if let Some(path) = lcov {
+        if let Err(e) = std::fs::write(path, coverage.lcov(program)) {
+            println!("{} Couldn't write `{}`: {}", "Error:".red(), path, e);
+            status = 1;
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+    use crate::{compile_mt_code, create_labels, execute_observed, ExecutionConfig};
+
+    #[test]
+    fn counts_statements_and_branch_outcomes() {
+        let dialect = Dialect::standard();
+        // Line 2 never runs, the first branch always jumps, the second one does both
+        let code = "summ Zero. ones One. maxi Four. Equal ones ones labels.\n\
+                    P ones.\n\
+                    labels.\n\
+                    Ad summ ones summ. Lower summ maxi labels. P summ.";
+        let (tokens, _) = compile_mt_code(code, &dialect);
+        let locations: Vec<Option<SourceLocation>> = [1, 1, 1, 1, 2, 3, 4, 4, 4]
+            .iter()
+            .map(|line| {
+                Some(SourceLocation {
+                    file: PathBuf::from("prog.mt"),
+                    line: *line,
+                })
+            })
+            .chain([None])
+            .collect();
+        let labels = create_labels(tokens.clone());
+        let mut coverage = Coverage::new(&tokens);
+        let status = execute_observed(
+            tokens.clone(),
+            labels.clone(),
+            &ExecutionConfig::default(),
+            &mut BufferConsole::new(""),
+            &mut coverage,
+        );
+        assert_eq!(status.unwrap(), 0);
+        assert_eq!((coverage.taken[3], coverage.not_taken[3]), (1, 0));
+        assert_eq!((coverage.taken[7], coverage.not_taken[7]), (3, 1));
+
+        let program = Program {
+            tokens: &tokens,
+            locations: &locations,
+            labels: &labels,
+            dialect: &dialect,
+            numerals: crate::numerals::Language::English.numerals(),
+        };
+        assert_eq!(
+            coverage.report(&program),
+            "Coverage: 7 of 8 statements (87.5%), 3 of 4 branch outcomes (75.0%)\n  \
+             never fell through: prog.mt:1  BRANCH_EQ ones ones -> labels@5\n  \
+             never ran:          prog.mt:2  PRINT ones\n"
+        );
+        assert_eq!(
+            coverage.lcov(&program),
+            "TN:\nSF:prog.mt\n\
+             BRDA:1,3,0,1\nBRDA:1,3,1,0\nBRDA:4,7,0,3\nBRDA:4,7,1,1\nBRF:4\nBRH:3\n\
+             DA:1,1\nDA:2,0\nDA:4,1\nLF:3\nLH:2\nend_of_record\n"
+        );
+    }
+}
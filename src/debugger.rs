@@ -0,0 +1,471 @@
+// `motts --step prog.mt`: an interactive debugger which can go back in time.
+//
+// While the program runs, the debugger keeps a history of the last statements (`--history <n>`,
+// 10000 by default): where each one was and which variables it changed, with their old values.
+// Going back doesn't run anything again, the variables are shown like they were back then by
+// undoing the changes. Going forward from the past replays the changes until the present is
+// reached, after that statements are run again.
+//
+// Once the program has ended, the history can still be looked at until `quit`.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::console::{Console, StdConsole};
+use crate::dialect::Dialect;
+use crate::disasm::decode;
+use crate::numerals::Numerals;
+use crate::trace::same_value;
+use crate::{execute_observed, print_runtime_error, ExecutionConfig, Observer, RuntimeError, Token, Type, Variable};
+
+pub const DEFAULT_HISTORY: usize = 10_000;
+
+static HELP: &str = "\
+step [n]            run the next statement (n statements)
+back [n]            go back one statement (n statements)
+continue            run until a breakpoint or the end
+break <idx|label>   stop before the statement
+delete <idx|label>  remove the breakpoint
+vars [name]         show the variables (or one of them)
+last-write <name>   go back to the statement which last changed the variable
+path [n]            show the last statements which ran before this one (10 by default)
+list                show the statements around this one
+quit                stop the program
+";
+
+// A statement which ran
+struct Step {
+    ip: usize,
+    // The variables it changed: name, value before (`None` if it was created), value after
+    changes: Vec<(String, Option<Type>, Type)>,
+}
+
+// The message of the error which stops the program on `quit`
+static QUIT: &str = "Stopped by the debugger.";
+
+pub struct Debugger<'a> {
+    tokens: &'a [Token],
+    labels: &'a HashMap<String, usize>,
+    dialect: &'a Dialect,
+    numerals: &'a Numerals,
+    // Where the commands are read from and everything is shown
+    io: &'a mut dyn Console,
+
+    history: VecDeque<Step>,
+    capacity: usize,
+    // The variables and the statement about to run right now
+    present: HashMap<String, Type>,
+    present_ip: usize,
+    started: bool,
+    // How many statements the user went back from the present
+    back: usize,
+    breakpoints: HashSet<usize>,
+    // Statements to run before asking again, `None` for `continue`
+    steps_left: Option<usize>,
+    // Nothing to read anymore, the program runs to its end without asking
+    out_of_commands: bool,
+    ended: bool,
+}
+
+// What a command asks the debugger to do
+enum Action {
+    Stay,
+    Run,
+    Quit,
+}
+
+impl Debugger<'_> {
+    pub fn new<'a>(
+        tokens: &'a [Token],
+        labels: &'a HashMap<String, usize>,
+        dialect: &'a Dialect,
+        numerals: &'a Numerals,
+        io: &'a mut dyn Console,
+        capacity: usize,
+    ) -> Debugger<'a> {
+        Debugger {
+            tokens,
+            labels,
+            dialect,
+            numerals,
+            io,
+            history: VecDeque::new(),
+            capacity: capacity.max(1),
+            present: HashMap::new(),
+            present_ip: 0,
+            started: false,
+            back: 0,
+            breakpoints: HashSet::new(),
+            steps_left: Some(0),
+            out_of_commands: false,
+            ended: false,
+        }
+    }
+
+    fn statement(&self, ip: usize) -> String {
+        match self.tokens.get(ip) {
+            Some(t) => format!("{:>4}  {}", ip, decode(ip, t, self.labels, self.dialect, self.numerals)),
+            None => format!("{:>4}  (end of the program)", ip),
+        }
+    }
+
+    // The statement about to run at the viewed time
+    fn view_ip(&self) -> usize {
+        match self.back {
+            0 => self.present_ip,
+            n => self.history[self.history.len() - n].ip,
+        }
+    }
+
+    // The variables at the viewed time
+    fn view_vars(&self) -> HashMap<String, Type> {
+        let mut vars: HashMap<String, Type> = self.present.clone();
+        for step in self.history.iter().rev().take(self.back) {
+            for (name, before, _) in &step.changes {
+                match before {
+                    Some(n) => vars.insert(name.clone(), n.clone()),
+                    None => vars.remove(name),
+                };
+            }
+        }
+        vars
+    }
+
+    fn show_location(&mut self) {
+        let mut line: String = format!("> {}", self.statement(self.view_ip()));
+        if self.back > 0 {
+            line.push_str(&format!("    ({} back)", self.back));
+        }
+        self.io.write(&format!("{}\n", line));
+    }
+
+    // Asks for commands until one of them continues the program
+    fn prompt(&mut self) -> Action {
+        self.show_location();
+        loop {
+            self.io.write("(motts) ");
+            let line: String = match self.io.read_line() {
+                Ok(Some(n)) => n,
+                _ => {
+                    self.out_of_commands = true;
+                    return Action::Run;
+                }
+            };
+            match self.command(line.trim()) {
+                Action::Stay => (),
+                action => return action,
+            }
+        }
+    }
+
+    fn command(&mut self, line: &str) -> Action {
+        let mut words = line.split_whitespace();
+        let command: &str = words.next().unwrap_or("");
+        let arg: Option<&str> = words.next();
+        let count: usize = match arg.map(|n| n.parse::<usize>()) {
+            Some(Ok(n)) => n,
+            _ => 1,
+        };
+        match command {
+            "" => (),
+            "s" | "step" => return self.step(count),
+            "b" | "back" => {
+                let possible: usize = self.history.len() - self.back;
+                if possible < count {
+                    self.io.write(&format!(
+                        "The history only goes back {} statements (see `--history`).\n",
+                        self.history.len()
+                    ));
+                }
+                self.back += count.min(possible);
+                self.show_location();
+            }
+            "c" | "continue" => {
+                if self.ended {
+                    self.io.write("The program has ended.\n");
+                } else {
+                    self.back = 0;
+                    self.steps_left = None;
+                    return Action::Run;
+                }
+            }
+            "br" | "break" | "d" | "delete" => match arg.and_then(|n| self.find_statement(n)) {
+                Some(ip) => {
+                    if command.starts_with('b') {
+                        self.breakpoints.insert(ip);
+                        self.io.write(&format!("Breakpoint at {}\n", self.statement(ip).trim_start()));
+                    } else if self.breakpoints.remove(&ip) {
+                        self.io.write(&format!("Removed the breakpoint at {}\n", ip));
+                    }
+                }
+                None => self.io.write("Expected the index of a statement or a label.\n"),
+            },
+            "v" | "vars" => self.show_vars(arg),
+            "lw" | "last-write" => match arg {
+                Some(name) => self.last_write(name),
+                None => self.io.write("Expected the name of a variable.\n"),
+            },
+            "p" | "path" => self.show_path(arg.and_then(|n| n.parse().ok()).unwrap_or(10)),
+            "l" | "list" => {
+                let ip: usize = self.view_ip();
+                for n in ip.saturating_sub(3)..(ip + 4).min(self.tokens.len()) {
+                    let marker: &str = if n == ip { ">" } else { " " };
+                    self.io.write(&format!("{} {}\n", marker, self.statement(n)));
+                }
+            }
+            "q" | "quit" => return Action::Quit,
+            "h" | "help" => self.io.write(HELP),
+            _ => self.io.write(&format!("Unknown command `{}`, see `help`.\n", command)),
+        }
+        Action::Stay
+    }
+
+    // Replays the history first, then runs statements
+    fn step(&mut self, count: usize) -> Action {
+        let replayed: usize = count.min(self.back);
+        self.back -= replayed;
+        let left: usize = count - replayed;
+        if left == 0 {
+            self.show_location();
+            return Action::Stay;
+        }
+        if self.ended {
+            self.show_location();
+            self.io.write("The program has ended.\n");
+            return Action::Stay;
+        }
+        self.steps_left = Some(left - 1);
+        Action::Run
+    }
+
+    fn find_statement(&self, arg: &str) -> Option<usize> {
+        match arg.parse::<usize>() {
+            Ok(n) if n < self.tokens.len() => Some(n),
+            Ok(_) => None,
+            Err(_) => self.labels.get(arg).copied(),
+        }
+    }
+
+    fn show_vars(&mut self, name: Option<&str>) {
+        let vars: HashMap<String, Type> = self.view_vars();
+        let mut names: Vec<&String> = match name {
+            Some(n) => vars.keys().filter(|k| k.as_str() == n).collect(),
+            None => vars.keys().collect(),
+        };
+        if names.is_empty() {
+            self.io.write("No such variable (yet).\n");
+        }
+        names.sort();
+        let mut out = String::new();
+        for name in names {
+            out.push_str(&format!("  {} = {}\n", name, show_value(&vars[name])));
+        }
+        self.io.write(&out);
+    }
+
+    fn last_write(&mut self, name: &str) {
+        let end: usize = self.history.len() - self.back;
+        let found = (0..end).rev().find_map(|n| {
+            let change = self.history[n].changes.iter().find(|c| c.0 == name)?;
+            Some((n, change.1.clone(), change.2.clone()))
+        });
+        match found {
+            Some((index, before, after)) => {
+                self.back = self.history.len() - index;
+                let before: String = before.map_or_else(|| String::from("nothing"), |n| show_value(&n));
+                self.io.write(&format!(
+                    "`{}` was changed from {} to {} here:\n",
+                    name,
+                    before,
+                    show_value(&after)
+                ));
+                self.show_location();
+            }
+            None => self.io.write(&format!("`{}` wasn't changed in the history.\n", name)),
+        }
+    }
+
+    // The statements which ran before the viewed one, with the jumps which led there
+    fn show_path(&mut self, count: usize) {
+        let end: usize = self.history.len() - self.back;
+        let mut ips: Vec<usize> = self.history.range(end.saturating_sub(count)..end).map(|s| s.ip).collect();
+        ips.push(self.view_ip());
+        let mut out = String::new();
+        for (i, ip) in ips.iter().enumerate() {
+            let marker: &str = if i == ips.len() - 1 { ">" } else { " " };
+            out.push_str(&format!("{} {}\n", marker, self.statement(*ip)));
+            if let Some(next) = ips.get(i + 1) {
+                if *next != ip + 1 {
+                    out.push_str(&format!("        (jumped to {})\n", next));
+                }
+            }
+        }
+        self.io.write(&out);
+    }
+
+    // Called once the program stopped, the history can be looked at until `quit`
+    pub fn finish(&mut self, result: &Result<i32, RuntimeError>) {
+        self.ended = true;
+        self.back = 0;
+        match result {
+            Ok(n) => self.io.write(&format!("The program exited with {}.\n", n)),
+            Err(e) if e.msg == QUIT => return,
+            Err(e) => self.io.write(&format!("The program stopped with `{}` on token {}.\n", e.msg, e.token_num)),
+        }
+        while !self.out_of_commands {
+            if let Action::Run | Action::Quit = self.prompt() {
+                return;
+            }
+        }
+    }
+}
+
+fn show_value(value: &Type) -> String {
+    match value {
+        Type::Number(n) => n.to_string(),
+        Type::String(s) => format!("{:?}", s),
+    }
+}
+
+impl Observer for Debugger<'_> {
+    fn enter(&mut self, ip: usize, vars: &HashMap<String, Variable>) -> Result<(), String> {
+        if !self.started {
+            self.started = true;
+            self.present = vars.iter().map(|(k, v)| (k.clone(), v.data.clone())).collect();
+        }
+        self.present_ip = ip;
+        if self.out_of_commands {
+            return Ok(());
+        }
+
+        if self.breakpoints.contains(&ip) && self.steps_left != Some(0) {
+            self.io.write("Breakpoint reached.\n");
+            self.steps_left = Some(0);
+        }
+        match self.steps_left {
+            Some(0) => (),
+            Some(n) => {
+                self.steps_left = Some(n - 1);
+                return Ok(());
+            }
+            None => return Ok(()),
+        }
+        match self.prompt() {
+            Action::Quit => Err(String::from(QUIT)),
+            _ => Ok(()),
+        }
+    }
+
+    fn leave(&mut self, ip: usize, vars: &HashMap<String, Variable>) -> Result<(), String> {
+        let mut changes: Vec<(String, Option<Type>, Type)> = vars
+            .iter()
+            .filter(|(name, v)| !self.present.get(*name).is_some_and(|old| same_value(old, &v.data)))
+            .map(|(name, v)| (name.clone(), self.present.get(name).cloned(), v.data.clone()))
+            .collect();
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, _, after) in &changes {
+            self.present.insert(name.clone(), after.clone());
+        }
+        self.history.push_back(Step { ip, changes });
+        if self.history.len() > self.capacity {
+            self.history.pop_front();
+        }
+        Ok(())
+    }
+}
+
+// Runs the program in the debugger, returns the exit status
+pub fn run_debugger(
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+    capacity: usize,
+) -> i32 {
+    let mut io = StdConsole;
+    io.write("Debugging, `help` lists the commands.\n");
+    let mut debugger = Debugger::new(&tokens, &labels, &config.dialect, config.numbers.numerals(), &mut io, capacity);
+    let result = execute_observed(tokens.clone(), labels.clone(), config, &mut StdConsole, &mut debugger);
+    debugger.finish(&result);
+    match result {
+        Ok(status) => status,
+        Err(e) => {
+            if e.msg != QUIT {
+                print_runtime_error(&e);
+            }
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+    use crate::{compile_mt_code, create_labels};
+
+    // Runs the program with the commands, returns what the debugger showed
+    fn debug(code: &str, commands: &str, capacity: usize) -> String {
+        let dialect = Dialect::standard();
+        let (tokens, _) = compile_mt_code(code, &dialect);
+        let labels = create_labels(tokens.clone());
+        let numerals = crate::numerals::Language::English.numerals();
+        let mut io = BufferConsole::new(commands);
+        let mut debugger = Debugger::new(&tokens, &labels, &dialect, numerals, &mut io, capacity);
+        let result = execute_observed(
+            tokens.clone(),
+            labels.clone(),
+            &ExecutionConfig::default(),
+            &mut BufferConsole::new(""),
+            &mut debugger,
+        );
+        debugger.finish(&result);
+        io.output
+    }
+
+    // A loop which counts to four, the first statement of the loop is 3
+    static LOOP: &str = "summ Zero. ones One. maxi Four. labels. Ad summ ones summ. Lower summ maxi labels. P summ.";
+
+    #[test]
+    fn steps_back_and_forth() {
+        let out = debug(LOOP, "step 5\nvars summ\nback 2\nvars summ\nstep\nstep 2\nvars summ\nquit\n", 100);
+        let expected = "\
+>    0  VAR summ = 0
+(motts) >    5  BRANCH_LT summ maxi -> labels@3
+(motts)   summ = 1
+(motts) >    3  LABEL labels    (2 back)
+(motts)   summ = 0
+(motts) >    4  ADD summ ones -> summ    (1 back)
+(motts) >    4  ADD summ ones -> summ
+(motts)   summ = 1
+(motts) ";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn finds_writes_and_paths_after_the_end() {
+        let out = debug(LOOP, "continue\nlast-write summ\npath 3\nback 100\nquit\n", 6);
+        let expected = "\
+>    0  VAR summ = 0
+(motts) The program exited with 0.
+>    7  EXIT
+(motts) `summ` was changed from 3 to 4 here:
+>    4  ADD summ ones -> summ    (3 back)
+(motts)      5  BRANCH_LT summ maxi -> labels@3
+        (jumped to 4)
+     4  ADD summ ones -> summ
+     5  BRANCH_LT summ maxi -> labels@3
+        (jumped to 4)
+>    4  ADD summ ones -> summ
+(motts) The history only goes back 6 statements (see `--history`).
+>    5  BRANCH_LT summ maxi -> labels@3    (6 back)
+(motts) ";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn stops_at_breakpoints() {
+        let out = debug(LOOP, "break 6\ncontinue\nvars summ\nquit\n", 100);
+        assert!(out.contains("Breakpoint reached.\n>    6  PRINT summ\n(motts)   summ = 4\n"), "{}", out);
+        // Running out of commands runs the program to its end
+        assert!(debug(LOOP, "", 100).ends_with("The program exited with 0.\n"));
+    }
+}
@@ -0,0 +1,290 @@
+// A dialect decides which word (length + case) stands for which operation, what the first
+// letter of a branch means and which variables are predefined.
+// Besides the standard dialect, dialects can be loaded from a file (`motts --dialect file`):
+//
+//   # Lines starting with '#' are comments
+//   base standard                 start from the standard dialect (otherwise start empty)
+//   op 1 upper Add                one letter uppercase words are additions now
+//   op 2 any none                 two letter words don't mean anything anymore
+//   branch q Equal                branches starting with 'q' jump if equal
+//   branch e none                 ... and those starting with 'e' are invalid
+//   var tabb string \t            predefined string (escapes: \n \t \s for space, \\)
+//   var hund number 100           predefined number
+//   novar dott                    removes a predefined variable
+
+use std::collections::HashMap;
+
+use crate::{Case, Operation, TokenMapIndex, Type};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BranchCondition {
+    Equal,
+    Less,
+    Greater,
+}
+
+#[derive(Clone)]
+pub struct Dialect {
+    operations: HashMap<TokenMapIndex, Operation>,
+    branch_conditions: HashMap<char, BranchCondition>,
+    pub predefined_variables: Vec<(String, Type)>,
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect::standard()
+    }
+}
+
+impl Dialect {
+    pub fn standard() -> Dialect {
+        Dialect {
+            operations: HashMap::from([
+                (TokenMapIndex(1, Case::Upper), Operation::Print),
+                (TokenMapIndex(1, Case::Lower), Operation::Input),
+                (TokenMapIndex(2, Case::Upper), Operation::Add),
+                (TokenMapIndex(2, Case::Lower), Operation::Sub),
+                (TokenMapIndex(3, Case::Upper), Operation::Mul),
+                (TokenMapIndex(3, Case::Lower), Operation::Div),
+                (TokenMapIndex(4, Case::Upper), Operation::Var),
+                (TokenMapIndex(4, Case::Lower), Operation::Var),
+                (TokenMapIndex(5, Case::Upper), Operation::Branch),
+                (TokenMapIndex(5, Case::Lower), Operation::Branch),
+                (TokenMapIndex(6, Case::Upper), Operation::Label),
+                (TokenMapIndex(6, Case::Lower), Operation::Label),
+                (TokenMapIndex(7, Case::Upper), Operation::Exit),
+                (TokenMapIndex(7, Case::Lower), Operation::Env),
+                (TokenMapIndex(8, Case::Upper), Operation::File),
+                (TokenMapIndex(8, Case::Lower), Operation::File),
+                (TokenMapIndex(9, Case::Upper), Operation::Include),
+                (TokenMapIndex(9, Case::Lower), Operation::Include),
+            ]),
+            branch_conditions: HashMap::from([
+                ('e', BranchCondition::Equal),
+                ('l', BranchCondition::Less),
+                ('g', BranchCondition::Greater),
+            ]),
+            predefined_variables: vec![
+                (String::from("newl"), Type::String(String::from("\n"))),
+                (String::from("spce"), Type::String(String::from(" "))),
+                (String::from("dott"), Type::String(String::from("."))),
+            ],
+        }
+    }
+
+    fn empty() -> Dialect {
+        Dialect {
+            operations: HashMap::new(),
+            branch_conditions: HashMap::new(),
+            predefined_variables: vec![],
+        }
+    }
+
+    pub fn operation(&self, length: usize, case: Case) -> Option<Operation> {
+        self.operations.get(&TokenMapIndex(length, case)).copied()
+    }
+
+    // The letter is compared case insensitive
+    pub fn branch_condition(&self, first_letter: char) -> Option<BranchCondition> {
+        self.branch_conditions
+            .get(&first_letter.to_ascii_lowercase())
+            .copied()
+    }
+
+    // Parses a dialect definition (see the top of this file), errors contain the line number
+    pub fn parse(text: &str) -> Result<Dialect, String> {
+        let mut dialect = Dialect::empty();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            parse_dialect_line(line, &mut dialect)
+                .map_err(|e| format!("Dialect line {}: {}", index + 1, e))?;
+        }
+        Ok(dialect)
+    }
+
+    pub fn load(path: &str) -> Result<Dialect, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Dialect::parse(&text),
+            Err(e) => Err(format!("Couldn't read dialect `{}`: {}", path, e)),
+        }
+    }
+
+    // The dialect given on the commandline (if any), otherwise the standard one
+    pub fn load_or_standard(path: Option<&str>) -> Result<Dialect, String> {
+        match path {
+            Some(path) => Dialect::load(path),
+            None => Ok(Dialect::standard()),
+        }
+    }
+}
+
+pub fn operation_from_name(name: &str) -> Option<Operation> {
+    let op = match name.to_lowercase().as_str() {
+        "print" => Operation::Print,
+        "input" => Operation::Input,
+        "add" => Operation::Add,
+        "sub" => Operation::Sub,
+        "mul" => Operation::Mul,
+        "div" => Operation::Div,
+        "var" => Operation::Var,
+        "branch" => Operation::Branch,
+        "label" => Operation::Label,
+        "env" => Operation::Env,
+        "file" => Operation::File,
+        "include" => Operation::Include,
+        "exit" => Operation::Exit,
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('s') => result.push(' '),
+            Some('\\') => result.push('\\'),
+            _ => return Err(format!("Invalid escape in `{}`.", text)),
+        }
+    }
+    Ok(result)
+}
+
+fn parse_dialect_line(line: &str, dialect: &mut Dialect) -> Result<(), String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["base", "standard"] => {
+            *dialect = Dialect::standard();
+        }
+        ["op", length, case, op] => {
+            let length: usize = match length.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(format!("`{}` is not a word length.", length)),
+            };
+            let cases: Vec<Case> = match *case {
+                "upper" => vec![Case::Upper],
+                "lower" => vec![Case::Lower],
+                "any" => vec![Case::Upper, Case::Lower],
+                _ => return Err(format!("Case has to be upper, lower or any, not `{}`.", case)),
+            };
+            for case in cases {
+                if *op == "none" {
+                    dialect.operations.remove(&TokenMapIndex(length, case));
+                    continue;
+                }
+                match operation_from_name(op) {
+                    Some(n) => {
+                        dialect.operations.insert(TokenMapIndex(length, case), n);
+                    }
+                    None => return Err(format!("Unknown operation `{}`.", op)),
+                }
+            }
+        }
+        ["branch", letter, condition] => {
+            let mut letters = letter.chars();
+            let letter: char = match (letters.next(), letters.next()) {
+                (Some(c), None) if c.is_alphabetic() => c.to_ascii_lowercase(),
+                _ => return Err(format!("`{}` is not a single letter.", letter)),
+            };
+            let condition: BranchCondition = match condition.to_lowercase().as_str() {
+                "equal" => BranchCondition::Equal,
+                "less" => BranchCondition::Less,
+                "greater" => BranchCondition::Greater,
+                "none" => {
+                    dialect.branch_conditions.remove(&letter);
+                    return Ok(());
+                }
+                _ => return Err(format!("Unknown branch condition `{}`.", condition)),
+            };
+            dialect.branch_conditions.insert(letter, condition);
+        }
+        ["var", name, typ, value @ ..] => {
+            let data: Type = match *typ {
+                "string" => Type::String(unescape(&value.join(" "))?),
+                "number" => match value.join(" ").parse::<f64>() {
+                    Ok(n) => Type::Number(n),
+                    Err(_) => return Err(format!("`{}` is not a number.", value.join(" "))),
+                },
+                _ => return Err(format!("Variable type has to be string or number, not `{}`.", typ)),
+            };
+            dialect.predefined_variables.retain(|(n, _)| n != name);
+            dialect.predefined_variables.push((name.to_string(), data));
+        }
+        ["novar", name] => {
+            dialect.predefined_variables.retain(|(n, _)| n != name);
+        }
+        _ => return Err(format!("Couldn't understand `{}`.", line)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dialect() {
+        let dialect = Dialect::parse(
+            "
+            # A dialect where everything is shifted by one letter
+            base standard
+            op 1 any none
+            op 2 upper Print
+            op 9 lower exit
+            branch e none
+            branch q Equal
+            var tabb string a\\tb\\sc
+            var hund number 100
+            novar dott
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(dialect.operation(1, Case::Upper), None);
+        assert_eq!(dialect.operation(2, Case::Upper), Some(Operation::Print));
+        assert_eq!(dialect.operation(2, Case::Lower), Some(Operation::Sub));
+        assert_eq!(dialect.operation(9, Case::Lower), Some(Operation::Exit));
+        assert_eq!(dialect.branch_condition('E'), None);
+        assert_eq!(dialect.branch_condition('Q'), Some(BranchCondition::Equal));
+
+        let names: Vec<&str> = dialect
+            .predefined_variables
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect();
+        assert_eq!(names, vec!["newl", "spce", "tabb", "hund"]);
+        assert!(dialect.predefined_variables[2].1 == Type::String(String::from("a\tb c")));
+        assert!(dialect.predefined_variables[3].1 == Type::Number(100.0));
+    }
+
+    #[test]
+    fn dialect_errors() {
+        // Without a base, nothing is defined
+        let dialect = Dialect::parse("op 3 lower Var").unwrap();
+        assert_eq!(dialect.operation(1, Case::Upper), None);
+        assert!(dialect.predefined_variables.is_empty());
+
+        match Dialect::parse("base standard\nop 0 upper Print") {
+            Ok(_) => panic!(),
+            Err(e) => assert!(e.starts_with("Dialect line 2")),
+        }
+        assert!(Dialect::parse("op 1 sideways Print").is_err());
+        assert!(Dialect::parse("op 1 upper Teleport").is_err());
+        assert!(Dialect::parse("branch xy Equal").is_err());
+        assert!(Dialect::parse("var numb number seven").is_err());
+        assert!(Dialect::parse("var text string \\x").is_err());
+        assert!(Dialect::parse("make it easier").is_err());
+    }
+}
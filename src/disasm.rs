@@ -0,0 +1,364 @@
+// `motts disasm`: lists what every statement of a program does, one statement per line.
+//
+//   idx  line  operation                        source
+//     5     8  LABEL Loopen                     Loopen
+//     6     9  MUL inde summ -> summ            Mul inde summ summ
+//     7    10  ADD inde ones -> inde            Ad inde ones inde
+//     8    11  BRANCH_LT inde maxi -> Loopen@5  Leeps inde maxi Loopen
+//
+// Jumps show the index of the label they go to (execution continues after it),
+// statements which can't be parsed show the error instead of an operation.
+
+use std::collections::HashMap;
+use std::fs;
+
+use colored::*;
+
+use crate::dialect::{BranchCondition, Dialect};
+use crate::header::split_header;
+use crate::numerals::{Language, Numerals};
+use crate::source::{locate_statements, Statement};
+use crate::{create_labels, parse_text_number, Operation, Token};
+
+pub struct DisasmOptions {
+    pub dialect: Option<String>,
+    pub numbers: Language,
+    pub file: String,
+}
+
+// One line of the listing
+struct Row {
+    index: usize,
+    // Line in the file (starting at 1), if the statement comes from a file
+    line: Option<usize>,
+    operation: String,
+    source: String,
+}
+
+// The argument at `i`, or `?` if the statement is too short
+fn operand(t: &Token, i: usize) -> &str {
+    t.args.get(i).map_or("?", String::as_str)
+}
+
+// Arguments the operation doesn't use
+fn extra_args(t: &Token, used: usize) -> String {
+    match t.args.get(used..) {
+        Some(extra) if !extra.is_empty() => format!(" (extra: {})", extra.join(" ")),
+        _ => String::new(),
+    }
+}
+
+// The label with the index of the statement it resolves to
+fn jump_target(name: &str, labels: &HashMap<String, usize>) -> String {
+    match labels.get(name) {
+        Some(n) => format!("{}@{}", name, n),
+        None => format!("{}@?", name),
+    }
+}
+
+// Words can contain newlines and tabs (statements are only split on spaces), which would break
+// the layout of a listing
+fn escape_whitespace(text: &str) -> String {
+    text.replace('\n', "\\n").replace('\t', "\\t")
+}
+
+// What the token at `index` does, e.g. `ADD onne twwo -> rslt`
+pub fn decode(
+    index: usize,
+    t: &Token,
+    labels: &HashMap<String, usize>,
+    dialect: &Dialect,
+    numerals: &Numerals,
+) -> String {
+    escape_whitespace(&describe(index, t, labels, dialect, numerals))
+}
+
+fn describe(
+    index: usize,
+    t: &Token,
+    labels: &HashMap<String, usize>,
+    dialect: &Dialect,
+    numerals: &Numerals,
+) -> String {
+    let condition = || {
+        match t.name.chars().next().and_then(|c| dialect.branch_condition(c)) {
+            Some(BranchCondition::Equal) => "EQ",
+            Some(BranchCondition::Less) => "LT",
+            Some(BranchCondition::Greater) => "GT",
+            None => "?",
+        }
+    };
+
+    match t.op {
+        Operation::Print => format!("PRINT {}", t.args.join(" ")).trim_end().to_string(),
+        Operation::Input => {
+            let typ: &str = match t.args.first().and_then(|n| n.chars().next()) {
+                Some(c) if c.is_ascii_uppercase() => "NUMBER",
+                Some(_) => "STRING",
+                None => "?",
+            };
+            let prompt: String = t.args.get(2).map_or(String::new(), |p| format!(" {}", p));
+            format!("INPUT_{}{} -> {}{}", typ, prompt, operand(t, 1), extra_args(t, 3))
+        }
+        Operation::Add | Operation::Sub | Operation::Mul | Operation::Div => format!(
+            "{} {} {} -> {}{}",
+            format!("{:?}", t.op).to_uppercase(),
+            operand(t, 0),
+            operand(t, 1),
+            operand(t, 2),
+            extra_args(t, 3)
+        ),
+        Operation::Var => match t.args.first() {
+            Some(first) if first.starts_with(char::is_uppercase) => {
+                match parse_text_number(t.args.clone(), numerals) {
+                    Ok(n) => format!("VAR {} = {}", t.name, n),
+                    Err(e) => format!("VAR {} = <{}>", t.name, e),
+                }
+            }
+            Some(_) => format!("VAR {} = {:?}", t.name, t.args.join(" ")),
+            None => format!("VAR {} = ?", t.name),
+        },
+        Operation::Branch => format!(
+            "BRANCH_{} {} {} -> {}{}",
+            condition(),
+            operand(t, 0),
+            operand(t, 1),
+            jump_target(operand(t, 2), labels),
+            extra_args(t, 3)
+        ),
+        Operation::Label => {
+            // Jumps go to the first definition of a label
+            let duplicate: String = match labels.get(&t.name) {
+                Some(n) if *n != index => format!(" (duplicate, jumps go to @{})", n),
+                _ => String::new(),
+            };
+            format!("LABEL {}{}{}", t.name, extra_args(t, 0), duplicate)
+        }
+        Operation::Env => format!("ENV {} -> {}{}", operand(t, 0), operand(t, 1), extra_args(t, 2)),
+        Operation::File => {
+            let path: &str = operand(t, 0);
+            match t.name.chars().next().map(|c| c.to_ascii_lowercase()) {
+                Some('r') => format!("FILE_READ {} -> {}{}", path, operand(t, 1), extra_args(t, 2)),
+                Some('l') => format!(
+                    "FILE_LINE {} -> {} else {}{}",
+                    path,
+                    operand(t, 1),
+                    jump_target(operand(t, 2), labels),
+                    extra_args(t, 3)
+                ),
+                Some(letter @ ('w' | 'a')) => {
+                    let name: &str = if letter == 'w' { "FILE_WRITE" } else { "FILE_APPEND" };
+                    format!("{} {} {}", name, path, t.args.get(1..).unwrap_or_default().join(" "))
+                        .trim_end()
+                        .to_string()
+                }
+                Some('e') => format!("FILE_EXISTS {} -> {}{}", path, operand(t, 1), extra_args(t, 2)),
+                _ => format!("FILE_? {}", t.args.join(" ")),
+            }
+        }
+        Operation::Include => format!("INCLUDE {}{}", operand(t, 0), extra_args(t, 1)),
+        Operation::Exit => match t.args.first() {
+            None => String::from("EXIT"),
+            Some(first) if first.starts_with(char::is_uppercase) => match parse_text_number(t.args.clone(), numerals) {
+                Ok(n) => format!("EXIT {}", n),
+                Err(e) => format!("EXIT <{}>", e),
+            },
+            Some(first) => format!("EXIT {}{}", first, extra_args(t, 1)),
+        },
+        Operation::Invalid => String::from("INVALID"),
+    }
+}
+
+fn render(rows: &[Row]) -> String {
+    let index_width: usize = rows.iter().map(|r| r.index.to_string().len()).max().unwrap_or(0).max(3);
+    let line_width: usize = rows
+        .iter()
+        .map(|r| r.line.map_or(1, |n| n.to_string().len()))
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let operation_width: usize = rows.iter().map(|r| r.operation.chars().count()).max().unwrap_or(0).max(9);
+
+    // Tokens which are already loaded don't have a source
+    let source: &str = if rows.iter().any(|r| !r.source.is_empty()) { "source" } else { "" };
+    let mut out: String = format!(
+        "{:>iw$}  {:>lw$}  {:ow$}  {}",
+        "idx",
+        "line",
+        "operation",
+        source,
+        iw = index_width,
+        lw = line_width,
+        ow = operation_width
+    )
+    .trim_end()
+    .to_string();
+    out.push('\n');
+    for row in rows {
+        let line: String = row.line.map_or(String::from("-"), |n| n.to_string());
+        let text: String = format!(
+            "{:>iw$}  {:>lw$}  {:ow$}  {}",
+            row.index,
+            line,
+            row.operation,
+            row.source,
+            iw = index_width,
+            lw = line_width,
+            ow = operation_width
+        );
+        out.push_str(text.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+// Lists the labels with the statement they jump to
+fn label_table(labels: &HashMap<String, usize>) -> String {
+    let mut sorted: Vec<(&String, &usize)> = labels.iter().collect();
+    sorted.sort_by_key(|(name, index)| (**index, *name));
+    let mut out = String::from("\nlabels:\n");
+    for (name, index) in sorted {
+        out.push_str(&format!("  {} -> {}\n", name, index));
+    }
+    out
+}
+
+// The listing of already loaded tokens (without source lines), used by `motts --debug`
+pub fn listing(tokens: &[Token], labels: &HashMap<String, usize>, dialect: &Dialect, numerals: &Numerals) -> String {
+    let rows: Vec<Row> = tokens
+        .iter()
+        .enumerate()
+        .map(|(index, t)| Row {
+            index,
+            line: None,
+            operation: decode(index, t, labels, dialect, numerals),
+            source: String::new(),
+        })
+        .collect();
+    render(&rows)
+}
+
+// The token a statement stands for when it is run (errors become `Invalid` tokens)
+fn runtime_token(statement: &Statement) -> Token {
+    match &statement.token {
+        Ok(t) => t.clone(),
+        Err(_) => Token {
+            op: Operation::Invalid,
+            nargs: 0,
+            args: vec![],
+            name: String::from("Invalid!"),
+        },
+    }
+}
+
+// The listing of a single file
+fn disassemble(content: &str, dialect: &Dialect, numbers: Language) -> Result<String, String> {
+    let (header, code) = split_header(content)?;
+    let numerals: &Numerals = header.numbers.unwrap_or(numbers).numerals();
+    let (statements, _) = locate_statements(&code, dialect);
+    let labels: HashMap<String, usize> = create_labels(statements.iter().map(runtime_token).collect());
+
+    let mut rows: Vec<Row> = statements
+        .iter()
+        .enumerate()
+        .map(|(index, statement)| {
+            let source: String = escape_whitespace(
+                &statement
+                    .words
+                    .iter()
+                    .map(|w| w.text.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+            );
+            let operation: String = match &statement.token {
+                Ok(t) => decode(index, t, &labels, dialect, numerals),
+                Err(e) => format!("ERROR {}", e.msg),
+            };
+            Row {
+                index,
+                line: Some(statement.start().line + 1),
+                operation,
+                source,
+            }
+        })
+        .collect();
+    // Every program ends with an implicit exit
+    rows.push(Row {
+        index: rows.len(),
+        line: None,
+        operation: String::from("EXIT"),
+        source: String::from("(end of the program)"),
+    });
+
+    let mut out: String = render(&rows);
+    if !labels.is_empty() {
+        out.push_str(&label_table(&labels));
+    }
+    Ok(out)
+}
+
+// Prints the listing of the file, returns the exit status
+pub fn run_disasm(options: &DisasmOptions) -> i32 {
+    let dialect: Dialect = match Dialect::load_or_standard(options.dialect.as_deref()) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+    let content: String = match fs::read_to_string(&options.file) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} Couldn't read `{}`: {}", "Error:".red(), options.file, e);
+            return 1;
+        }
+    };
+    match disassemble(&content, &dialect, options.numbers) {
+        Ok(listing) => {
+            print!("{}", listing);
+            0
+        }
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_of_a_program() {
+        let code = "#numbers french\nsumm Un virgule cinq. text hi  there.\n\
+                    labels.\nAd summ onne rslt. Lower summ maxi labels.\n\
+                    // comment\nInvalidness a b.\nreadfile path cont. Goodbye Deux.";
+        let listing = disassemble(code, &Dialect::standard(), Language::English).unwrap();
+        let expected = "\
+idx  line  operation                             source
+  0     2  VAR summ = 1.5                        summ Un virgule cinq
+  1     2  VAR text = \"hi  there\"                text hi  there
+  2     3  LABEL labels                          labels
+  3     4  ADD summ onne -> rslt                 Ad summ onne rslt
+  4     4  BRANCH_LT summ maxi -> labels@2       Lower summ maxi labels
+  5     6  ERROR Provided Operation is invalid.  Invalidness a b
+  6     7  FILE_READ path -> cont                readfile path cont
+  7     7  EXIT 2                                Goodbye Deux
+  8     -  EXIT                                  (end of the program)
+
+labels:
+  labels -> 2
+";
+        assert_eq!(listing, expected);
+
+        let listing = disassemble("labels. labels. Equal a b nowher.", &Dialect::standard(), Language::English).unwrap();
+        assert!(listing.contains("  1     1  LABEL labels (duplicate, jumps go to @0)  labels\n"), "{}", listing);
+        assert!(listing.contains("BRANCH_EQ a b -> nowher@?"));
+
+        // A word with a line break in it stays on one line
+        let listing = disassemble("Ad onee\nonee onee.", &Dialect::standard(), Language::English).unwrap();
+        assert!(listing.contains("  0     1  ADD onee\\nonee onee -> ?  Ad onee\\nonee onee\n"), "{}", listing);
+        assert_eq!(listing.lines().count(), 3, "{}", listing);
+    }
+}
@@ -0,0 +1,1189 @@
+// `motts emit-c`: translates a mott program into a standalone C program.
+//
+// - every variable becomes a typed local (`double` or `char *`), the type is inferred from how
+//   the variable is written (a variable which is both a Number and a String can't be compiled)
+// - labels become C labels and branches `if (...) goto`
+// - errors the interpreter only finds while running (missing variables, dividing by zero, ...)
+//   are errors at the same token in the C program, with the same message
+//
+// The generated code only needs the C standard library (and `stat` for checking if a file exists):
+//   motts emit-c program.mt -o program.c && cc -O2 program.c -o program -lm
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+
+use colored::*;
+
+use crate::dialect::{BranchCondition, Dialect};
+use crate::modules::{load_program, LoadedProgram};
+use crate::numerals::{Language, Numerals};
+use crate::symbols::{label_ref, name_at, variable_refs};
+use crate::{
+    create_labels, is_interpreter_variable, parse_text_number, Operation, Token, Type,
+    ARG_COUNT_VAR_NAME, EOF_VAR_NAME, MAX_PROGRAM_ARGS,
+};
+
+pub struct EmitCOptions {
+    pub dialect: Option<String>,
+    pub numbers: Language,
+    // Where to write the C code (stdout if not given)
+    pub output: Option<String>,
+    pub file: String,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum VarType {
+    Number,
+    String,
+}
+
+// Helpers used by the generated code
+static C_RUNTIME: &str = r#"#include <ctype.h>
+#include <errno.h>
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+
+/* How often the user is asked again for a number (`motts --input-retries`) */
+#ifndef MOTT_INPUT_RETRIES
+#define MOTT_INPUT_RETRIES 0
+#endif
+
+/* Enough for every double written out without an exponent */
+#define MOTT_NUMBER_SIZE 400
+
+static void mott_error(int token, const char *msg) {
+    printf("Error: `%s` on token %d \n\n", msg, token);
+    printf("The program terminated because of the above error.\n");
+    exit(1);
+}
+
+static void *mott_alloc(void *data, size_t size) {
+    data = realloc(data, size);
+    if (data == NULL) {
+        fputs("Out of memory\n", stderr);
+        exit(1);
+    }
+    return data;
+}
+
+static char *mott_copy(const char *text) {
+    size_t length = strlen(text);
+    char *copy = mott_alloc(NULL, length + 1);
+    memcpy(copy, text, length + 1);
+    return copy;
+}
+
+/* Replaces the value of a string variable (which owns the new value afterwards) */
+static void mott_set(char **variable, char *value) {
+    free(*variable);
+    *variable = value;
+}
+
+/* Writes the number like motts does: the shortest digits which read back as the same
+   number, never with an exponent */
+static void mott_format_number(double x, char *out) {
+    char scientific[32];
+    char digits[32];
+    const char *s = scientific;
+    int precision, exponent, point, i;
+    int count = 0;
+
+    if (isnan(x)) {
+        strcpy(out, "NaN");
+        return;
+    }
+    if (isinf(x)) {
+        strcpy(out, x < 0 ? "-inf" : "inf");
+        return;
+    }
+    for (precision = 0; precision < 17; precision++) {
+        snprintf(scientific, sizeof scientific, "%.*e", precision, x);
+        if (strtod(scientific, NULL) == x) {
+            break;
+        }
+    }
+
+    /* scientific is -d.ddde+XX */
+    if (*s == '-') {
+        *out++ = '-';
+        s++;
+    }
+    for (; *s != 'e'; s++) {
+        if (*s != '.') {
+            digits[count++] = *s;
+        }
+    }
+    exponent = atoi(s + 1);
+    while (count > 1 && digits[count - 1] == '0') {
+        count--;
+    }
+
+    point = exponent + 1;
+    if (point <= 0) {
+        *out++ = '0';
+        *out++ = '.';
+        for (i = 0; i < -point; i++) {
+            *out++ = '0';
+        }
+        for (i = 0; i < count; i++) {
+            *out++ = digits[i];
+        }
+    } else {
+        for (i = 0; i < count || i < point; i++) {
+            if (i == point) {
+                *out++ = '.';
+            }
+            *out++ = i < count ? digits[i] : '0';
+        }
+    }
+    *out = '\0';
+}
+
+/* A string which is built piece by piece */
+typedef struct {
+    char *data;
+    size_t length;
+} mott_text;
+
+static void mott_append(mott_text *text, const char *part) {
+    size_t length = strlen(part);
+    text->data = mott_alloc(text->data, text->length + length + 1);
+    memcpy(text->data + text->length, part, length + 1);
+    text->length += length;
+}
+
+static void mott_append_number(mott_text *text, double x) {
+    char number[MOTT_NUMBER_SIZE];
+    mott_format_number(x, number);
+    mott_append(text, number);
+}
+
+/* Reads a line from stdin without the "\n" or "\r\n". Returns 0 at the end of the input. */
+static int mott_read_line(char **line) {
+    mott_text text = {NULL, 0};
+    char chunk[256];
+    int got_any = 0;
+
+    mott_append(&text, "");
+    while (fgets(chunk, sizeof chunk, stdin) != NULL) {
+        got_any = 1;
+        mott_append(&text, chunk);
+        if (text.data[text.length - 1] == '\n') {
+            break;
+        }
+    }
+    if (!got_any) {
+        free(text.data);
+        return 0;
+    }
+    if (text.length > 0 && text.data[text.length - 1] == '\n') {
+        text.data[--text.length] = '\0';
+        if (text.length > 0 && text.data[text.length - 1] == '\r') {
+            text.data[--text.length] = '\0';
+        }
+    }
+    *line = text.data;
+    return 1;
+}
+
+/* Parses the whole text (without the whitespace around it) as a number */
+static int mott_parse_number(const char *text, double *number) {
+    const char *start = text;
+    const char *end;
+    const char *c;
+    char *parsed_end;
+
+    while (isspace((unsigned char)*start)) {
+        start++;
+    }
+    end = start + strlen(start);
+    while (end > start && isspace((unsigned char)end[-1])) {
+        end--;
+    }
+    if (start == end) {
+        return 0;
+    }
+    /* strtod also reads hexadecimal numbers, motts doesn't */
+    for (c = start; c < end; c++) {
+        if (*c == 'x' || *c == 'X') {
+            return 0;
+        }
+    }
+    *number = strtod(start, &parsed_end);
+    return parsed_end == end;
+}
+
+static void mott_file_error(int token, const char *path) {
+    char msg[1024];
+    snprintf(msg, sizeof msg, "FileError: %s: %s", path, strerror(errno));
+    mott_error(token, msg);
+}
+
+static char *mott_read_file(int token, const char *path) {
+    FILE *file = fopen(path, "rb");
+    mott_text text = {NULL, 0};
+    char chunk[4096];
+    size_t length;
+
+    if (file == NULL) {
+        mott_file_error(token, path);
+    }
+    mott_append(&text, "");
+    while ((length = fread(chunk, 1, sizeof chunk - 1, file)) > 0) {
+        chunk[length] = '\0';
+        mott_append(&text, chunk);
+    }
+    fclose(file);
+    return text.data;
+}
+
+/* Files which are being read line by line */
+struct mott_open_file {
+    char *path;
+    char *content;
+    size_t offset;
+    struct mott_open_file *next;
+};
+static struct mott_open_file *mott_open_files = NULL;
+
+static void mott_forget_lines(const char *path) {
+    struct mott_open_file **file;
+    for (file = &mott_open_files; *file != NULL; file = &(*file)->next) {
+        if (strcmp((*file)->path, path) == 0) {
+            struct mott_open_file *old = *file;
+            *file = old->next;
+            free(old->path);
+            free(old->content);
+            free(old);
+            return;
+        }
+    }
+}
+
+/* Reads the next line of the file. Returns 0 at the end (the next read starts at the top again). */
+static int mott_next_line(int token, const char *path, char **line) {
+    struct mott_open_file *file;
+    const char *start;
+    const char *newline;
+    size_t length;
+
+    for (file = mott_open_files; file != NULL; file = file->next) {
+        if (strcmp(file->path, path) == 0) {
+            break;
+        }
+    }
+    if (file == NULL) {
+        char *content = mott_read_file(token, path);
+        file = mott_alloc(NULL, sizeof *file);
+        file->path = mott_copy(path);
+        file->content = content;
+        file->offset = 0;
+        file->next = mott_open_files;
+        mott_open_files = file;
+    }
+
+    start = file->content + file->offset;
+    if (*start == '\0') {
+        mott_forget_lines(path);
+        return 0;
+    }
+    newline = strchr(start, '\n');
+    length = newline != NULL ? (size_t)(newline - start) : strlen(start);
+    file->offset += length + (newline != NULL ? 1 : 0);
+    if (length > 0 && start[length - 1] == '\r') {
+        length--;
+    }
+    *line = mott_alloc(NULL, length + 1);
+    memcpy(*line, start, length);
+    (*line)[length] = '\0';
+    return 1;
+}
+
+static void mott_write_file(int token, const char *path, const char *content, int append) {
+    FILE *file = fopen(path, append ? "ab" : "wb");
+    if (file == NULL) {
+        mott_file_error(token, path);
+    }
+    fputs(content, file);
+    fclose(file);
+    mott_forget_lines(path);
+}
+
+static int mott_file_exists(const char *path) {
+    struct stat info;
+    return stat(path, &info) == 0;
+}
+"#;
+
+// Turns a mott name (which can contain any character but spaces and dots) into a C identifier
+fn c_identifier(prefix: &str, name: &str) -> String {
+    let mut identifier = String::from(prefix);
+    for c in name.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => identifier.push(c),
+            '_' => identifier.push_str("__"),
+            _ => {
+                let _ = write!(identifier, "_{:x}_", c as u32);
+            }
+        }
+    }
+    identifier
+}
+
+fn variable_name(name: &str) -> String {
+    c_identifier("v_", name)
+}
+
+// Whether the variable was assigned yet
+fn set_flag(name: &str) -> String {
+    c_identifier("set_", name)
+}
+
+fn label_name(name: &str) -> String {
+    c_identifier("l_", name)
+}
+
+// `{:?}` writes `inf` and `NaN`, which aren't C
+fn c_number(n: f64) -> String {
+    if n.is_nan() {
+        String::from("NAN")
+    } else if n.is_infinite() {
+        String::from(if n > 0.0 { "INFINITY" } else { "-INFINITY" })
+    } else {
+        format!("{:?}", n)
+    }
+}
+
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'\n' => literal.push_str("\\n"),
+            b'\t' => literal.push_str("\\t"),
+            // `??` could start a trigraph
+            b'?' => literal.push_str("\\?"),
+            0x20..=0x7e => literal.push(byte as char),
+            _ => {
+                let _ = write!(literal, "\\{:03o}", byte);
+            }
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn starts_uppercase(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_uppercase)
+}
+
+fn file_letter(t: &Token) -> Option<char> {
+    t.name.chars().next().map(|c| c.to_ascii_lowercase())
+}
+
+// The type a token gives the variable in the arg it writes to
+fn written_type(t: &Token) -> Option<VarType> {
+    let typ = match t.op {
+        Operation::Var => match t.args.first() {
+            Some(first) if starts_uppercase(first) => VarType::Number,
+            Some(_) => VarType::String,
+            None => return None,
+        },
+        Operation::Input => match t.args.first() {
+            Some(first) if first.starts_with(|c: char| c.is_ascii_uppercase()) => VarType::Number,
+            _ => VarType::String,
+        },
+        Operation::Add | Operation::Sub | Operation::Mul | Operation::Div => VarType::Number,
+        Operation::Env => VarType::String,
+        Operation::File if file_letter(t) == Some('e') => VarType::Number,
+        Operation::File => VarType::String,
+        _ => return None,
+    };
+    Some(typ)
+}
+
+// The type a token expects the variable read in `args[i]` to have (if it cares)
+fn read_type(t: &Token, i: usize, dialect: &Dialect) -> Option<VarType> {
+    match t.op {
+        Operation::Add | Operation::Sub | Operation::Mul | Operation::Div => Some(VarType::Number),
+        Operation::Exit => Some(VarType::Number),
+        Operation::File if i == 0 => Some(VarType::String),
+        Operation::Branch => match t.name.chars().next().and_then(|c| dialect.branch_condition(c)) {
+            Some(BranchCondition::Less) | Some(BranchCondition::Greater) => Some(VarType::Number),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn predefined_type(name: &str, dialect: &Dialect) -> Option<VarType> {
+    if name == EOF_VAR_NAME || name == ARG_COUNT_VAR_NAME {
+        return Some(VarType::Number);
+    }
+    dialect
+        .predefined_variables
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, data)| match data {
+            Type::Number(_) => VarType::Number,
+            Type::String(_) => VarType::String,
+        })
+}
+
+fn add_type(types: &mut HashMap<String, VarType>, name: &str, typ: VarType) -> Result<(), String> {
+    match types.insert(name.to_string(), typ) {
+        Some(old) if old != typ => Err(format!(
+            "`{}` is used as a Number and as a String, which a C variable can't be.",
+            name
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Finds out which type every variable has
+fn infer_types(tokens: &[Token], dialect: &Dialect) -> Result<HashMap<String, VarType>, String> {
+    let mut types: HashMap<String, VarType> = HashMap::new();
+    let mut program_args: Vec<(&Token, usize)> = vec![];
+
+    for t in tokens {
+        for r in variable_refs(t) {
+            let name: &String = match name_at(t, r.position) {
+                Some(n) => n,
+                None => continue,
+            };
+            if r.is_write {
+                if let Some(typ) = written_type(t) {
+                    add_type(&mut types, name, typ)?;
+                }
+            } else if let Some(typ) = predefined_type(name, dialect) {
+                add_type(&mut types, name, typ)?;
+            } else if is_interpreter_variable(name) {
+                if let crate::symbols::NamePosition::Arg(i) = r.position {
+                    program_args.push((t, i));
+                }
+            }
+        }
+    }
+    // Input needs `eoff`, even if the program never looks at it
+    if tokens.iter().any(|t| t.op == Operation::Input) {
+        add_type(&mut types, EOF_VAR_NAME, VarType::Number)?;
+    }
+
+    // Program arguments are Numbers or Strings depending on what is passed to the program,
+    // so their type comes from how they are used (Strings if it doesn't matter)
+    for (t, i) in &program_args {
+        let typ: Option<VarType> = read_type(t, *i, dialect).or_else(|| {
+            // Compared to another variable
+            let other: &String = match i {
+                0 => t.args.get(1)?,
+                1 => t.args.first()?,
+                _ => return None,
+            };
+            (t.op == Operation::Branch).then(|| types.get(other).copied()).flatten()
+        });
+        if let Some(typ) = typ {
+            add_type(&mut types, &t.args[*i], typ)?;
+        }
+    }
+    for (t, i) in &program_args {
+        if !types.contains_key(&t.args[*i]) {
+            types.insert(t.args[*i].clone(), VarType::String);
+        }
+    }
+    Ok(types)
+}
+
+struct Emitter<'a> {
+    types: HashMap<String, VarType>,
+    // Variables which are read somewhere, they need a flag telling whether they exist yet
+    read: HashSet<String>,
+    labels: &'a HashMap<String, usize>,
+    dialect: &'a Dialect,
+    numerals: &'static Numerals,
+}
+
+// C code for a statement, or the error it always runs into
+type StatementCode = Result<Vec<String>, String>;
+
+impl Emitter<'_> {
+    // Predefined variables exist from the start
+    fn always_set(&self, name: &str) -> bool {
+        predefined_type(name, self.dialect).is_some()
+    }
+
+    // Makes sure the variable exists (with the right type) before it is used, returns its C name
+    fn read(
+        &self,
+        ip: usize,
+        name: &str,
+        expected: Option<VarType>,
+        (missing, wrong_type): (&str, &str),
+        code: &mut Vec<String>,
+    ) -> Result<String, String> {
+        let typ: VarType = match self.types.get(name) {
+            Some(n) => *n,
+            None => return Err(missing.to_string()),
+        };
+        if expected.is_some_and(|e| e != typ) {
+            return Err(wrong_type.to_string());
+        }
+        if !self.always_set(name) {
+            code.push(format!("if (!{}) mott_error({}, {});", set_flag(name), ip, c_string(missing)));
+        }
+        Ok(variable_name(name))
+    }
+
+    // Checks the type the variable is assigned with, returns its C name
+    fn write(&self, name: &str, typ: VarType, wrong_type: &str) -> Result<String, String> {
+        match self.types.get(name) {
+            Some(n) if *n != typ => Err(wrong_type.to_string()),
+            _ => Ok(variable_name(name)),
+        }
+    }
+
+    // Remembers that the variable exists now
+    fn assigned(&self, name: &str, code: &mut Vec<String>) {
+        if self.read.contains(name) && !self.always_set(name) {
+            code.push(format!("{} = 1;", set_flag(name)));
+        }
+    }
+
+    // Code appending the values of the variables to `text` (like Print joins them)
+    fn join(&self, ip: usize, names: &[String], missing: &str, code: &mut Vec<String>) -> Result<Vec<String>, String> {
+        let mut parts: Vec<String> = vec![String::from("mott_text text = {NULL, 0};")];
+        parts.push(String::from("mott_append(&text, \"\");"));
+        for name in names {
+            let variable: String = self.read(ip, name, None, (missing, ""), code)?;
+            parts.push(match self.types[name] {
+                VarType::Number => format!("mott_append_number(&text, {});", variable),
+                VarType::String => format!("mott_append(&text, {});", variable),
+            });
+        }
+        Ok(parts)
+    }
+
+    fn arithmetic(&self, ip: usize, t: &Token, operator: &str) -> StatementCode {
+        if t.nargs != 3 {
+            return Err(String::from("InvalidAmountArguments"));
+        }
+        let messages = ("VariableDoesNotExist", "ArithmeticOnString");
+        let mut code: Vec<String> = vec![];
+        // Dividing checks the divisor first
+        let (first, second): (String, String) = if t.op == Operation::Div {
+            let second: String = self.read(ip, &t.args[1], Some(VarType::Number), messages, &mut code)?;
+            code.push(format!("if ({} == 0.0) mott_error({}, \"ZeroDivisionError\");", second, ip));
+            (self.read(ip, &t.args[0], Some(VarType::Number), messages, &mut code)?, second)
+        } else {
+            let first: String = self.read(ip, &t.args[0], Some(VarType::Number), messages, &mut code)?;
+            (first, self.read(ip, &t.args[1], Some(VarType::Number), messages, &mut code)?)
+        };
+        let result: String = self.write(&t.args[2], VarType::Number, "StoringToString")?;
+        code.push(format!("{} = {} {} {};", result, first, operator, second));
+        self.assigned(&t.args[2], &mut code);
+        Ok(code)
+    }
+
+    fn var(&self, t: &Token) -> StatementCode {
+        let first: &String = match t.args.first() {
+            Some(n) if n.is_empty() => return Err(String::from("Var token has an empty argument (check spaces).")),
+            Some(n) => n,
+            None => return Err(String::from("Var token is missing argument(s).")),
+        };
+        let mut code: Vec<String> = vec![];
+        if starts_uppercase(first) {
+            let value: f64 = parse_text_number(t.args.clone(), self.numerals).map_err(|e| e.to_string())?;
+            let variable: String = self.write(&t.name, VarType::Number, "Changing type of variable from String to number")?;
+            code.push(format!("{} = {};", variable, c_number(value)));
+        } else {
+            let variable: String = self.write(&t.name, VarType::String, "Changing type of variable from Number to String")?;
+            code.push(format!("mott_set(&{}, mott_copy({}));", variable, c_string(&t.args.join(" "))));
+        }
+        self.assigned(&t.name, &mut code);
+        Ok(code)
+    }
+
+    fn input(&self, ip: usize, t: &Token) -> StatementCode {
+        if t.nargs != 2 && t.nargs != 3 {
+            return Err(String::from("Input needs two args (three with a prompt)."));
+        }
+        let is_number: bool = match t.args[0].chars().next() {
+            Some(c) => c.is_ascii_uppercase(),
+            None => return Err(String::from("Input type argument is empty (check spaces).")),
+        };
+        let mut code: Vec<String> = vec![];
+        let prompt: Option<Vec<String>> = match t.args.get(2) {
+            Some(name) => Some(self.join(
+                ip,
+                std::slice::from_ref(name),
+                "Couldn't find the prompt var, you are trying to use.",
+                &mut code,
+            )?),
+            None => None,
+        };
+        let destination: String = variable_name(&t.args[1]);
+        let eof: String = variable_name(EOF_VAR_NAME);
+
+        code.push(String::from("{"));
+        code.push(String::from("    int retries_left = MOTT_INPUT_RETRIES;"));
+        code.push(String::from("    char *line;"));
+        code.push(String::from("    for (;;) {"));
+        if let Some(prompt) = prompt {
+            code.push(String::from("        {"));
+            code.extend(prompt.into_iter().map(|n| format!("            {}", n)));
+            code.push(String::from("            fputs(text.data, stdout);"));
+            code.push(String::from("            free(text.data);"));
+            code.push(String::from("        }"));
+        }
+        code.push(String::from("        fflush(stdout);"));
+        code.push(String::from("        if (!mott_read_line(&line)) {"));
+        code.push(format!("            if ({} != 0) mott_error({}, \"EndOfInput\");", eof, ip));
+        code.push(format!("            {} = 1;", eof));
+        if is_number {
+            code.push(format!("            {} = 0;", destination));
+        } else {
+            code.push(format!("            mott_set(&{}, mott_copy(\"\"));", destination));
+        }
+        code.push(String::from("            break;"));
+        code.push(String::from("        }"));
+        if is_number {
+            code.push(String::from("        {"));
+            code.push(String::from("            double number;"));
+            code.push(String::from("            int is_number = mott_parse_number(line, &number);"));
+            code.push(String::from("            free(line);"));
+            code.push(String::from("            if (is_number) {"));
+            code.push(format!("                {} = number;", destination));
+            code.push(String::from("                break;"));
+            code.push(String::from("            }"));
+            code.push(String::from("        }"));
+            code.push(String::from(
+                "        printf(\"The program expected a Number, which your input is *not*!\\n\");",
+            ));
+            code.push(format!("        if (retries_left == 0) mott_error({}, \"NotANumber\");", ip));
+            code.push(String::from("        retries_left--;"));
+        } else {
+            code.push(format!("        mott_set(&{}, line);", destination));
+            code.push(String::from("        break;"));
+        }
+        code.push(String::from("    }"));
+        code.push(String::from("}"));
+        self.assigned(&t.args[1], &mut code);
+        Ok(code)
+    }
+
+    fn branch(&self, ip: usize, t: &Token) -> StatementCode {
+        if t.nargs != 3 {
+            return Err(String::from("Branch Opcode does not have exactly *3* arguments."));
+        }
+        if !self.labels.contains_key(&t.args[2]) {
+            return Err(String::from("Couldn't find label you are trying to jump to."));
+        }
+        let condition: BranchCondition = match t.name.chars().next().and_then(|c| self.dialect.branch_condition(c)) {
+            Some(n) => n,
+            None => {
+                return Err(String::from(
+                    "Branch command doesn't start with <e/l/g> (or uppercase version) and is invalid.",
+                ))
+            }
+        };
+
+        let mut code: Vec<String> = vec![];
+        let messages = ("VariableDoesNotExist", "");
+        let first: String = self.read(ip, &t.args[0], None, messages, &mut code)?;
+        let second: String = self.read(ip, &t.args[1], None, messages, &mut code)?;
+        let typ: VarType = self.types[&t.args[0]];
+        if typ != self.types[&t.args[1]] {
+            return Err(String::from("VarsNotOfSameType"));
+        }
+
+        let check: String = match (typ, condition) {
+            (VarType::Number, BranchCondition::Equal) => format!("{} == {}", first, second),
+            (VarType::Number, BranchCondition::Less) => format!("{} < {}", first, second),
+            (VarType::Number, BranchCondition::Greater) => format!("{} > {}", first, second),
+            (VarType::String, BranchCondition::Equal) => format!("strcmp({}, {}) == 0", first, second),
+            (VarType::String, _) => return Err(String::from("InvalidComparisonForTypes")),
+        };
+        code.push(format!("if ({}) goto {};", check, label_name(&t.args[2])));
+        Ok(code)
+    }
+
+    fn env(&self, t: &Token) -> StatementCode {
+        if t.nargs != 2 {
+            return Err(String::from("Env needs exactly two args."));
+        }
+        let destination: String = self.write(&t.args[1], VarType::String, "Changing type of variable from Number to String")?;
+        let mut code: Vec<String> = vec![
+            String::from("{"),
+            format!("    const char *value = getenv({});", c_string(&t.args[0])),
+            format!("    mott_set(&{}, mott_copy(value != NULL ? value : \"\"));", destination),
+            String::from("}"),
+        ];
+        self.assigned(&t.args[1], &mut code);
+        Ok(code)
+    }
+
+    fn file(&self, ip: usize, t: &Token) -> StatementCode {
+        let letter: char = file_letter(t).unwrap_or(' ');
+        let expected_args: bool = match letter {
+            'r' | 'e' => t.nargs == 2,
+            'l' => t.nargs == 3,
+            'w' | 'a' => t.nargs >= 2,
+            _ => return Err(String::from("UnknownFileOperation")),
+        };
+        if !expected_args {
+            return Err(String::from("InvalidAmountArguments"));
+        }
+
+        let mut code: Vec<String> = vec![];
+        let path: String = self.read(
+            ip,
+            &t.args[0],
+            Some(VarType::String),
+            ("VariableDoesNotExist", "PathIsNotAString"),
+            &mut code,
+        )?;
+        match letter {
+            'r' => {
+                let destination: String = self.write(&t.args[1], VarType::String, "StoringToNumber")?;
+                code.push(format!("mott_set(&{}, mott_read_file({}, {}));", destination, ip, path));
+                self.assigned(&t.args[1], &mut code);
+            }
+            'l' => {
+                if !self.labels.contains_key(&t.args[2]) {
+                    return Err(String::from("LabelDoesNotExist"));
+                }
+                let destination: String = self.write(&t.args[1], VarType::String, "StoringToNumber")?;
+                code.push(String::from("{"));
+                code.push(String::from("    char *line;"));
+                code.push(format!("    if (!mott_next_line({}, {}, &line)) goto {};", ip, path, label_name(&t.args[2])));
+                code.push(format!("    mott_set(&{}, line);", destination));
+                code.push(String::from("}"));
+                self.assigned(&t.args[1], &mut code);
+            }
+            'w' | 'a' => {
+                let parts: Vec<String> = self.join(ip, &t.args[1..], "VariableDoesNotExist", &mut code)?;
+                code.push(String::from("{"));
+                code.extend(parts.into_iter().map(|n| format!("    {}", n)));
+                code.push(format!(
+                    "    mott_write_file({}, {}, text.data, {});",
+                    ip,
+                    path,
+                    (letter == 'a') as u8
+                ));
+                code.push(String::from("    free(text.data);"));
+                code.push(String::from("}"));
+            }
+            _ => {
+                let destination: String = self.write(&t.args[1], VarType::Number, "StoringToString")?;
+                code.push(format!("{} = mott_file_exists({});", destination, path));
+                self.assigned(&t.args[1], &mut code);
+            }
+        }
+        Ok(code)
+    }
+
+    fn exit(&self, ip: usize, t: &Token) -> StatementCode {
+        let first: &String = match t.args.first() {
+            Some(n) => n,
+            None => return Ok(vec![String::from("return 0;")]),
+        };
+        let is_valid = |status: f64| status.fract() == 0.0 && (0.0..=255.0).contains(&status);
+        let invalid: &str = "The exit status has to be a whole number between zero and 255.";
+
+        if starts_uppercase(first) {
+            let status: f64 = parse_text_number(t.args.clone(), self.numerals)
+                .map_err(|_| String::from("Couldn't parse the exit status."))?;
+            if !is_valid(status) {
+                return Err(invalid.to_string());
+            }
+            return Ok(vec![format!("return {};", status as i32)]);
+        }
+        if t.nargs != 1 {
+            return Err(String::from("Exit takes either one variable or a spelled out number."));
+        }
+
+        let mut code: Vec<String> = vec![];
+        let status: String = self.read(
+            ip,
+            first,
+            Some(VarType::Number),
+            ("Couldn't find var, you are trying to use.", "The exit status has to be a Number."),
+            &mut code,
+        )?;
+        code.push(format!(
+            "if ({0} != floor({0}) || {0} < 0 || {0} > 255) mott_error({1}, {2});",
+            status,
+            ip,
+            c_string(invalid)
+        ));
+        code.push(format!("return (int){};", status));
+        Ok(code)
+    }
+
+    fn statement(&self, ip: usize, t: &Token) -> StatementCode {
+        match t.op {
+            Operation::Print => {
+                let mut code: Vec<String> = vec![];
+                let parts: Vec<String> =
+                    self.join(ip, &t.args, "Couldn't find var, you are trying to use.", &mut code)?;
+                code.push(String::from("{"));
+                code.extend(parts.into_iter().map(|n| format!("    {}", n)));
+                code.push(String::from("    mott_append(&text, \"\\n\");"));
+                code.push(String::from("    fputs(text.data, stdout);"));
+                code.push(String::from("    free(text.data);"));
+                code.push(String::from("}"));
+                Ok(code)
+            }
+            Operation::Input => self.input(ip, t),
+            Operation::Add => self.arithmetic(ip, t, "+"),
+            Operation::Sub => self.arithmetic(ip, t, "-"),
+            Operation::Mul => self.arithmetic(ip, t, "*"),
+            Operation::Div => self.arithmetic(ip, t, "/"),
+            Operation::Var => self.var(t),
+            Operation::Branch => self.branch(ip, t),
+            // The C label is added by `emit_c`
+            Operation::Label => Ok(vec![]),
+            Operation::Env => self.env(t),
+            Operation::File => self.file(ip, t),
+            Operation::Exit => self.exit(ip, t),
+            Operation::Include => Err(String::from("Include can only be used in files run by motts.")),
+            Operation::Invalid => Err(String::from("Trying to execute 'Invalid' operation.")),
+        }
+    }
+}
+
+// Translates the tokens of a program (with the exit token at the end, like `load_program` returns them)
+fn emit_c(
+    tokens: &[Token],
+    labels: &HashMap<String, usize>,
+    dialect: &Dialect,
+    numbers: Language,
+    source_name: &str,
+) -> Result<String, String> {
+    let types: HashMap<String, VarType> = infer_types(tokens, dialect)?;
+    let read: HashSet<String> = tokens
+        .iter()
+        .flat_map(|t| {
+            variable_refs(t)
+                .into_iter()
+                .filter(|r| !r.is_write)
+                .filter_map(move |r| name_at(t, r.position).cloned())
+        })
+        .collect();
+    // Only labels which are jumped to become C labels (others would be unused)
+    let jump_targets: HashSet<&String> = tokens
+        .iter()
+        .filter_map(|t| label_ref(t).and_then(|p| name_at(t, p)))
+        .filter(|n| labels.contains_key(*n))
+        .collect();
+    let emitter = Emitter {
+        types,
+        read,
+        labels,
+        dialect,
+        numerals: numbers.numerals(),
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "/* Generated by `motts emit-c` from {} */", source_name.replace("*/", "* /"));
+    out.push_str(C_RUNTIME);
+    out.push_str("\nint main(int argc, char **argv) {\n");
+
+    let mut names: Vec<(&String, &VarType)> = emitter.types.iter().collect();
+    names.sort();
+    for (name, typ) in &names {
+        match typ {
+            VarType::Number => {
+                let _ = writeln!(out, "    double {} = 0;", variable_name(name));
+            }
+            VarType::String => {
+                let _ = writeln!(out, "    char *{} = NULL;", variable_name(name));
+            }
+        }
+    }
+    for (name, _) in &names {
+        if emitter.read.contains(*name) && !emitter.always_set(name) {
+            let _ = writeln!(out, "    int {} = 0;", set_flag(name));
+        }
+    }
+    out.push_str("    (void)argc;\n    (void)argv;\n\n");
+    let _ = writeln!(
+        out,
+        "    if (argc - 1 > {0}) {{\n        \
+         printf(\"Error: A mott program takes at most {0} arguments (`arga` to `argz`), not %d.\\n\", argc - 1);\n        \
+         return 1;\n    }}\n",
+        MAX_PROGRAM_ARGS
+    );
+
+    // Predefined variables and program arguments
+    for (name, _) in &names {
+        let variable: String = variable_name(name);
+        if let Some((_, data)) = dialect.predefined_variables.iter().find(|(n, _)| n == *name) {
+            let _ = match data {
+                Type::Number(n) => writeln!(out, "    {} = {};", variable, c_number(*n)),
+                Type::String(s) => writeln!(out, "    {} = mott_copy({});", variable, c_string(s)),
+            };
+        } else if *name == ARG_COUNT_VAR_NAME {
+            let _ = writeln!(out, "    {} = argc - 1;", variable);
+        } else if *name != EOF_VAR_NAME && is_interpreter_variable(name) {
+            let index: u32 = name.chars().last().map_or(0, |c| c as u32 - 'a' as u32) + 1;
+            let _ = writeln!(out, "    if (argc > {}) {{", index);
+            match emitter.types[*name] {
+                VarType::Number => {
+                    let _ = writeln!(
+                        out,
+                        "        if (!mott_parse_number(argv[{}], &{})) mott_error(0, {});",
+                        index,
+                        variable,
+                        c_string(&format!("The program argument `{}` has to be a Number.", name))
+                    );
+                }
+                VarType::String => {
+                    let _ = writeln!(out, "        {} = mott_copy(argv[{}]);", variable, index);
+                }
+            }
+            if emitter.read.contains(*name) {
+                let _ = writeln!(out, "        {} = 1;", set_flag(name));
+            }
+            out.push_str("    }\n");
+        }
+    }
+
+    for (ip, t) in tokens.iter().enumerate() {
+        let source: String = format!("{} {}", t.name, t.args.join(" "));
+        let _ = writeln!(out, "\n    /* {}: {} */", ip, source.trim().replace("*/", "* /"));
+        if t.op == Operation::Label && labels.get(&t.name) == Some(&ip) && jump_targets.contains(&t.name) {
+            let _ = writeln!(out, "{}: ;", label_name(&t.name));
+        }
+        let code: Vec<String> = match emitter.statement(ip, t) {
+            Ok(n) => n,
+            Err(msg) => vec![format!("mott_error({}, {});", ip, c_string(&msg))],
+        };
+        for line in code {
+            let _ = writeln!(out, "    {}", line);
+        }
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+// Writes the C code for the program, returns the exit status
+pub fn run_emit_c(options: &EmitCOptions) -> i32 {
+    let dialect: Dialect = match Dialect::load_or_standard(options.dialect.as_deref()) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+    let LoadedProgram { tokens, is_valid_code, header, .. } = match load_program(&options.file, &dialect) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+    if !is_valid_code {
+        println!("{}", "Code can't be compiled as a result of the above errors.".red());
+        return 1;
+    }
+
+    let labels: HashMap<String, usize> = create_labels(tokens.clone());
+    let numbers: Language = header.numbers.unwrap_or(options.numbers);
+    let code: String = match emit_c(&tokens, &labels, &dialect, numbers, &options.file) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+
+    match &options.output {
+        Some(path) => match fs::write(path, code) {
+            Ok(()) => 0,
+            Err(e) => {
+                println!("{} Couldn't write `{}`: {}", "Error:".red(), path, e);
+                1
+            }
+        },
+        None => {
+            print!("{}", code);
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+    use crate::{execute_code_tokens, ExecutionConfig};
+    use std::io::Write as _;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn names_and_types() {
+        assert_eq!(c_identifier("v_", "lib/count::coun"), "v_lib_2f_count_3a__3a_coun");
+        assert_eq!(c_identifier("v_", "a_b"), "v_a__b");
+        assert_eq!(c_string("say \"hi\"\n??é"), "\"say \\\"hi\\\"\\n\\?\\?\\303\\251\"");
+        assert_eq!(c_number(1.5), "1.5");
+        assert_eq!(c_number(f64::NEG_INFINITY), "-INFINITY");
+        assert_eq!(c_number(f64::NAN), "NAN");
+
+        let dialect = Dialect::standard();
+        let (tokens, _) = crate::tokenize_statements(
+            "numb One. text hi. Ad numb numb summ. i str line. P newl arga. Equal argb numb labels. labels.",
+            &dialect,
+        );
+        let types = infer_types(&tokens, &dialect).unwrap();
+        let expected = [
+            ("numb", VarType::Number),
+            ("text", VarType::String),
+            ("summ", VarType::Number),
+            ("line", VarType::String),
+            ("newl", VarType::String),
+            ("eoff", VarType::Number),
+            ("arga", VarType::String),
+            ("argb", VarType::Number),
+        ];
+        assert_eq!(types.len(), expected.len());
+        for (name, typ) in expected {
+            assert_eq!(types[name], typ, "{}", name);
+        }
+
+        let (tokens, _) = crate::tokenize_statements("numb One. numb hello.", &dialect);
+        assert!(infer_types(&tokens, &dialect).is_err());
+    }
+
+    // Runs the program in motts, returns the output and exit status
+    fn interpret(path: &Path, input: &str, args: &[String]) -> (String, i32) {
+        let dialect = Dialect::standard();
+        let program = load_program(path.to_str().unwrap(), &dialect).unwrap();
+        assert!(program.is_valid_code);
+        let labels = create_labels(program.tokens.clone());
+        let config = ExecutionConfig {
+            program_args: args.to_vec(),
+            numbers: program.header.numbers.unwrap_or_default(),
+            ..Default::default()
+        };
+        let mut console = BufferConsole::new(input);
+        match execute_code_tokens(program.tokens, labels, &config, &mut console) {
+            Ok(status) => (console.output, status),
+            Err(e) => (
+                format!(
+                    "{}Error: `{}` on token {} \n\nThe program terminated because of the above error.\n",
+                    console.output, e.msg, e.token_num
+                ),
+                1,
+            ),
+        }
+    }
+
+    // Compiles the program to C and runs it, returns the output and exit status
+    fn compile_and_run(path: &Path, dir: &Path, input: &str, args: &[String]) -> (String, i32) {
+        let dialect = Dialect::standard();
+        let program = load_program(path.to_str().unwrap(), &dialect).unwrap();
+        let labels = create_labels(program.tokens.clone());
+        let numbers = program.header.numbers.unwrap_or_default();
+        let code = emit_c(&program.tokens, &labels, &dialect, numbers, "test").unwrap();
+
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let c_file = dir.join(format!("{}.c", name));
+        let binary = dir.join(name);
+        fs::write(&c_file, code).unwrap();
+        let compiled = Command::new("cc")
+            .args(["-std=c99", "-Wall", "-D_POSIX_C_SOURCE=200809L", "-o"])
+            .arg(&binary)
+            .arg(&c_file)
+            .arg("-lm")
+            .output()
+            .unwrap();
+        assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+
+        let mut child = Command::new(&binary)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        (String::from_utf8(output.stdout).unwrap(), output.status.code().unwrap())
+    }
+
+    #[test]
+    fn compiled_programs_behave_like_motts() {
+        if Command::new("cc").arg("--version").output().is_err() {
+            println!("Skipping compiled_programs_behave_like_motts: no C compiler (cc) found");
+            return;
+        }
+        let dir: PathBuf = std::env::temp_dir().join(format!("motts-emit-c-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let data_file: String = dir.join("data").to_str().unwrap().to_string();
+
+        let programs: [(&str, &str, &str, Vec<String>); 6] = [
+            (
+                "numbers",
+                "thre Three. twoo Two. zero Zero. negt Minus One comma Five.\n\
+                 div twoo thre rslt. P rslt. Mul negt thre rslt. P rslt spce negt.\n\
+                 bign One zero zero zero zero zero zero zero zero zero zero zero zero zero zero zero zero zero zero zero.\n\
+                 Mul bign bign rslt. P rslt. div zero twoo rslt. P rslt.\n\
+                 div thre zero rslt.",
+                "",
+                vec![],
+            ),
+            (
+                "strings",
+                "text  two  spaces and \"quotes\" \\ é.\nP text newl dott.\nGreet text text finish.\nfinish.\nFailure Four two.",
+                "",
+                vec![],
+            ),
+            (
+                "inputs",
+                "prom say something: .\nl str line prom.\nr Num numb.\nP line spce numb.\nr Num numb.\nP numb eoff.\nr Num numb.",
+                "hello there\n 12.5 \n",
+                vec![],
+            ),
+            (
+                "files",
+                "text hello. onee One.\nwriteout arga text newl.\nappendix arga text spce argc newl.\n\
+                 readfile arga cont. P cont. existing arga isit. P isit.\n\
+                 loopin.\nlinefrom arga line finish.\nP line.\nEqual onee onee loopin.\nfinish.\nStopped argb.",
+                "",
+                vec![data_file, String::from("7")],
+            ),
+            ("missing", "P nope.", "", vec![]),
+            ("branches", "a Two. b Three. Lower b a skip. P a. skip. Greater b a done. P b. done. Equal a a end. P a. end.", "", vec![]),
+        ];
+        for (name, code, input, args) in &programs {
+            let path = dir.join(format!("{}.mt", name));
+            fs::write(&path, code).unwrap();
+            assert_eq!(
+                compile_and_run(&path, &dir, input, args),
+                interpret(&path, input, args),
+                "{}",
+                name
+            );
+        }
+
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        for (name, input) in [
+            ("fact.mt", ""),
+            ("loop_to_4.mt", ""),
+            ("add_1.mt", "41\n"),
+            ("compte_fr.mt", ""),
+            ("include.mt", ""),
+            ("higher_lower_game.mt", "7\n3\n9\n7\n"),
+        ] {
+            let path = examples.join(name);
+            assert_eq!(compile_and_run(&path, &dir, input, &[]), interpret(&path, input, &[]), "{}", name);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+// File operations (8 letter words). Like branches, the first letter decides what happens:
+//   [r]ead   <r...> path dst          reads the whole file into the string dst
+//   [l]ine   <l...> path dst label    reads the next line into dst, jumps to label at the end of the file
+//   [w]rite  <w...> path vars...      (over)writes the file with the joined vars
+//   [a]ppend <a...> path vars...      appends the joined vars to the file
+//   [e]xists <e...> path dst          sets the number dst to one if the file exists, else to zero
+// Paths are always taken from string variables.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::prelude::*;
+use std::path::Path;
+
+use crate::{concat_variables, Token, Type, Variable};
+
+#[derive(Debug)]
+pub enum FileError {
+    InvalidAmountArguments,
+    VariableDoesNotExist,
+    PathIsNotAString,
+    StoringToNumber,
+    StoringToString,
+    LabelDoesNotExist,
+    UnknownFileOperation,
+    Io(String),
+}
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Io(msg) => write!(f, "FileError: {}", msg),
+            _ => fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+// Files which are currently being read line by line, with the lines that are still left.
+// A file is removed again once its end was reached, so the next read starts from the top.
+#[derive(Default)]
+pub struct OpenFiles {
+    lines: HashMap<String, VecDeque<String>>,
+}
+
+fn get_path(name: &str, vars: &HashMap<String, Variable>) -> Result<String, FileError> {
+    match vars.get(name) {
+        Some(n) => match &n.data {
+            Type::String(s) => Ok(s.clone()),
+            Type::Number(_) => Err(FileError::PathIsNotAString),
+        },
+        None => Err(FileError::VariableDoesNotExist),
+    }
+}
+
+fn store_string(
+    name: &str,
+    value: String,
+    vars: &mut HashMap<String, Variable>,
+) -> Result<(), FileError> {
+    if let Some(Variable {
+        data: Type::Number(_),
+    }) = vars.get(name)
+    {
+        return Err(FileError::StoringToNumber);
+    }
+    vars.insert(
+        name.to_string(),
+        Variable {
+            data: Type::String(value),
+        },
+    );
+    Ok(())
+}
+
+fn read_file(path: &str) -> Result<String, FileError> {
+    std::fs::read_to_string(path).map_err(|e| FileError::Io(format!("{}: {}", path, e)))
+}
+
+fn write_file(path: &str, content: &str, append: bool) -> Result<(), FileError> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path);
+    match file.and_then(|mut f| f.write_all(content.as_bytes())) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(FileError::Io(format!("{}: {}", path, e))),
+    }
+}
+
+// Executes a file token. Returns the token index to jump to, if the line read hit the end of the file.
+pub fn file_operation(
+    t: &Token,
+    vars: &mut HashMap<String, Variable>,
+    open_files: &mut OpenFiles,
+    labels: &HashMap<String, usize>,
+) -> Result<Option<usize>, FileError> {
+    let first_letter: char = match t.name.chars().next() {
+        Some(n) => n.to_ascii_lowercase(),
+        None => return Err(FileError::UnknownFileOperation),
+    };
+
+    let expected_args = |ok: bool| -> Result<(), FileError> {
+        if ok {
+            Ok(())
+        } else {
+            Err(FileError::InvalidAmountArguments)
+        }
+    };
+
+    match first_letter {
+        'r' => {
+            expected_args(t.nargs == 2)?;
+            let path: String = get_path(&t.args[0], vars)?;
+            let content: String = read_file(&path)?;
+            store_string(&t.args[1], content, vars)?;
+        }
+        'l' => {
+            expected_args(t.nargs == 3)?;
+            let path: String = get_path(&t.args[0], vars)?;
+            let label_location: usize = match labels.get(&t.args[2]) {
+                Some(n) => *n,
+                None => return Err(FileError::LabelDoesNotExist),
+            };
+
+            if !open_files.lines.contains_key(&path) {
+                let content: String = read_file(&path)?;
+                open_files
+                    .lines
+                    .insert(path.clone(), content.lines().map(String::from).collect());
+            }
+
+            match open_files.lines.get_mut(&path).and_then(|n| n.pop_front()) {
+                Some(line) => store_string(&t.args[1], line, vars)?,
+                None => {
+                    open_files.lines.remove(&path);
+                    return Ok(Some(label_location));
+                }
+            }
+        }
+        'w' | 'a' => {
+            expected_args(t.nargs >= 2)?;
+            let path: String = get_path(&t.args[0], vars)?;
+            let content: String = match concat_variables(&t.args[1..], vars) {
+                Some(n) => n,
+                None => return Err(FileError::VariableDoesNotExist),
+            };
+            write_file(&path, &content, first_letter == 'a')?;
+            // Reading line by line starts over after the file was changed
+            open_files.lines.remove(&path);
+        }
+        'e' => {
+            expected_args(t.nargs == 2)?;
+            let path: String = get_path(&t.args[0], vars)?;
+            if let Some(Variable {
+                data: Type::String(_),
+            }) = vars.get(&t.args[1])
+            {
+                return Err(FileError::StoringToString);
+            }
+            let exists: f64 = if Path::new(&path).exists() { 1.0 } else { 0.0 };
+            vars.insert(
+                t.args[1].clone(),
+                Variable {
+                    data: Type::Number(exists),
+                },
+            );
+        }
+        _ => return Err(FileError::UnknownFileOperation),
+    }
+    Ok(None)
+}
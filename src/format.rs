@@ -0,0 +1,319 @@
+// `motts fmt`: re-emits mott programs in a uniform style.
+//
+// - one statement per line, ending in a dot
+// - the words of a statement are separated by a single space
+//   (except for the text of strings, which is kept as it is)
+// - statements between a label and the last branch jumping back to it are indented
+// - comments are kept: at the end of the statement they were written after, or on their own line
+// - the header and single empty lines between statements are kept
+
+use std::fs;
+
+use colored::*;
+
+use crate::dialect::Dialect;
+use crate::header::{header_lines, split_header};
+use crate::symbols::{label_ref, name_at};
+use crate::{find_comment, tokenize_text_code, Operation, STATEMENT_SEP};
+
+static INDENT: &str = "    ";
+
+pub struct FormatOptions {
+    // Only report which files would change, without writing them
+    pub check: bool,
+    pub dialect: Option<String>,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Item {
+    Statement { text: String, comments: Vec<String> },
+    Comment(String),
+    EmptyLine,
+}
+
+// Splits the code (without the header) into statements, comments and empty lines
+fn parse_items(code: &str) -> Vec<Item> {
+    let mut items: Vec<Item> = vec![];
+    let mut pending: String = String::new();
+    let mut pending_comments: Vec<String> = vec![];
+
+    let mut at_statement_start: bool = true;
+    for line in code.lines() {
+        let (code_part, comment) = match find_comment(line, &mut at_statement_start) {
+            Some(n) => (&line[..n], Some(line[n..].trim_end().to_string())),
+            None => (line, None),
+        };
+
+        if code_part.trim().is_empty() && pending.trim().is_empty() && comment.is_none() {
+            if !matches!(items.last(), None | Some(Item::EmptyLine)) {
+                items.push(Item::EmptyLine);
+            }
+            continue;
+        }
+
+        let mut finished_on_this_line: bool = false;
+        let mut parts = code_part.split(STATEMENT_SEP).peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                // The rest of the line belongs to a statement which isn't finished yet
+                if !pending.is_empty() || !part.trim().is_empty() {
+                    pending.push_str(part);
+                    pending.push('\n');
+                }
+                break;
+            }
+            pending.push_str(part);
+            items.push(Item::Statement {
+                text: pending.trim().to_string(),
+                comments: std::mem::take(&mut pending_comments),
+            });
+            pending.clear();
+            finished_on_this_line = true;
+        }
+
+        if let Some(comment) = comment {
+            if !pending.trim().is_empty() {
+                pending_comments.push(comment);
+            } else if finished_on_this_line {
+                if let Some(Item::Statement { comments, .. }) = items.last_mut() {
+                    comments.push(comment);
+                }
+            } else {
+                pending.clear();
+                items.push(Item::Comment(comment));
+            }
+        }
+    }
+
+    // A missing dot at the end is added
+    if !pending.trim().is_empty() {
+        items.push(Item::Statement {
+            text: pending.trim().to_string(),
+            comments: pending_comments,
+        });
+    }
+    while let Some(Item::EmptyLine) = items.last() {
+        items.pop();
+    }
+    items
+}
+
+// Puts a single space between all words, strings keep their text as it is.
+// Two spaces in a row are an empty argument, which is an error when the statement runs, so those
+// statements are left alone: joining the words would turn them into different, working code.
+fn normalize_statement(text: &str, dialect: &Dialect) -> String {
+    let words: Vec<&str> = text.split(' ').filter(|w| !w.is_empty()).collect();
+    if let Ok(t) = tokenize_text_code(text, dialect) {
+        if t.args.iter().any(String::is_empty) {
+            return text.to_string();
+        }
+        let is_string = t.op == Operation::Var
+            && words.get(1).is_some_and(|a| !a.starts_with(char::is_uppercase));
+        if is_string {
+            if let Some((name, value)) = text.split_once(' ') {
+                return format!("{} {}", name, value);
+            }
+        }
+    }
+    words.join(" ")
+}
+
+// How deep every statement is indented: one level for every loop (label and a later branch
+// jumping back to it) the statement is inside of
+fn indentation(statements: &[&str], dialect: &Dialect) -> Vec<usize> {
+    let tokens: Vec<_> = statements
+        .iter()
+        .map(|s| tokenize_text_code(s, dialect).ok())
+        .collect();
+    let mut depth: Vec<usize> = vec![0; statements.len()];
+
+    for (label_index, label) in tokens.iter().enumerate() {
+        let label_name: &String = match label {
+            Some(t) if t.op == Operation::Label => &t.name,
+            _ => continue,
+        };
+        let last_jump_back: Option<usize> = tokens
+            .iter()
+            .enumerate()
+            .skip(label_index + 1)
+            .filter(|(_, t)| {
+                t.as_ref().is_some_and(|t| {
+                    label_ref(t).and_then(|p| name_at(t, p)) == Some(label_name)
+                })
+            })
+            .map(|(i, _)| i)
+            .next_back();
+        if let Some(end) = last_jump_back {
+            for d in depth.iter_mut().take(end).skip(label_index + 1) {
+                *d += 1;
+            }
+        }
+    }
+    depth
+}
+
+pub fn format_code(content: &str, dialect: &Dialect) -> Result<String, String> {
+    // The header is kept as it is
+    let header_lines: Vec<&str> = header_lines(content);
+    let (_, code) = split_header(content)?;
+    let items: Vec<Item> = parse_items(&code);
+
+    let statements: Vec<&str> = items
+        .iter()
+        .filter_map(|i| match i {
+            Item::Statement { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    let depth: Vec<usize> = indentation(&statements, dialect);
+
+    let mut out = String::new();
+    for line in &header_lines {
+        out.push_str(line.trim());
+        out.push('\n');
+    }
+
+    let mut statement_index: usize = 0;
+    for item in &items {
+        // Comments are indented like the next statement
+        let level: usize = depth.get(statement_index).copied().unwrap_or(0);
+        match item {
+            Item::Statement { text, comments } => {
+                out.push_str(&INDENT.repeat(level));
+                out.push_str(&normalize_statement(text, dialect));
+                out.push_str(STATEMENT_SEP);
+                if !comments.is_empty() {
+                    out.push(' ');
+                    out.push_str(&comments.join(" "));
+                }
+                statement_index += 1;
+            }
+            Item::Comment(comment) => {
+                out.push_str(&INDENT.repeat(level));
+                out.push_str(comment);
+            }
+            Item::EmptyLine => (),
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// Formats all files, returns the exit status
+pub fn run_fmt(options: &FormatOptions) -> i32 {
+    let dialect: Dialect = match Dialect::load_or_standard(options.dialect.as_deref()) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+
+    let mut status: i32 = 0;
+    for file in &options.files {
+        let content: String = match fs::read_to_string(file) {
+            Ok(n) => n,
+            Err(e) => {
+                println!("{} Couldn't read `{}`: {}", "Error:".red(), file, e);
+                status = 1;
+                continue;
+            }
+        };
+        let formatted: String = match format_code(&content, &dialect) {
+            Ok(n) => n,
+            Err(e) => {
+                println!("{} {}: {}", "Error:".red(), file, e);
+                status = 1;
+                continue;
+            }
+        };
+        if formatted == content {
+            continue;
+        }
+
+        if options.check {
+            println!("{} isn't formatted.", file);
+            status = 1;
+        } else if let Err(e) = fs::write(file, formatted) {
+            println!("{} Couldn't write `{}`: {}", "Error:".red(), file, e);
+            status = 1;
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(code: &str) -> String {
+        format_code(code, &Dialect::standard()).unwrap()
+    }
+
+    #[test]
+    fn formats_statements() {
+        assert_eq!(
+            format("summ Zero.  ones One.\n\n\n\nmaxi Four.  P summ"),
+            "summ Zero.\nones One.\n\nmaxi Four.\nP summ.\n"
+        );
+        // Empty arguments stop the program, they aren't joined away
+        assert_eq!(format("summ   Zero. P  summ."), "summ   Zero.\nP  summ.\n");
+        // Strings keep their spaces
+        assert_eq!(format("text  a   b."), "text  a   b.\n");
+        assert_eq!(format("#numbers french\nnumb Un.\n"), "#numbers french\nnumb Un.\n");
+        // An empty line inside of the header doesn't end it
+        assert_eq!(
+            format("#numbers french\n\n#scope global\nnumb Un.\nP numb."),
+            "#numbers french\n#scope global\nnumb Un.\nP numb.\n"
+        );
+        // `//` inside of a statement isn't a comment
+        assert_eq!(format("urll https://example.  P urll. // done"), "urll https://example.\nP urll. // done\n");
+    }
+
+    #[test]
+    fn indents_loops() {
+        let code = "outerr.\ninnerr.\nP summ.\nEqual a b innerr.\nEqual a b outerr.\nfinish.\nP summ.\n";
+        assert_eq!(
+            format(code),
+            "outerr.\n    innerr.\n        P summ.\n    Equal a b innerr.\nEqual a b outerr.\nfinish.\nP summ.\n"
+        );
+        // Jumping forward doesn't indent
+        assert_eq!(format("Equal a b endeee. P a. endeee."), "Equal a b endeee.\nP a.\nendeee.\n");
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let code = "// start\nnumb One. // one\nP numb. P numb. // print\n\n// end";
+        assert_eq!(
+            format(code),
+            "// start\nnumb One. // one\nP numb.\nP numb. // print\n\n// end\n"
+        );
+        assert_eq!(format("loopin.\n// inside\nEqual a b loopin."), "loopin.\n// inside\nEqual a b loopin.\n");
+    }
+
+    #[test]
+    fn formatting_is_stable() {
+        for code in [
+            include_str!("../examples/fact.mt"),
+            include_str!("../examples/higher_lower_game.mt"),
+            include_str!("../examples/loop_to_4.mt"),
+            include_str!("../examples/compte_fr.mt"),
+        ] {
+            let once = format(code);
+            assert_eq!(format(&once), once);
+
+            // Formatting doesn't change what the code means
+            let dialect = Dialect::standard();
+            let (before, _) = crate::tokenize_statements(code, &dialect);
+            let (after, _) = crate::tokenize_statements(&once, &dialect);
+            let describe = |tokens: Vec<crate::Token>| -> Vec<String> {
+                tokens
+                    .iter()
+                    .map(|t| format!("{:?} {} {:?}", t.op, t.name, t.args))
+                    .collect()
+            };
+            assert_eq!(describe(before), describe(after));
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// Fuzz targets in the style of cargo-fuzz: every target gets arbitrary bytes and has to survive
+// them. A mott error is a fine answer to garbage, a Rust panic is a bug.
+//
+// `cargo test fuzz` runs every target over its corpus in `fuzz/corpus/<target>/` and then over
+// inputs made by mutating the corpus. Everything runs offline on a stable compiler. Longer runs:
+//
+//   MOTTS_FUZZ_ITERATIONS=1000000 MOTTS_FUZZ_SEED=7 cargo test --release fuzz -- --nocapture
+//
+// Inputs which panic are written to `fuzz/artifacts/<target>/` and the test fails. Once the panic
+// is fixed, move the input into the corpus so it stays fixed.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::console::BufferConsole;
+use crate::dialect::Dialect;
+use crate::header::split_header;
+use crate::highlight;
+use crate::limits::Limits;
+use crate::numerals::Language;
+use crate::source::locate_statements;
+use crate::{create_labels, execute_code_tokens, exit_token, parse_text_number, ExecutionConfig, Operation, Token};
+
+// Inputs are cut off here, longer ones don't find anything new
+const MAX_INPUT_LEN: usize = 4096;
+// Mutated inputs per target in a normal `cargo test`
+const DEFAULT_ITERATIONS: u64 = 300;
+
+// Splitting and tokenizing, with and without a header
+fn tokenize(data: &[u8]) {
+    let code = String::from_utf8_lossy(data);
+    let dialect = Dialect::standard();
+    locate_statements(&code, &dialect);
+    if let Ok((_, code)) = split_header(&code) {
+        locate_statements(&code, &dialect);
+    }
+}
+
+// The words of a number, in every language
+fn number(data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    let words: Vec<String> = text.split(' ').map(String::from).collect();
+    for language in [Language::English, Language::French, Language::German, Language::Spanish] {
+        let _ = parse_text_number(words.clone(), language.numerals());
+    }
+}
+
+// Highlighting as a terminal and as an HTML page
+fn highlight(data: &[u8]) {
+    let code = String::from_utf8_lossy(data);
+    let dialect = Dialect::standard();
+    let _ = highlight::to_ansi(&code, &dialect, true);
+    let _ = highlight::to_html(&code, &dialect, "fuzz");
+}
+
+// Runs the program, in the sandbox and with small limits.
+// Everything after the first NUL byte is the input of the program.
+fn execute(data: &[u8]) {
+    let (program, input) = match data.iter().position(|b| *b == 0) {
+        Some(n) => (&data[..n], &data[n + 1..]),
+        None => (data, &[][..]),
+    };
+    let (header, code) = match split_header(&String::from_utf8_lossy(program)) {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+    let dialect = Dialect::standard();
+
+    // Statements which don't compile are run as well, like `tokenize_statements` would tokenize them
+    let mut tokens: Vec<Token> = locate_statements(&code, &dialect)
+        .0
+        .into_iter()
+        .map(|s| {
+            s.token.unwrap_or(Token {
+                op: Operation::Invalid,
+                nargs: 0,
+                args: vec![],
+                name: String::from("Invalid!"),
+            })
+        })
+        .collect();
+    tokens.push(exit_token());
+
+    let labels: HashMap<String, usize> = create_labels(tokens.clone());
+    let config = ExecutionConfig {
+        numbers: header.numbers.unwrap_or_default(),
+        program_args: vec![String::from("first"), String::from("2")],
+        limits: Limits {
+            max_statements: Some(10_000),
+            timeout: Some(Duration::from_secs(1)),
+            max_variables: Some(1000),
+            max_string_bytes: Some(1 << 16),
+        },
+        sandbox: true,
+        ..Default::default()
+    };
+    let mut console = BufferConsole::new(&String::from_utf8_lossy(input));
+    let _ = execute_code_tokens(tokens, labels, &config, &mut console);
+}
+
+// Words which mean something to motts, so mutations reach past the tokenizer
+static DICTIONARY: &[&str] = &[
+    "P", "i", "Ad", "su", "Mul", "div", "vari", "Equal", "lower", "Greate", "labels", "Goodbye",
+    "homeenv", "readfile", "linefiles", "Includeit", "zero", "One", "two", "nine", "comma", "minus",
+    "un", "virgule", "moins", "eins", "komma", "uno", "coma", "newl", "argc", "argv", "eof", ".",
+    " ", "  ", "\n", "//", "#numbers fr\n", "#scope global\n", "\0",
+];
+
+// xorshift64, good enough to pick mutations and reproducible with the same seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // A number in `0..n` (`n` > 0)
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn mutate(input: &[u8], corpus: &[Vec<u8>], rng: &mut Rng) -> Vec<u8> {
+    let mut out: Vec<u8> = input.to_vec();
+    for _ in 0..=rng.below(4) {
+        let at: usize = rng.below(out.len() + 1);
+        match rng.below(6) {
+            0 | 1 => {
+                let word: &str = DICTIONARY[rng.below(DICTIONARY.len())];
+                out.splice(at..at, word.bytes());
+            }
+            2 => out.insert(at, rng.next() as u8),
+            3 => {
+                let end: usize = (at + rng.below(16)).min(out.len());
+                out.drain(at..end);
+            }
+            4 => {
+                let end: usize = (at + rng.below(32)).min(out.len());
+                let copy: Vec<u8> = out[at..end].to_vec();
+                out.splice(at..at, copy);
+            }
+            _ => {
+                // The start of this input with the end of another one
+                let other: &[u8] = &corpus[rng.below(corpus.len())];
+                let from: usize = rng.below(other.len() + 1);
+                out.truncate(at);
+                out.extend_from_slice(&other[from..]);
+            }
+        }
+    }
+    out.truncate(MAX_INPUT_LEN);
+    out
+}
+
+fn env_number(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|n| n.parse().ok()).unwrap_or(default)
+}
+
+fn fuzz_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz")
+}
+
+fn read_corpus(target: &str) -> Vec<Vec<u8>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(fuzz_dir().join("corpus").join(target))
+        .unwrap()
+        .map(|n| n.unwrap().path())
+        .filter(|n| n.is_file())
+        .collect();
+    files.sort();
+    files.into_iter().map(|n| fs::read(n).unwrap()).collect()
+}
+
+// Writes the input which panicked, returns where
+fn save_artifact(target: &str, input: &[u8]) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    let dir: PathBuf = fuzz_dir().join("artifacts").join(target);
+    fs::create_dir_all(&dir).unwrap();
+    let path: PathBuf = dir.join(format!("crash-{:016x}", hasher.finish()));
+    fs::write(&path, input).unwrap();
+    path
+}
+
+// The corpus, then mutations of it. Panics with the saved inputs if the target panicked.
+fn run_target(target: &str, fuzz: fn(&[u8])) {
+    let corpus: Vec<Vec<u8>> = read_corpus(target);
+    assert!(!corpus.is_empty(), "The corpus of `{}` is empty", target);
+    let iterations: u64 = env_number("MOTTS_FUZZ_ITERATIONS", DEFAULT_ITERATIONS);
+    // xorshift gets stuck on zero
+    let mut rng = Rng(env_number("MOTTS_FUZZ_SEED", 1).max(1));
+
+    let mut crashes: Vec<PathBuf> = vec![];
+    let mut check = |input: &[u8]| {
+        if panic::catch_unwind(AssertUnwindSafe(|| fuzz(input))).is_err() {
+            crashes.push(save_artifact(target, input));
+        }
+    };
+    for input in &corpus {
+        check(input);
+    }
+    for _ in 0..iterations {
+        let input: Vec<u8> = mutate(&corpus[rng.below(corpus.len())], &corpus, &mut rng);
+        check(&input);
+    }
+    assert!(crashes.is_empty(), "`{}` panicked on {:#?}", target, crashes);
+}
+
+#[test]
+fn fuzz_tokenize() {
+    run_target("tokenize", tokenize);
+}
+
+#[test]
+fn fuzz_number() {
+    run_target("number", number);
+}
+
+#[test]
+fn fuzz_highlight() {
+    run_target("highlight", highlight);
+}
+
+#[test]
+fn fuzz_execute() {
+    run_target("execute", execute);
+}
@@ -0,0 +1,144 @@
+// Runs every program in `examples/` and compares what it does with the files next to it:
+//
+//   name.mt       the program
+//   name.stdin    what the program reads (optional)
+//   name.args     program arguments, one per line (optional)
+//   name.stdout   what the program prints
+//   name.stderr   the error the program stopped with (only if it stops with one)
+//   name.exit     the exit status (only if it isn't zero)
+//
+// `MOTTS_BLESS=1 cargo test golden` writes the expected files from what the programs do now.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::console::BufferConsole;
+use crate::dialect::Dialect;
+use crate::modules::load_program;
+use crate::{create_labels, execute_code_tokens, ExecutionConfig};
+
+// What a program did
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    stdout: String,
+    stderr: String,
+    exit: i32,
+}
+
+fn read_optional(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+fn run_example(program: &Path) -> Outcome {
+    let input: String = read_optional(&program.with_extension("stdin")).unwrap_or_default();
+    let program_args: Vec<String> = read_optional(&program.with_extension("args"))
+        .map(|n| n.lines().map(String::from).collect())
+        .unwrap_or_default();
+
+    let dialect = Dialect::standard();
+    let loaded = match load_program(program.to_str().unwrap(), &dialect) {
+        Ok(n) => n,
+        Err(e) => {
+            return Outcome {
+                stdout: String::new(),
+                stderr: format!("Error: {}\n", e),
+                exit: 1,
+            }
+        }
+    };
+    if !loaded.is_valid_code {
+        return Outcome {
+            stdout: String::new(),
+            stderr: String::from("Code can't run as a result of the above errors.\n"),
+            exit: 1,
+        };
+    }
+
+    let labels = create_labels(loaded.tokens.clone());
+    let config = ExecutionConfig {
+        program_args,
+        numbers: loaded.header.numbers.unwrap_or_default(),
+        dialect,
+        ..Default::default()
+    };
+    let mut console = BufferConsole::new(&input);
+    let (stderr, exit) = match execute_code_tokens(loaded.tokens, labels, &config, &mut console) {
+        Ok(status) => (String::new(), status),
+        // Like `print_runtime_error`, without the colours
+        Err(e) => (
+            format!(
+                "Error: `{}` on token {} \n\nThe program terminated because of the above error.\n",
+                e.msg, e.token_num
+            ),
+            1,
+        ),
+    };
+    Outcome {
+        stdout: console.output,
+        stderr,
+        exit,
+    }
+}
+
+// The expectation, `None` if the program was never blessed
+fn expected(program: &Path) -> Option<Outcome> {
+    Some(Outcome {
+        stdout: read_optional(&program.with_extension("stdout"))?,
+        stderr: read_optional(&program.with_extension("stderr")).unwrap_or_default(),
+        exit: match read_optional(&program.with_extension("exit")) {
+            Some(n) => n.trim().parse().expect("The .exit file has to contain a number"),
+            None => 0,
+        },
+    })
+}
+
+// Writes the files for the outcome, leaving out (and removing) the ones which aren't needed
+fn bless(program: &Path, outcome: &Outcome) {
+    let write_or_remove = |extension: &str, content: Option<String>| {
+        let path: PathBuf = program.with_extension(extension);
+        match content {
+            Some(n) => fs::write(path, n).unwrap(),
+            None => {
+                let _ = fs::remove_file(path);
+            }
+        }
+    };
+    write_or_remove("stdout", Some(outcome.stdout.clone()));
+    write_or_remove("stderr", Some(outcome.stderr.clone()).filter(|n| !n.is_empty()));
+    write_or_remove("exit", (outcome.exit != 0).then(|| format!("{}\n", outcome.exit)));
+}
+
+#[test]
+fn golden_examples() {
+    let examples: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let blessing: bool = std::env::var("MOTTS_BLESS").is_ok_and(|n| !n.is_empty() && n != "0");
+
+    // Files in subdirectories are only included by the examples
+    let mut programs: Vec<PathBuf> = fs::read_dir(&examples)
+        .unwrap()
+        .map(|n| n.unwrap().path())
+        .filter(|n| n.is_file() && n.extension().is_some_and(|e| e == "mt"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty());
+
+    let mut failures: Vec<String> = vec![];
+    for program in &programs {
+        let outcome: Outcome = run_example(program);
+        if blessing {
+            bless(program, &outcome);
+            continue;
+        }
+        match expected(program) {
+            Some(n) if n == outcome => (),
+            Some(n) => failures.push(format!(
+                "{}:\nexpected {:#?}\nbut got  {:#?}",
+                program.display(),
+                n,
+                outcome
+            )),
+            None => failures.push(format!("{}: no .stdout file (run with MOTTS_BLESS=1)", program.display())),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
@@ -0,0 +1,146 @@
+// A mott file can start with header lines, which configure how the rest of it is read:
+//
+//   #numbers french
+//   #scope global
+//   #deny unused-variable duplicate-label
+//   zero Un deux.
+//
+// Every header line starts with `#`, followed by the setting and its value.
+// The header ends at the first line not starting with `#`.
+
+use crate::lint::{Level, Lint};
+use crate::numerals::Language;
+
+// Where the variables of an included file live
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Scope {
+    // Shared with all other files (like the variables of the main file)
+    Global,
+    // Only visible in the file itself (the default for included files)
+    Module,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    pub numbers: Option<Language>,
+    pub scope: Option<Scope>,
+    // Lint levels, in the order they are set
+    pub lints: Vec<(Lint, Level)>,
+}
+
+// How many lines (counted like `split_inclusive('\n')`) the header takes: everything up to the
+// last `#` line before the code starts, empty lines in between included
+fn header_len(content: &str) -> usize {
+    let mut len: usize = 0;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let trimmed: &str = line.trim();
+        if trimmed.starts_with('#') {
+            len = i + 1;
+        } else if !trimmed.is_empty() {
+            break;
+        }
+    }
+    len
+}
+
+// The header lines (trimmed) as `split_header` reads them
+pub fn header_lines(content: &str) -> Vec<&str> {
+    content
+        .split_inclusive('\n')
+        .take(header_len(content))
+        .map(str::trim)
+        .filter(|l| l.starts_with('#'))
+        .collect()
+}
+
+// Splits the header off of the source code. The header lines are replaced by empty lines,
+// so line numbers in the returned code still match the file.
+pub fn split_header(content: &str) -> Result<(Header, String), String> {
+    let mut header = Header::default();
+    let mut code = String::new();
+    let header_len: usize = header_len(content);
+
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let trimmed: &str = line.trim();
+        if i < header_len && trimmed.starts_with('#') {
+            parse_header_line(&trimmed[1..], &mut header)?;
+            if line.ends_with('\n') {
+                code.push('\n');
+            }
+            continue;
+        }
+        code.push_str(line);
+    }
+    Ok((header, code))
+}
+
+fn parse_header_line(line: &str, header: &mut Header) -> Result<(), String> {
+    let mut words = line.split_whitespace();
+    let key: &str = words.next().unwrap_or("");
+    let value: &str = words.next().unwrap_or("");
+
+    match key {
+        "numbers" => match Language::from_name(value) {
+            Some(n) => header.numbers = Some(n),
+            None => return Err(format!("Unknown language `{}` for numbers.", value)),
+        },
+        "scope" => match value {
+            "global" => header.scope = Some(Scope::Global),
+            "module" => header.scope = Some(Scope::Module),
+            _ => return Err(format!("Scope has to be global or module, not `{}`.", value)),
+        },
+        "allow" | "warn" | "deny" => {
+            let level: Level = Level::from_name(key).unwrap_or(Level::Warn);
+            for name in std::iter::once(value).chain(words) {
+                match Lint::from_name(name) {
+                    Some(n) => header.lints.push((n, level)),
+                    None => return Err(format!("Unknown lint `{}` (the lints are {}).", name, Lint::names())),
+                }
+            }
+        }
+        _ => return Err(format!("Unknown header setting `{}`.", key)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_is_stripped() {
+        let (header, code) = split_header("#numbers fr\n\nzero Un.\n# not a header.\n").unwrap();
+        assert_eq!(header.numbers, Some(Language::French));
+        assert_eq!(code, "\n\nzero Un.\n# not a header.\n");
+
+        let (header, code) = split_header("zero One.").unwrap();
+        assert_eq!(header, Header::default());
+        assert_eq!(code, "zero One.");
+
+        let (header, _) = split_header("#scope global\n#numbers de\n").unwrap();
+        assert_eq!(header.scope, Some(Scope::Global));
+        assert_eq!(header.numbers, Some(Language::German));
+
+        assert!(split_header("#numbers klingon\n").is_err());
+        assert!(split_header("#scope everywhere\n").is_err());
+        assert!(split_header("#colour blue\n").is_err());
+
+        let content = "#numbers french\n\n  #scope global\nnumb Un.\n# not a header.\n";
+        assert_eq!(header_lines(content), vec!["#numbers french", "#scope global"]);
+        let (header, code) = split_header(content).unwrap();
+        assert_eq!(header.scope, Some(Scope::Global));
+        assert_eq!(code, "\n\n\nnumb Un.\n# not a header.\n");
+
+        let (header, _) = split_header("#deny unused-label self-comparison\n#allow unused-label\n").unwrap();
+        assert_eq!(
+            header.lints,
+            vec![
+                (Lint::UnusedLabel, Level::Deny),
+                (Lint::SelfComparison, Level::Deny),
+                (Lint::UnusedLabel, Level::Allow),
+            ]
+        );
+        assert!(split_header("#deny typos\n").is_err());
+        assert!(split_header("#warn\n").is_err());
+    }
+}
@@ -0,0 +1,397 @@
+// `motts highlight`: shows what every word of a program does, as colours in the terminal or
+// as a standalone HTML page (where hovering over a word explains it).
+//
+// Words are classified by what they mean in the program, not by how they look:
+// the operation word by its `Operation`, variables, labels, spelled out numbers, the text of
+// strings, comments and the header.
+
+use std::fmt::Write;
+use std::fs;
+
+use colored::*;
+
+use crate::dialect::Dialect;
+use crate::header::split_header;
+use crate::source::{locate_statements, Position, Statement};
+use crate::symbols::{label_ref, variable_refs, NamePosition};
+use crate::{find_comment, Operation, Token};
+
+pub struct HighlightOptions {
+    pub html: bool,
+    pub dialect: Option<String>,
+    pub file: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Class {
+    // Spaces, dots and words without a special meaning
+    Plain,
+    Operation(Operation),
+    // A word which should be an operation, but isn't
+    Invalid,
+    Variable,
+    Label,
+    Number,
+    Text,
+    Comment,
+    Header,
+}
+
+impl Class {
+    // Name of the CSS class
+    fn css(self) -> String {
+        match self {
+            Class::Operation(op) => format!("op-{}", format!("{:?}", op).to_lowercase()),
+            Class::Plain => String::from("plain"),
+            Class::Invalid => String::from("invalid"),
+            Class::Variable => String::from("variable"),
+            Class::Label => String::from("label"),
+            Class::Number => String::from("number"),
+            Class::Text => String::from("string"),
+            Class::Comment => String::from("comment"),
+            Class::Header => String::from("header"),
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Class::Operation(op) => format!("{:?} operation", op),
+            Class::Plain => String::new(),
+            Class::Invalid => String::from("not an operation"),
+            Class::Variable => String::from("variable"),
+            Class::Label => String::from("label"),
+            Class::Number => String::from("spelled out number"),
+            Class::Text => String::from("string"),
+            Class::Comment => String::from("comment"),
+            Class::Header => String::from("header"),
+        }
+    }
+
+    fn paint(self, text: &str) -> ColoredString {
+        match self {
+            Class::Operation(Operation::Var) => text.yellow().bold(),
+            Class::Operation(Operation::Add | Operation::Sub | Operation::Mul | Operation::Div) => {
+                text.magenta().bold()
+            }
+            Class::Operation(Operation::Branch | Operation::Label | Operation::Exit) => {
+                text.red().bold()
+            }
+            Class::Operation(_) => text.blue().bold(),
+            Class::Invalid => text.white().on_red(),
+            Class::Variable => text.yellow(),
+            Class::Label => text.red(),
+            Class::Number => text.cyan(),
+            Class::Text => text.green(),
+            Class::Comment | Class::Header => text.bright_black().italic(),
+            Class::Plain => text.normal(),
+        }
+    }
+}
+
+// The classes of the args of the token (args without a special meaning are left out)
+fn arg_classes(t: &Token) -> Vec<(usize, Class)> {
+    let mut classes: Vec<(usize, Class)> = vec![];
+    for r in variable_refs(t) {
+        if let NamePosition::Arg(i) = r.position {
+            classes.push((i, Class::Variable));
+        }
+    }
+    if let Some(NamePosition::Arg(i)) = label_ref(t) {
+        classes.push((i, Class::Label));
+    }
+
+    let first_is_uppercase: bool = t
+        .args
+        .first()
+        .and_then(|a| a.chars().next())
+        .is_some_and(char::is_uppercase);
+    let value_class: Class = if first_is_uppercase { Class::Number } else { Class::Text };
+    match t.op {
+        Operation::Var => classes.extend((0..t.nargs).map(|i| (i, value_class))),
+        Operation::Exit if first_is_uppercase => classes.extend((0..t.nargs).map(|i| (i, Class::Number))),
+        // The name of the environment variable and the path
+        Operation::Env | Operation::Include if t.nargs > 0 => classes.push((0, Class::Text)),
+        _ => (),
+    }
+    classes
+}
+
+fn word_classes(statement: &Statement) -> Vec<Class> {
+    let mut classes: Vec<Class> = vec![Class::Plain; statement.words.len()];
+    match &statement.token {
+        Ok(t) => {
+            classes[0] = Class::Operation(t.op);
+            for (i, class) in arg_classes(t) {
+                classes[i + 1] = class;
+            }
+        }
+        Err(_) => classes[0] = Class::Invalid,
+    }
+    classes
+}
+
+// The class of every character, line by line
+pub fn classify(content: &str, dialect: &Dialect) -> Result<Vec<Vec<Class>>, String> {
+    let (_, code) = split_header(content)?;
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut classes: Vec<Vec<Class>> = lines
+        .iter()
+        .map(|l| vec![Class::Plain; l.chars().count()])
+        .collect();
+
+    let mut set = |start: Position, end: Position, class: Class| {
+        for (line, line_classes) in classes.iter_mut().enumerate().take(end.line + 1).skip(start.line) {
+            let from: usize = if line == start.line { start.column } else { 0 };
+            let to: usize = if line == end.line { end.column } else { line_classes.len() };
+            for c in &mut line_classes[from..to] {
+                *c = class;
+            }
+        }
+    };
+
+    // Header lines were replaced by empty lines, comments are cut off at their line's end
+    let mut at_statement_start: bool = true;
+    for (index, (line, code_line)) in lines.iter().zip(code.split('\n')).enumerate() {
+        let length: usize = line.chars().count();
+        if code_line.is_empty() && line.trim_start().starts_with('#') {
+            set(Position { line: index, column: 0 }, Position { line: index, column: length }, Class::Header);
+        } else if let Some(n) = find_comment(line, &mut at_statement_start) {
+            let column: usize = line[..n].chars().count();
+            set(Position { line: index, column }, Position { line: index, column: length }, Class::Comment);
+        }
+    }
+
+    let (statements, _) = locate_statements(&code, dialect);
+    for statement in &statements {
+        for (word, class) in statement.words.iter().zip(word_classes(statement)) {
+            set(word.start, word.end, class);
+        }
+    }
+    Ok(classes)
+}
+
+// Splits the line into runs of characters with the same class
+fn runs<'a>(line: &'a str, classes: &[Class]) -> Vec<(&'a str, Class)> {
+    let mut runs: Vec<(&str, Class)> = vec![];
+    let mut start: usize = 0;
+    let offsets: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
+    for i in 1..=offsets.len() {
+        if i == offsets.len() || classes[i] != classes[i - 1] {
+            let end: usize = offsets.get(i).copied().unwrap_or(line.len());
+            runs.push((&line[start..end], classes[i - 1]));
+            start = end;
+        }
+    }
+    runs
+}
+
+// The escape codes of the painted text, written out whatever `colored` thinks of the terminal
+fn escape(painted: &ColoredString) -> String {
+    let mut codes: Vec<String> = vec![];
+    for (style, code) in [(Styles::Bold, "1"), (Styles::Italic, "3")] {
+        if painted.style().contains(style) {
+            codes.push(String::from(code));
+        }
+    }
+    codes.extend(painted.bgcolor().map(|c| c.to_bg_str().into_owned()));
+    codes.extend(painted.fgcolor().map(|c| c.to_fg_str().into_owned()));
+    if codes.is_empty() {
+        return painted.to_string();
+    }
+    format!("\x1B[{}m{}\x1B[0m", codes.join(";"), &**painted)
+}
+
+// Without `color` the text is left as it is
+pub fn to_ansi(content: &str, dialect: &Dialect, color: bool) -> Result<String, String> {
+    let classes = classify(content, dialect)?;
+    let lines: Vec<String> = content
+        .split('\n')
+        .zip(&classes)
+        .map(|(line, classes)| {
+            runs(line, classes)
+                .into_iter()
+                .map(|(text, class)| if color { escape(&class.paint(text)) } else { String::from(text) })
+                .collect()
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+static HTML_STYLE: &str = "
+body { background: #1e1e1e; color: #d4d4d4; font-family: monospace; }
+pre { font-size: 14px; line-height: 1.4; }
+.op-var { color: #e5c07b; font-weight: bold; }
+.op-add, .op-sub, .op-mul, .op-div { color: #c678dd; font-weight: bold; }
+.op-branch, .op-label, .op-exit { color: #e06c75; font-weight: bold; }
+.op-print, .op-input, .op-env, .op-file, .op-include { color: #61afef; font-weight: bold; }
+.invalid { background: #be5046; color: #ffffff; }
+.variable { color: #e5c07b; }
+.label { color: #e06c75; }
+.number { color: #56b6c2; }
+.string { color: #98c379; }
+.comment, .header { color: #7f848e; font-style: italic; }
+.legend span { margin-right: 1em; }
+";
+
+pub fn to_html(content: &str, dialect: &Dialect, title: &str) -> Result<String, String> {
+    let classes = classify(content, dialect)?;
+    let mut body = String::new();
+    let mut used: Vec<Class> = vec![];
+
+    for (index, (line, classes)) in content.split('\n').zip(&classes).enumerate() {
+        if index > 0 {
+            body.push('\n');
+        }
+        for (text, class) in runs(line, classes) {
+            if class == Class::Plain || text.trim().is_empty() {
+                body.push_str(&escape_html(text));
+                continue;
+            }
+            if !used.contains(&class) {
+                used.push(class);
+            }
+            let _ = write!(
+                body,
+                "<span class=\"{}\" title=\"{}\">{}</span>",
+                class.css(),
+                class.describe(),
+                escape_html(text)
+            );
+        }
+    }
+
+    let legend: Vec<String> = used
+        .iter()
+        .map(|c| format!("<span class=\"{}\">{}</span>", c.css(), c.describe()))
+        .collect();
+    Ok(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<pre>{}</pre>\n<p class=\"legend\">{}</p>\n</body>\n</html>\n",
+        escape_html(title),
+        HTML_STYLE,
+        body,
+        legend.join("\n")
+    ))
+}
+
+// Prints the highlighted file, returns the exit status
+pub fn run_highlight(options: &HighlightOptions) -> i32 {
+    let dialect: Dialect = match Dialect::load_or_standard(options.dialect.as_deref()) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+    let content: String = match fs::read_to_string(&options.file) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} Couldn't read `{}`: {}", "Error:".red(), options.file, e);
+            return 1;
+        }
+    };
+
+    let result = if options.html {
+        to_html(&content, &dialect, &options.file)
+    } else {
+        to_ansi(&content, &dialect, colored::control::SHOULD_COLORIZE.should_colorize())
+    };
+    match result {
+        Ok(n) => {
+            print!("{}", n);
+            0
+        }
+        Err(e) => {
+            println!("{} {}: {}", "Error:".red(), options.file, e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The class of every word (split on spaces) of every line
+    fn classes_of(code: &str) -> Vec<Vec<(String, Class)>> {
+        let classes = classify(code, &Dialect::standard()).unwrap();
+        code.split('\n')
+            .zip(&classes)
+            .map(|(line, classes)| {
+                runs(line, classes)
+                    .into_iter()
+                    .map(|(text, class)| (text.trim().trim_end_matches('.').to_string(), class))
+                    .filter(|(text, _)| !text.is_empty())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn words_are_classified() {
+        let code = "#numbers english\nnumb Four two. text hi there.\nlabels. // loop\nAd numb numb numb.\nLower numb numb labels.\nInvalidness.";
+        let words = |s: &[(&str, Class)]| -> Vec<(String, Class)> {
+            s.iter().map(|(t, c)| (t.to_string(), *c)).collect()
+        };
+        assert_eq!(
+            classes_of(code),
+            vec![
+                words(&[("#numbers english", Class::Header)]),
+                words(&[
+                    ("numb", Class::Operation(Operation::Var)),
+                    ("Four", Class::Number),
+                    ("two", Class::Number),
+                    ("text", Class::Operation(Operation::Var)),
+                    ("hi", Class::Text),
+                    ("there", Class::Text),
+                ]),
+                words(&[("labels", Class::Operation(Operation::Label)), ("// loop", Class::Comment)]),
+                words(&[
+                    ("Ad", Class::Operation(Operation::Add)),
+                    ("numb", Class::Variable),
+                    ("numb", Class::Variable),
+                    ("numb", Class::Variable),
+                ]),
+                words(&[
+                    ("Lower", Class::Operation(Operation::Branch)),
+                    ("numb", Class::Variable),
+                    ("numb", Class::Variable),
+                    ("labels", Class::Label),
+                ]),
+                words(&[("Invalidness", Class::Invalid)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn env_and_include_without_arguments() {
+        assert_eq!(
+            classes_of("fetches.\nIncluding."),
+            vec![
+                vec![(String::from("fetches"), Class::Operation(Operation::Env))],
+                vec![(String::from("Including"), Class::Operation(Operation::Include))],
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_html_and_ansi() {
+        let dialect = Dialect::standard();
+        let html = to_html("text <b>.\nP text. // done", &dialect, "a & b").unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>a &amp; b</title>"));
+        assert!(html.contains("<span class=\"string\" title=\"string\">&lt;b&gt;</span>"));
+        assert!(html.contains("<span class=\"op-print\" title=\"Print operation\">P</span> <span class=\"variable\""));
+        assert!(html.contains("<span class=\"comment\" title=\"comment\">// done</span>"));
+
+        let ansi = to_ansi("P text. // done", &dialect, true).unwrap();
+        assert_eq!(ansi, "\x1B[1;34mP\x1B[0m \x1B[33mtext\x1B[0m. \x1B[3;90m// done\x1B[0m");
+        assert_eq!(to_ansi("P text.", &dialect, false).unwrap(), "P text.");
+    }
+}
@@ -0,0 +1,177 @@
+// Limits for running programs which can't be trusted (e.g. submissions of students):
+//
+//   motts --max-statements 1000000 --timeout 2 --max-variables 100 --max-string-bytes 65536 --sandbox prog.mt
+//
+// A program going over a limit stops with a `LimitError` on the token which went over it.
+// In the sandbox, file operations and reading environment variables stop the program with a
+// `SandboxError` and files can only include files from their own directory.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::{Type, Variable};
+
+#[derive(Clone, Default)]
+pub struct Limits {
+    // How many statements can be run in total
+    pub max_statements: Option<u64>,
+    // Checked before every statement (a waiting Input isn't interrupted)
+    pub timeout: Option<Duration>,
+    // Variables created by the program (predefined ones and program arguments don't count)
+    pub max_variables: Option<usize>,
+    // The length of all strings stored in variables together
+    pub max_string_bytes: Option<usize>,
+}
+
+#[allow(clippy::enum_variant_names)] // The names are shown as the error message
+#[derive(Debug)]
+pub enum LimitError {
+    StatementLimitReached,
+    TimeLimitReached,
+    VariableLimitReached,
+    StringLimitReached,
+}
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug)]
+pub enum SandboxError {
+    FileAccessDenied,
+    EnvAccessDenied,
+}
+impl fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Keeps track of a running program
+pub struct Limiter<'a> {
+    limits: &'a Limits,
+    started: Instant,
+    statements: u64,
+    // Variables which existed before the program started
+    predefined: usize,
+}
+
+impl Limiter<'_> {
+    pub fn new(limits: &Limits, predefined: usize) -> Limiter<'_> {
+        Limiter {
+            limits,
+            started: Instant::now(),
+            statements: 0,
+            predefined,
+        }
+    }
+
+    // Called before every statement
+    pub fn before_statement(&mut self) -> Result<(), LimitError> {
+        self.statements += 1;
+        if self.limits.max_statements.is_some_and(|max| self.statements > max) {
+            return Err(LimitError::StatementLimitReached);
+        }
+        if self.limits.timeout.is_some_and(|max| self.started.elapsed() > max) {
+            return Err(LimitError::TimeLimitReached);
+        }
+        Ok(())
+    }
+
+    // Called after every statement, with the variables it might have changed
+    pub fn after_statement(&self, vars: &HashMap<String, Variable>) -> Result<(), LimitError> {
+        if let Some(max) = self.limits.max_variables {
+            if vars.len().saturating_sub(self.predefined) > max {
+                return Err(LimitError::VariableLimitReached);
+            }
+        }
+        if let Some(max) = self.limits.max_string_bytes {
+            let total: usize = vars
+                .values()
+                .map(|v| match &v.data {
+                    Type::String(s) => s.len(),
+                    Type::Number(_) => 0,
+                })
+                .sum();
+            if total > max {
+                return Err(LimitError::StringLimitReached);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+    use crate::dialect::Dialect;
+    use crate::{compile_mt_code, create_labels, execute_code_tokens, ExecutionConfig, RuntimeError};
+
+    fn run_limited(code: &str, input: &str, limits: Limits, sandbox: bool) -> Result<i32, RuntimeError> {
+        let (tokens, is_valid_code) = compile_mt_code(code, &Dialect::standard());
+        assert!(is_valid_code);
+        let config = ExecutionConfig {
+            limits,
+            sandbox,
+            ..Default::default()
+        };
+        let labels = create_labels(tokens.clone());
+        execute_code_tokens(tokens, labels, &config, &mut BufferConsole::new(input))
+    }
+
+    #[test]
+    fn limits_stop_programs() {
+        let endless = "ones One. labels. Ad ones ones ones. Equal ones ones labels.";
+        let e = run_limited(
+            endless,
+            "",
+            Limits {
+                max_statements: Some(100),
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(e.msg, LimitError::StatementLimitReached.to_string());
+
+        let e = run_limited(
+            endless,
+            "",
+            Limits {
+                timeout: Some(Duration::from_millis(20)),
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(e.msg, LimitError::TimeLimitReached.to_string());
+
+        // Predefined variables don't count
+        let limits = Limits {
+            max_variables: Some(2),
+            ..Default::default()
+        };
+        assert!(run_limited("aaaa One. bbbb One. P newl argc.", "", limits.clone(), false).is_ok());
+        let e = run_limited("aaaa One. bbbb One. cccc One.", "", limits, false).unwrap_err();
+        assert_eq!((e.token_num, e.msg.as_str()), (2, "VariableLimitReached"));
+
+        let limits = Limits {
+            max_string_bytes: Some(20),
+            ..Default::default()
+        };
+        let e = run_limited("i str line. i str more.", "0123456789\n0123456789abc\n", limits, false).unwrap_err();
+        assert_eq!((e.token_num, e.msg.as_str()), (1, "StringLimitReached"));
+    }
+
+    #[test]
+    fn sandbox_denies_files_and_env() {
+        let e = run_limited("path /etc/passwd. readfile path cont.", "", Limits::default(), true).unwrap_err();
+        assert_eq!((e.token_num, e.msg.as_str()), (1, "FileAccessDenied"));
+        let e = run_limited("homeenv HOME home.", "", Limits::default(), true).unwrap_err();
+        assert_eq!(e.msg, SandboxError::EnvAccessDenied.to_string());
+        assert!(run_limited("homeenv HOME home.", "", Limits::default(), false).is_ok());
+    }
+}
@@ -0,0 +1,353 @@
+// Lints: things in a program which are allowed, but most likely a mistake.
+//
+// Every lint has a name and a level. `allow` ignores it, `warn` prints a warning and `deny`
+// prints an error and doesn't run the program. Levels are set on the commandline or in the
+// header of the file (which wins over the commandline):
+//
+//   motts --deny duplicate-label --allow unused-variable prog.mt
+//
+//   #deny duplicate-label
+//   #allow unused-variable unused-label
+//
+// The lints only look at the code, nothing is run.
+
+use std::collections::{HashMap, HashSet};
+
+use colored::*;
+
+use crate::cfg::{Cfg, EdgeKind};
+use crate::dialect::{BranchCondition, Dialect};
+use crate::symbols::{label_ref, name_at, variable_refs};
+use crate::types::infer;
+use crate::{is_interpreter_variable, print_compile_warning, Operation, Token};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Lint {
+    LabelArguments,
+    DuplicateLabel,
+    UnusedLabel,
+    UnusedVariable,
+    UnreachableCode,
+    SelfComparison,
+    OverwrittenPredefined,
+    TypeConflict,
+    StringComparison,
+    StringArithmetic,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+// Every lint with its name and default level
+static LINTS: &[(Lint, &str, Level)] = &[
+    (Lint::LabelArguments, "label-arguments", Level::Warn),
+    (Lint::DuplicateLabel, "duplicate-label", Level::Warn),
+    (Lint::UnusedLabel, "unused-label", Level::Warn),
+    (Lint::UnusedVariable, "unused-variable", Level::Warn),
+    (Lint::UnreachableCode, "unreachable-code", Level::Warn),
+    (Lint::SelfComparison, "self-comparison", Level::Warn),
+    (Lint::OverwrittenPredefined, "overwritten-predefined", Level::Warn),
+    (Lint::TypeConflict, "type-conflict", Level::Warn),
+    (Lint::StringComparison, "string-comparison", Level::Warn),
+    (Lint::StringArithmetic, "string-arithmetic", Level::Warn),
+];
+
+impl Lint {
+    pub fn from_name(name: &str) -> Option<Lint> {
+        LINTS.iter().find(|(_, n, _)| *n == name).map(|(lint, _, _)| *lint)
+    }
+
+    pub fn name(self) -> &'static str {
+        LINTS.iter().find(|(lint, _, _)| *lint == self).map_or("", |(_, n, _)| n)
+    }
+
+    fn default_level(self) -> Level {
+        LINTS.iter().find(|(lint, _, _)| *lint == self).map_or(Level::Warn, |(_, _, level)| *level)
+    }
+
+    // The names of all lints, for error messages
+    pub fn names() -> String {
+        LINTS.iter().map(|(_, n, _)| *n).collect::<Vec<&str>>().join(", ")
+    }
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+// The level of every lint, the default levels unless set otherwise
+#[derive(Debug, Default, Clone)]
+pub struct LintLevels {
+    levels: HashMap<Lint, Level>,
+}
+
+impl LintLevels {
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or_else(|| lint.default_level())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub level: Level,
+    // The token index of the statement
+    pub token: usize,
+    pub message: String,
+}
+
+struct Checker<'a> {
+    tokens: &'a [Token],
+    dialect: &'a Dialect,
+    levels: &'a LintLevels,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn add(&mut self, lint: Lint, token: usize, message: String) {
+        let level: Level = self.levels.level(lint);
+        if level != Level::Allow {
+            self.diagnostics.push(Diagnostic { lint, level, token, message });
+        }
+    }
+
+    // Whether the branch compares a variable with itself, and with which condition
+    fn self_comparison(&self, t: &Token) -> Option<BranchCondition> {
+        if t.op != Operation::Branch || t.args.len() < 2 || t.args[0] != t.args[1] {
+            return None;
+        }
+        self.dialect.branch_condition(t.name.chars().next()?)
+    }
+
+    fn labels(&mut self) {
+        let jumped_to: HashSet<&String> = self
+            .tokens
+            .iter()
+            .filter_map(|t| name_at(t, label_ref(t)?))
+            .collect();
+        let mut defined: HashSet<&String> = HashSet::new();
+        for (i, t) in self.tokens.iter().enumerate().filter(|(_, t)| t.op == Operation::Label) {
+            if t.nargs > 0 {
+                self.add(Lint::LabelArguments, i, String::from("You have a label with more than zero arguments."));
+            }
+            if !defined.insert(&t.name) {
+                self.add(
+                    Lint::DuplicateLabel,
+                    i,
+                    format!("You are defining the label `{}` more than once!", t.name),
+                );
+            } else if !jumped_to.contains(&t.name) {
+                self.add(Lint::UnusedLabel, i, format!("Nothing jumps to the label `{}`.", t.name));
+            }
+        }
+    }
+
+    fn variables(&mut self) {
+        let mut read: HashSet<&String> = HashSet::new();
+        // The first write of every variable, in the order of the code
+        let mut written: Vec<(usize, &String)> = vec![];
+        for (i, t) in self.tokens.iter().enumerate() {
+            for r in variable_refs(t) {
+                let name: &String = match name_at(t, r.position) {
+                    Some(n) => n,
+                    None => continue,
+                };
+                if !r.is_write {
+                    read.insert(name);
+                    continue;
+                }
+                let predefined: bool = is_interpreter_variable(name)
+                    || self.dialect.predefined_variables.iter().any(|(n, _)| n == name);
+                if predefined {
+                    self.add(
+                        Lint::OverwrittenPredefined,
+                        i,
+                        format!("This overwrites the predefined variable `{}`.", name),
+                    );
+                } else if written.iter().all(|(_, n)| *n != name) {
+                    written.push((i, name));
+                }
+            }
+        }
+        for (i, name) in written {
+            if !read.contains(name) {
+                self.add(Lint::UnusedVariable, i, format!("The variable `{}` is never read.", name));
+            }
+        }
+    }
+
+    fn branches(&mut self) {
+        for (i, t) in self.tokens.iter().enumerate() {
+            // `Equal onee onee label` is how a mott program jumps unconditionally, that one is fine
+            match self.self_comparison(t) {
+                Some(BranchCondition::Less) | Some(BranchCondition::Greater) => self.add(
+                    Lint::SelfComparison,
+                    i,
+                    format!("`{}` is compared with itself, so this branch never jumps.", t.args[0]),
+                ),
+                _ => (),
+            }
+        }
+    }
+
+    fn unreachable(&mut self, labels: &HashMap<String, usize>) {
+        let mut cfg = Cfg::build(self.tokens, labels);
+        // A variable always equals itself (strictly, NaN doesn't, but nobody relies on that)
+        let always_jumps: Vec<bool> = cfg
+            .blocks
+            .iter()
+            .map(|b| self.self_comparison(&self.tokens[b.end - 1]) == Some(BranchCondition::Equal))
+            .collect();
+        // The branch (if any) which would fall through into the block, but always jumps
+        let cut_off_by: Vec<Option<usize>> = (0..cfg.blocks.len())
+            .map(|index| {
+                cfg.predecessors(index)
+                    .find(|e| e.kind == EdgeKind::FallThrough && always_jumps[e.from])
+                    .map(|e| cfg.blocks[e.from].end - 1)
+            })
+            .collect();
+        cfg.edges.retain(|e| !(e.kind == EdgeKind::FallThrough && always_jumps[e.from]));
+
+        let reachable: Vec<bool> = cfg.reachable();
+        for (index, block) in cfg.blocks.iter().enumerate() {
+            // Only the start of unreachable code is reported. The implicit exit at the end is
+            // unreachable after every `Goodbye`.
+            if reachable[index] || (index > 0 && !reachable[index - 1]) || block.start == self.tokens.len() - 1 {
+                continue;
+            }
+            let message: String = match cut_off_by[index] {
+                Some(branch) => format!("This code never runs, the branch on token {} always jumps.", branch),
+                None => String::from("This code never runs."),
+            };
+            self.add(Lint::UnreachableCode, block.start, message);
+        }
+    }
+
+    // Type problems, found by inferring the types of the variables (see `types`)
+    fn types(&mut self, labels: &HashMap<String, usize>) {
+        for problem in infer(self.tokens, labels, self.dialect).problems {
+            self.add(problem.lint, problem.token, problem.message);
+        }
+    }
+}
+
+// Runs every lint which isn't allowed, the diagnostics are sorted by token.
+// `labels` from `create_labels`.
+pub fn check(
+    tokens: &[Token],
+    labels: &HashMap<String, usize>,
+    dialect: &Dialect,
+    levels: &LintLevels,
+) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        tokens,
+        dialect,
+        levels,
+        diagnostics: vec![],
+    };
+    checker.labels();
+    checker.variables();
+    checker.branches();
+    checker.unreachable(labels);
+    checker.types(labels);
+    let mut diagnostics: Vec<Diagnostic> = checker.diagnostics;
+    diagnostics.sort_by_key(|d| d.token);
+    diagnostics
+}
+
+// Prints the diagnostics, returns whether one of them is denied
+pub fn print_diagnostics(diagnostics: &[Diagnostic]) -> bool {
+    for d in diagnostics {
+        let message: String = format!("{} [{}]", d.message, d.lint.name());
+        match d.level {
+            Level::Deny => println!("{} on token {}: {}", "Error".red(), d.token, message),
+            _ => print_compile_warning(d.token, &message),
+        }
+    }
+    diagnostics.iter().any(|d| d.level == Level::Deny)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile_mt_code, create_labels};
+
+    fn lints(code: &str, levels: &LintLevels) -> Vec<(Lint, usize)> {
+        let dialect = Dialect::standard();
+        let (tokens, _) = compile_mt_code(code, &dialect);
+        let labels = create_labels(tokens.clone());
+        check(&tokens, &labels, &dialect, levels).iter().map(|d| (d.lint, d.token)).collect()
+    }
+
+    #[test]
+    fn finds_every_lint() {
+        let code = "onee One. gone Two. spce blank. \
+                    labels. P onee. Equal onee onee finish. P onee. \
+                    labels. Lower onee onee labels. \
+                    finish. unused arg.";
+        assert_eq!(
+            lints(code, &LintLevels::default()),
+            vec![
+                (Lint::UnusedVariable, 1),
+                (Lint::OverwrittenPredefined, 2),
+                (Lint::UnreachableCode, 6),
+                (Lint::DuplicateLabel, 7),
+                (Lint::SelfComparison, 8),
+                (Lint::LabelArguments, 10),
+                (Lint::UnusedLabel, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn gotos_and_loops_are_fine() {
+        let code = "summ Zero. ones One. maxi Four. \
+                    labels. Ad summ ones summ. Lower summ maxi labels. \
+                    Equal ones ones finish. \
+                    finish. P summ. Goodbye.";
+        assert_eq!(lints(code, &LintLevels::default()), vec![]);
+    }
+
+    #[test]
+    fn levels() {
+        let mut levels = LintLevels::default();
+        levels.set(Lint::UnusedVariable, Level::Allow);
+        levels.set(Lint::DuplicateLabel, Level::Deny);
+        let dialect = Dialect::standard();
+        let (tokens, _) = compile_mt_code("gone Two. labels. labels. Equal gone gone labels.", &dialect);
+        let diagnostics = check(&tokens, &create_labels(tokens.clone()), &dialect, &levels);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                lint: Lint::DuplicateLabel,
+                level: Level::Deny,
+                token: 2,
+                message: String::from("You are defining the label `labels` more than once!"),
+            }]
+        );
+
+        let (tokens, _) = compile_mt_code("onee One. Equal onee onee finish. P onee. finish.", &dialect);
+        let diagnostics = check(&tokens, &create_labels(tokens.clone()), &dialect, &LintLevels::default());
+        assert_eq!(diagnostics[0].message, "This code never runs, the branch on token 1 always jumps.");
+
+        assert_eq!(Lint::from_name("self-comparison"), Some(Lint::SelfComparison));
+        assert_eq!(Lint::SelfComparison.name(), "self-comparison");
+        assert_eq!(Lint::from_name("everything"), None);
+        assert_eq!(Level::from_name("deny"), Some(Level::Deny));
+    }
+}
@@ -0,0 +1,1071 @@
+// `motts lsp`: a language server for editors, talking JSON-RPC over stdin/stdout.
+//
+// - diagnostics: compile errors, jumps to labels which don't exist, variables never assigned, ...
+// - hover: which operation a word is (and why: its length and case), what a variable or label is
+// - go to definition and find references for variables and labels
+// - rename, which keeps the names working (a variable created by a Var statement has to stay
+//   a Var word, labels have to stay Label words)
+// - completion of the variables and labels in the file
+//
+// Every file is looked at on its own, included files aren't loaded.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::dialect::{BranchCondition, Dialect};
+use crate::header::split_header;
+use crate::numerals::Language;
+use crate::source::{locate_statements, Position, Statement};
+use crate::symbols::{label_ref, name_at, variable_refs, NamePosition};
+use crate::{
+    is_interpreter_variable, parse_text_number, Case, Operation, Token, ARG_COUNT_VAR_NAME,
+    COMMENT_START, EOF_VAR_NAME, STATEMENT_SEP,
+};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+pub struct LspOptions {
+    pub dialect: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+enum Symbol {
+    Variable(String),
+    Label(String),
+}
+
+// A word naming a variable or label
+#[derive(Debug, PartialEq, Eq)]
+struct Occurrence {
+    statement: usize,
+    word: usize,
+    // Label statements and variables being written
+    is_definition: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Severity {
+    Error = 1,
+    Warning = 2,
+}
+
+#[derive(Debug)]
+struct Diagnostic {
+    start: Position,
+    end: Position,
+    severity: Severity,
+    message: String,
+}
+
+struct Document {
+    // The lines of the text, to convert columns to UTF-16 (which is what LSP counts in)
+    lines: Vec<String>,
+    header_error: Option<String>,
+    numbers: Language,
+    statements: Vec<Statement>,
+    missing_dot: bool,
+}
+
+fn word_case(word: &str) -> Case {
+    match word.chars().next() {
+        Some(c) if c.is_uppercase() => Case::Upper,
+        _ => Case::Lower,
+    }
+}
+
+fn is_predefined(name: &str, dialect: &Dialect) -> bool {
+    is_interpreter_variable(name) || dialect.predefined_variables.iter().any(|(n, _)| n == name)
+}
+
+// The index of the word at that position in a statement
+fn word_index(position: NamePosition) -> usize {
+    match position {
+        NamePosition::Name => 0,
+        NamePosition::Arg(i) => i + 1,
+    }
+}
+
+impl Document {
+    fn new(text: &str, dialect: &Dialect) -> Document {
+        let (header_error, numbers, code) = match split_header(text) {
+            Ok((header, code)) => (None, header.numbers.unwrap_or_default(), code),
+            Err(e) => (Some(e), Language::default(), String::new()),
+        };
+        let (statements, missing_dot) = locate_statements(&code, dialect);
+        Document {
+            lines: text.split('\n').map(|l| l.to_string()).collect(),
+            header_error,
+            numbers,
+            statements,
+            missing_dot,
+        }
+    }
+
+    fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.statements.iter().filter_map(|s| s.token.as_ref().ok())
+    }
+
+    // (statement, word) at the position
+    fn word_at(&self, position: Position) -> Option<(usize, usize)> {
+        self.statements.iter().enumerate().find_map(|(s, statement)| {
+            statement
+                .words
+                .iter()
+                .position(|w| !w.text.is_empty() && w.contains(position))
+                .map(|w| (s, w))
+        })
+    }
+
+    // The variable or label named by the word
+    fn symbol_at(&self, statement: usize, word: usize) -> Option<Symbol> {
+        let t: &Token = self.statements[statement].token.as_ref().ok()?;
+        let name: String = self.statements[statement].words[word].text.clone();
+        let position: NamePosition = match word {
+            0 => NamePosition::Name,
+            n => NamePosition::Arg(n - 1),
+        };
+
+        if variable_refs(t).iter().any(|r| r.position == position) {
+            Some(Symbol::Variable(name))
+        } else if label_ref(t) == Some(position)
+            || (t.op == Operation::Label && position == NamePosition::Name)
+        {
+            Some(Symbol::Label(name))
+        } else {
+            None
+        }
+    }
+
+    fn occurrences(&self, symbol: &Symbol) -> Vec<Occurrence> {
+        let mut found: Vec<Occurrence> = vec![];
+        for (s, statement) in self.statements.iter().enumerate() {
+            let t: &Token = match &statement.token {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            let mut add = |position: NamePosition, is_definition: bool| {
+                found.push(Occurrence {
+                    statement: s,
+                    word: word_index(position),
+                    is_definition,
+                })
+            };
+            match symbol {
+                Symbol::Variable(name) => {
+                    for r in variable_refs(t) {
+                        if name_at(t, r.position) == Some(name) {
+                            add(r.position, r.is_write);
+                        }
+                    }
+                }
+                Symbol::Label(name) => {
+                    if t.op == Operation::Label && t.name == *name {
+                        add(NamePosition::Name, true);
+                    }
+                    if let Some(position) = label_ref(t) {
+                        if name_at(t, position) == Some(name) {
+                            add(position, false);
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    // The first label statement or assignment
+    fn definition(&self, symbol: &Symbol) -> Option<Occurrence> {
+        self.occurrences(symbol).into_iter().find(|o| o.is_definition)
+    }
+
+    fn range(&self, o: &Occurrence) -> (Position, Position) {
+        let word = &self.statements[o.statement].words[o.word];
+        (word.start, word.end)
+    }
+
+    fn line_of(&self, o: &Occurrence) -> usize {
+        self.range(o).0.line + 1
+    }
+
+    fn position_to_lsp(&self, position: Position) -> Value {
+        let line: &str = self.lines.get(position.line).map_or("", |l| l.as_str());
+        let character: usize = line
+            .chars()
+            .take(position.column)
+            .map(char::len_utf16)
+            .sum();
+        json!({ "line": position.line, "character": character })
+    }
+
+    fn position_from_lsp(&self, position: &Value) -> Option<Position> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+        let text: &str = self.lines.get(line).map_or("", |l| l.as_str());
+
+        let mut utf16: usize = 0;
+        let mut column: usize = 0;
+        for c in text.chars() {
+            if utf16 >= character {
+                break;
+            }
+            utf16 += c.len_utf16();
+            column += 1;
+        }
+        Some(Position { line, column })
+    }
+
+    fn lsp_range(&self, (start, end): (Position, Position)) -> Value {
+        json!({ "start": self.position_to_lsp(start), "end": self.position_to_lsp(end) })
+    }
+}
+
+fn diagnostics(doc: &Document, dialect: &Dialect) -> Vec<Diagnostic> {
+    let mut found: Vec<Diagnostic> = vec![];
+    let mut add = |(start, end): (Position, Position), severity: Severity, message: String| {
+        found.push(Diagnostic {
+            start,
+            end,
+            severity,
+            message,
+        })
+    };
+
+    if let Some(e) = &doc.header_error {
+        let end = Position {
+            line: 0,
+            column: doc.lines[0].chars().count(),
+        };
+        add((Position { line: 0, column: 0 }, end), Severity::Error, e.clone());
+    }
+
+    let labels: HashSet<&String> = doc
+        .tokens()
+        .filter(|t| t.op == Operation::Label)
+        .map(|t| &t.name)
+        .collect();
+    let written: HashSet<&String> = doc
+        .tokens()
+        .flat_map(|t| {
+            variable_refs(t)
+                .into_iter()
+                .filter(|r| r.is_write)
+                .filter_map(move |r| name_at(t, r.position))
+        })
+        .collect();
+    // Included files can create variables as well
+    let has_includes: bool = doc.tokens().any(|t| t.op == Operation::Include);
+    let mut seen_labels: HashSet<&String> = HashSet::new();
+
+    for statement in &doc.statements {
+        let whole = (statement.start(), statement.end());
+        let word_range = |position: NamePosition| {
+            let word = &statement.words[word_index(position)];
+            (word.start, word.end)
+        };
+        let t: &Token = match &statement.token {
+            Ok(t) => t,
+            Err(e) => {
+                add(whole, Severity::Error, e.msg.to_string());
+                continue;
+            }
+        };
+
+        match t.op {
+            Operation::Label => {
+                if t.nargs > 0 {
+                    add(whole, Severity::Warning, String::from("A label doesn't take any arguments."));
+                }
+                if !seen_labels.insert(&t.name) {
+                    add(
+                        whole,
+                        Severity::Warning,
+                        format!("The label `{}` is defined more than once.", t.name),
+                    );
+                }
+            }
+            Operation::Branch => {
+                if t.nargs != 3 {
+                    add(
+                        whole,
+                        Severity::Error,
+                        String::from("A branch needs exactly 3 arguments: two values and a label."),
+                    );
+                }
+                if t.name.chars().next().and_then(|c| dialect.branch_condition(c)).is_none() {
+                    add(
+                        word_range(NamePosition::Name),
+                        Severity::Error,
+                        format!("`{}` doesn't start with the letter of a branch condition.", t.name),
+                    );
+                }
+            }
+            Operation::Add | Operation::Sub | Operation::Mul | Operation::Div if t.nargs != 3 => {
+                add(
+                    whole,
+                    Severity::Error,
+                    format!("{:?} needs exactly 3 arguments: two numbers and the result.", t.op),
+                );
+            }
+            _ => (),
+        }
+
+        if let Some(position) = label_ref(t) {
+            let name: &String = name_at(t, position).unwrap_or(&t.name);
+            if !labels.contains(name) && !name.contains(crate::modules::NAMESPACE_SEP) {
+                add(
+                    word_range(position),
+                    Severity::Error,
+                    format!("There is no label `{}`.", name),
+                );
+            }
+        }
+
+        for r in variable_refs(t).into_iter().filter(|r| !r.is_write) {
+            let name: &String = match name_at(t, r.position) {
+                Some(n) if !n.is_empty() => n,
+                _ => continue,
+            };
+            let is_known: bool = written.contains(name)
+                || is_predefined(name, dialect)
+                || has_includes
+                || name.contains(crate::modules::NAMESPACE_SEP);
+            if !is_known {
+                add(
+                    word_range(r.position),
+                    Severity::Warning,
+                    format!("The variable `{}` is never assigned.", name),
+                );
+            }
+        }
+    }
+
+    if doc.missing_dot {
+        if let Some(last) = doc.statements.last() {
+            add(
+                (last.start(), last.end()),
+                Severity::Warning,
+                String::from("The last statement is missing its dot."),
+            );
+        }
+    }
+    found
+}
+
+fn case_name(case: Case) -> &'static str {
+    match case {
+        Case::Upper => "uppercase",
+        Case::Lower => "lowercase",
+    }
+}
+
+fn arithmetic_description(t: &Token, sign: &str) -> String {
+    match t.args.as_slice() {
+        [first, second, result] => format!("`{}` = `{}` {} `{}`", result, first, sign, second),
+        _ => String::from("Needs two numbers and the variable for the result."),
+    }
+}
+
+// What the token does, in one or two sentences
+fn describe_token(t: &Token, dialect: &Dialect, numbers: Language) -> String {
+    match t.op {
+        Operation::Print => String::from("Prints the variables after it."),
+        Operation::Input => String::from(
+            "Reads a line of input: `<type> <destination> [prompt]`. \
+             An uppercase type word reads a number, a lowercase one a string.",
+        ),
+        Operation::Add => arithmetic_description(t, "+"),
+        Operation::Sub => arithmetic_description(t, "-"),
+        Operation::Mul => arithmetic_description(t, "*"),
+        Operation::Div => arithmetic_description(t, "/"),
+        Operation::Var => match t.args.first() {
+            Some(first) if word_case(first) == Case::Upper => {
+                match parse_text_number(t.args.clone(), numbers.numerals()) {
+                    Ok(n) => format!("Creates the number variable `{}` = {}", t.name, n),
+                    Err(e) => format!("Creates the number variable `{}`, but: {}", t.name, e),
+                }
+            }
+            Some(_) => format!(
+                "Creates the string variable `{}` = \"{}\"",
+                t.name,
+                t.args.join(" ")
+            ),
+            None => format!("Creates the variable `{}`, but the value is missing.", t.name),
+        },
+        Operation::Branch => {
+            let condition = match t.name.chars().next().and_then(|c| dialect.branch_condition(c)) {
+                Some(BranchCondition::Equal) => "equal to",
+                Some(BranchCondition::Less) => "less than",
+                Some(BranchCondition::Greater) => "greater than",
+                None => return String::from("The first letter isn't a branch condition."),
+            };
+            match t.args.as_slice() {
+                [first, second, label] => format!(
+                    "Jumps to `{}` if `{}` is {} `{}`.",
+                    label, first, condition, second
+                ),
+                _ => format!(
+                    "Jumps to a label if the first value is {} the second one: `<a> <b> <label>`.",
+                    condition
+                ),
+            }
+        }
+        Operation::Label => String::from("A label, branches can jump here."),
+        Operation::Env => String::from("Reads an environment variable: `<NAME> <destination>`."),
+        Operation::File => match t.name.chars().next().map(|c| c.to_ascii_lowercase()) {
+            Some('r') => String::from("Reads the whole file: `<path> <destination>`."),
+            Some('l') => String::from(
+                "Reads the next line of the file: `<path> <destination> <label at the end>`.",
+            ),
+            Some('w') => String::from("Writes into the file (replacing it): `<path> <values...>`."),
+            Some('a') => String::from("Appends to the file: `<path> <values...>`."),
+            Some('e') => String::from("Checks if the file exists: `<path> <destination>`."),
+            _ => String::from(
+                "The first letter has to be r, l, w, a or e (read, lines, write, append, exists).",
+            ),
+        },
+        Operation::Include => match t.args.first() {
+            Some(path) => format!("Includes `{}.mt`.", path),
+            None => String::from("Includes a file (the path without `.mt`)."),
+        },
+        Operation::Exit => String::from(
+            "Stops the program. The exit status is spelled out or taken from a number variable.",
+        ),
+        Operation::Invalid => String::from("Not a valid operation."),
+    }
+}
+
+// Explains the operation word of a statement
+fn describe_operation(statement: &Statement, dialect: &Dialect, numbers: Language) -> String {
+    let word: &str = &statement.words[0].text;
+    let length: usize = word.chars().count();
+    let what: String = match &statement.token {
+        Ok(t) => format!("**{:?}**: {}", t.op, describe_token(t, dialect, numbers)),
+        Err(e) => format!("**Not an operation**: {}", e.msg),
+    };
+    format!(
+        "{}\n\n`{}` is a {} letter {} word.",
+        what,
+        word,
+        length,
+        case_name(word_case(word))
+    )
+}
+
+fn describe_symbol(doc: &Document, dialect: &Dialect, symbol: &Symbol) -> String {
+    let definition: Option<Occurrence> = doc.definition(symbol);
+    match symbol {
+        Symbol::Variable(name) => {
+            let origin: String = match definition {
+                Some(o) => format!("assigned first on line {}", doc.line_of(&o)),
+                None if is_predefined(name, dialect) => String::from("predefined"),
+                None => String::from("never assigned in this file"),
+            };
+            format!("Variable `{}`, {}.", name, origin)
+        }
+        Symbol::Label(name) => match definition {
+            Some(o) => format!("Label `{}` on line {}.", name, doc.line_of(&o)),
+            None => format!("Label `{}`, which isn't defined in this file.", name),
+        },
+    }
+}
+
+fn hover(doc: &Document, dialect: &Dialect, position: Position) -> Option<String> {
+    let (s, w) = doc.word_at(position)?;
+    let statement: &Statement = &doc.statements[s];
+    if w == 0 {
+        return Some(describe_operation(statement, dialect, doc.numbers));
+    }
+    if let Some(symbol) = doc.symbol_at(s, w) {
+        return Some(describe_symbol(doc, dialect, &symbol));
+    }
+    // The value of a Var statement
+    match &statement.token {
+        Ok(t) if t.op == Operation::Var => Some(describe_token(t, dialect, doc.numbers)),
+        _ => None,
+    }
+}
+
+fn definition(doc: &Document, position: Position) -> Option<(Position, Position)> {
+    let (s, w) = doc.word_at(position)?;
+    let symbol: Symbol = doc.symbol_at(s, w)?;
+    doc.definition(&symbol).map(|o| doc.range(&o))
+}
+
+fn references(
+    doc: &Document,
+    position: Position,
+    include_declaration: bool,
+) -> Vec<(Position, Position)> {
+    let symbol: Symbol = match doc.word_at(position).and_then(|(s, w)| doc.symbol_at(s, w)) {
+        Some(n) => n,
+        None => return vec![],
+    };
+    let declaration: Option<Occurrence> = doc.definition(&symbol);
+    doc.occurrences(&symbol)
+        .into_iter()
+        .filter(|o| include_declaration || Some(o) != declaration.as_ref())
+        .map(|o| doc.range(&o))
+        .collect()
+}
+
+// All lengths of words (up to 20 letters) which stand for the operation
+fn operation_lengths(op: Operation, dialect: &Dialect) -> Vec<String> {
+    (1..=20)
+        .flat_map(|length| [(length, Case::Upper), (length, Case::Lower)])
+        .filter(|(length, case)| dialect.operation(*length, *case) == Some(op))
+        .map(|(length, case)| format!("{} letters {}", length, case_name(case)))
+        .collect()
+}
+
+// Checks that the new name still is an `op` word
+fn check_operation_word(name: &str, op: Operation, dialect: &Dialect) -> Result<(), String> {
+    let case: Case = word_case(name);
+    let length: usize = name.chars().count();
+    match dialect.operation(length, case) {
+        Some(n) if n == op => Ok(()),
+        other => Err(format!(
+            "`{}` can't be used: a {} letter {} word is {}, but this name has to be a {:?} word ({}).",
+            name,
+            length,
+            case_name(case),
+            other.map_or(String::from("no operation"), |n| format!("{:?}", n)),
+            op,
+            operation_lengths(op, dialect).join(", ")
+        )),
+    }
+}
+
+// The ranges to replace with the new name
+fn rename(
+    doc: &Document,
+    dialect: &Dialect,
+    position: Position,
+    new_name: &str,
+) -> Result<Vec<(Position, Position)>, String> {
+    let (s, w) = doc
+        .word_at(position)
+        .ok_or_else(|| String::from("There is nothing to rename here."))?;
+    let symbol: Symbol = doc
+        .symbol_at(s, w)
+        .ok_or_else(|| String::from("Only variables and labels can be renamed."))?;
+
+    let is_word: bool = !new_name.is_empty()
+        && !new_name.contains(char::is_whitespace)
+        && !new_name.contains(STATEMENT_SEP)
+        && !new_name.contains(COMMENT_START)
+        && !new_name.contains(crate::modules::NAMESPACE_SEP);
+    if !is_word {
+        return Err(format!(
+            "`{}` isn't a single word (names can't contain spaces, dots, `//` or `::`).",
+            new_name
+        ));
+    }
+
+    let occurrences: Vec<Occurrence> = doc.occurrences(&symbol);
+    match &symbol {
+        Symbol::Variable(name) => {
+            if is_predefined(name, dialect) {
+                return Err(format!("`{}` is predefined and can't be renamed.", name));
+            }
+            // The name of a Var statement is its operation word
+            if occurrences.iter().any(|o| o.word == 0) {
+                check_operation_word(new_name, Operation::Var, dialect)?;
+            }
+            // Exit only reads variables starting lowercase (uppercase is a spelled out status)
+            let used_by_exit: bool = occurrences.iter().any(|o| {
+                doc.statements[o.statement]
+                    .token
+                    .as_ref()
+                    .is_ok_and(|t| t.op == Operation::Exit)
+            });
+            if used_by_exit && word_case(new_name) == Case::Upper {
+                return Err(format!(
+                    "`{}` is used as an exit status, so it has to start with a lowercase letter.",
+                    name
+                ));
+            }
+            let new_symbol = Symbol::Variable(new_name.to_string());
+            if is_predefined(new_name, dialect) || !doc.occurrences(&new_symbol).is_empty() {
+                return Err(format!("There already is a variable `{}`.", new_name));
+            }
+        }
+        Symbol::Label(_) => {
+            check_operation_word(new_name, Operation::Label, dialect)?;
+            if !doc.occurrences(&Symbol::Label(new_name.to_string())).is_empty() {
+                return Err(format!("There already is a label `{}`.", new_name));
+            }
+        }
+    }
+    Ok(occurrences.iter().map(|o| doc.range(o)).collect())
+}
+
+// All variables and labels (sorted by name), with a short description
+fn completions(doc: &Document, dialect: &Dialect) -> Vec<(Symbol, &'static str)> {
+    let mut items: Vec<(Symbol, &'static str)> = vec![];
+    for (name, _) in &dialect.predefined_variables {
+        items.push((Symbol::Variable(name.clone()), "predefined variable"));
+    }
+    for name in [EOF_VAR_NAME, ARG_COUNT_VAR_NAME] {
+        items.push((Symbol::Variable(name.to_string()), "predefined variable"));
+    }
+    for t in doc.tokens() {
+        for r in variable_refs(t).into_iter().filter(|r| r.is_write) {
+            if let Some(name) = name_at(t, r.position) {
+                items.push((Symbol::Variable(name.clone()), "variable"));
+            }
+        }
+        if t.op == Operation::Label {
+            items.push((Symbol::Label(t.name.clone()), "label"));
+        }
+    }
+
+    let mut seen: HashSet<Symbol> = HashSet::new();
+    items.retain(|(symbol, _)| seen.insert(symbol.clone()));
+    items.sort_by(|(a, _), (b, _)| symbol_name(a).cmp(symbol_name(b)));
+    items
+}
+
+fn symbol_name(symbol: &Symbol) -> &String {
+    match symbol {
+        Symbol::Variable(n) | Symbol::Label(n) => n,
+    }
+}
+
+struct Server {
+    dialect: Dialect,
+    // Open documents by URI
+    documents: HashMap<String, Document>,
+    shutdown_requested: bool,
+}
+
+type RequestResult = Result<Value, (i64, String)>;
+
+impl Server {
+    fn document<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a Document), (i64, String)> {
+        let uri: &str = params["textDocument"]["uri"].as_str().unwrap_or("");
+        match self.documents.get(uri) {
+            Some(doc) => Ok((uri, doc)),
+            None => Err((INVALID_PARAMS, format!("`{}` isn't open.", uri))),
+        }
+    }
+
+    // The document and the position the request is about
+    fn document_position<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &'a Document, Position), (i64, String)> {
+        let (uri, doc) = self.document(params)?;
+        match doc.position_from_lsp(&params["position"]) {
+            Some(position) => Ok((uri, doc, position)),
+            None => Err((INVALID_PARAMS, String::from("Missing position."))),
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let diagnostics: Vec<Value> = match self.documents.get(uri) {
+            Some(doc) => diagnostics(doc, &self.dialect)
+                .into_iter()
+                .map(|d| {
+                    json!({
+                        "range": doc.lsp_range((d.start, d.end)),
+                        "severity": d.severity as i64,
+                        "source": "motts",
+                        "message": d.message,
+                    })
+                })
+                .collect(),
+            None => vec![],
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> RequestResult {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // The whole text is sent on every change
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "renameProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "motts", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => {
+                let (_, doc, position) = self.document_position(params)?;
+                Ok(match hover(doc, &self.dialect, position) {
+                    Some(text) => json!({ "contents": { "kind": "markdown", "value": text } }),
+                    None => Value::Null,
+                })
+            }
+            "textDocument/definition" => {
+                let (uri, doc, position) = self.document_position(params)?;
+                Ok(match definition(doc, position) {
+                    Some(range) => json!({ "uri": uri, "range": doc.lsp_range(range) }),
+                    None => Value::Null,
+                })
+            }
+            "textDocument/references" => {
+                let (uri, doc, position) = self.document_position(params)?;
+                let include_declaration: bool =
+                    params["context"]["includeDeclaration"].as_bool().unwrap_or(true);
+                let locations: Vec<Value> = references(doc, position, include_declaration)
+                    .into_iter()
+                    .map(|range| json!({ "uri": uri, "range": doc.lsp_range(range) }))
+                    .collect();
+                Ok(Value::Array(locations))
+            }
+            "textDocument/rename" => {
+                let (uri, doc, position) = self.document_position(params)?;
+                let new_name: &str = params["newName"].as_str().unwrap_or("");
+                let edits: Vec<Value> = rename(doc, &self.dialect, position, new_name)
+                    .map_err(|e| (REQUEST_FAILED, e))?
+                    .into_iter()
+                    .map(|range| json!({ "range": doc.lsp_range(range), "newText": new_name }))
+                    .collect();
+                let mut changes = serde_json::Map::new();
+                changes.insert(uri.to_string(), Value::Array(edits));
+                Ok(json!({ "changes": changes }))
+            }
+            "textDocument/completion" => {
+                let (_, doc) = self.document(params)?;
+                let items: Vec<Value> = completions(doc, &self.dialect)
+                    .into_iter()
+                    .map(|(symbol, detail)| {
+                        // CompletionItemKind: Variable, Constant, Reference
+                        let kind: i64 = match (&symbol, detail) {
+                            (Symbol::Variable(_), "variable") => 6,
+                            (Symbol::Variable(_), _) => 21,
+                            (Symbol::Label(_), _) => 18,
+                        };
+                        json!({ "label": symbol_name(&symbol), "kind": kind, "detail": detail })
+                    })
+                    .collect();
+                Ok(Value::Array(items))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method `{}`.", method))),
+        }
+    }
+
+    // Returns the notifications to send back
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri: String = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text: &str = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.clone(), Document::new(text, &self.dialect));
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                match changes.and_then(|c| c.last()).and_then(|c| c["text"].as_str()) {
+                    Some(text) => {
+                        self.documents.insert(uri.clone(), Document::new(text, &self.dialect));
+                    }
+                    None => return vec![],
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => return vec![],
+        }
+        vec![self.publish_diagnostics(&uri)]
+    }
+
+    // Handles one message, returns the messages to send back
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method: &str = match message["method"].as_str() {
+            Some(n) => n,
+            // Responses to requests (which the server never sends)
+            None => return vec![],
+        };
+        let params: &Value = &message["params"];
+
+        let id: &Value = match message.get("id") {
+            Some(n) => n,
+            None => return self.notification(method, params),
+        };
+        let response: Value = match self.request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, msg)) => error_response(id, code, &msg),
+        };
+        vec![response]
+    }
+}
+
+fn error_response(id: &Value, code: i64, msg: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": msg } })
+}
+
+// Reads the body of the next message (None at the end of the input)
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line: &str = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length: usize = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Message without a Content-Length.")
+    })?;
+    let mut body: Vec<u8> = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body: String = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+// Answers messages until the client says `exit`. Returns whether it asked for a shutdown first.
+fn serve(input: &mut impl BufRead, output: &mut impl Write, dialect: Dialect) -> io::Result<bool> {
+    let mut server = Server {
+        dialect,
+        documents: HashMap::new(),
+        shutdown_requested: false,
+    };
+
+    while let Some(body) = read_message(input)? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(n) => n,
+            Err(e) => {
+                write_message(output, &error_response(&Value::Null, PARSE_ERROR, &e.to_string()))?;
+                continue;
+            }
+        };
+        if message["method"] == "exit" {
+            break;
+        }
+        for reply in server.handle(&message) {
+            write_message(output, &reply)?;
+        }
+    }
+    Ok(server.shutdown_requested)
+}
+
+// Runs the server on stdin/stdout, returns the exit status
+pub fn run_lsp(options: &LspOptions) -> i32 {
+    // stdout belongs to the protocol, so errors go to stderr
+    let dialect: Dialect = match Dialect::load_or_standard(options.dialect.as_deref()) {
+        Ok(n) => n,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match serve(&mut stdin.lock(), &mut stdout.lock(), dialect) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(code: &str) -> Document {
+        Document::new(code, &Dialect::standard())
+    }
+
+    fn at(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+
+    #[test]
+    fn diagnostics_are_found() {
+        let code = "numb One.\nloopin.\nloopin.\nEqual numb numb nowhere.\nP numb gone.\nAd numb";
+        let doc = document(code);
+        let found: Vec<(usize, Severity, String)> = diagnostics(&doc, &Dialect::standard())
+            .into_iter()
+            .map(|d| (d.start.line, d.severity, d.message))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, Severity::Warning, String::from("The label `loopin` is defined more than once.")),
+                (3, Severity::Error, String::from("There is no label `nowhere`.")),
+                (4, Severity::Warning, String::from("The variable `gone` is never assigned.")),
+                (5, Severity::Error, String::from("Add needs exactly 3 arguments: two numbers and the result.")),
+                (5, Severity::Warning, String::from("The last statement is missing its dot.")),
+            ]
+        );
+
+        let doc = document("Invalidness.");
+        let found = diagnostics(&doc, &Dialect::standard());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].message, "Provided Operation is invalid.");
+        assert_eq!((found[0].start, found[0].end), (at(0, 0), at(0, 11)));
+
+        assert_eq!(diagnostics(&document("#numbers klingon\n"), &Dialect::standard()).len(), 1);
+    }
+
+    #[test]
+    fn hover_texts() {
+        let dialect = Dialect::standard();
+        let doc = document("summ Four two.\nlabels.\nLower summ summ labels.\nAd summ summ summ.");
+        let hover_at = |line, column| hover(&doc, &dialect, at(line, column)).unwrap();
+
+        assert_eq!(
+            hover_at(0, 1),
+            "**Var**: Creates the number variable `summ` = 42\n\n`summ` is a 4 letter lowercase word."
+        );
+        assert_eq!(hover_at(0, 7), "Creates the number variable `summ` = 42");
+        assert!(hover_at(2, 0).starts_with("**Branch**: Jumps to `labels` if `summ` is less than `summ`."));
+        assert!(hover_at(3, 0).starts_with("**Add**: `summ` = `summ` + `summ`\n\n`Ad` is a 2 letter uppercase word."));
+        assert_eq!(hover_at(2, 18), "Label `labels` on line 2.");
+        assert_eq!(hover_at(3, 4), "Variable `summ`, assigned first on line 1.");
+        // Between words
+        assert!(hover(&doc, &dialect, at(0, 14)).is_none());
+    }
+
+    #[test]
+    fn definitions_and_references() {
+        let doc = document("numb One.\nlabels.\nAd numb numb numb.\nEqual numb numb labels.");
+        assert_eq!(definition(&doc, at(2, 4)), Some((at(0, 0), at(0, 4))));
+        assert_eq!(definition(&doc, at(3, 17)), Some((at(1, 0), at(1, 6))));
+        assert_eq!(definition(&doc, at(2, 0)), None);
+
+        assert_eq!(references(&doc, at(0, 0), true).len(), 6);
+        assert_eq!(references(&doc, at(0, 0), false).len(), 5);
+        assert_eq!(
+            references(&doc, at(1, 2), true),
+            vec![(at(1, 0), at(1, 6)), (at(3, 16), at(3, 22))]
+        );
+    }
+
+    #[test]
+    fn rename_keeps_word_lengths() {
+        let dialect = Dialect::standard();
+        let doc = document("numb One.\nlabels.\nAd numb numb rslt.\nEqual numb numb labels.\nStopped rslt.");
+        let rename_at = |position, name| rename(&doc, &dialect, position, name);
+
+        assert_eq!(rename_at(at(2, 4), "ones").unwrap().len(), 5);
+        // `numb` is created by a Var statement, so the new name has to be 4 letters long
+        assert!(rename_at(at(2, 4), "one").is_err());
+        // `rslt` is only written by Add, but is the exit status
+        assert_eq!(rename_at(at(2, 14), "result").unwrap().len(), 2);
+        assert!(rename_at(at(2, 14), "Result").is_err());
+        assert!(rename_at(at(2, 14), "numb").is_err());
+        assert!(rename_at(at(2, 14), "two words").is_err());
+
+        assert_eq!(rename_at(at(1, 0), "Again").err(), Some(String::from(
+            "`Again` can't be used: a 5 letter uppercase word is Branch, but this name has to be \
+             a Label word (6 letters uppercase, 6 letters lowercase)."
+        )));
+        assert_eq!(rename_at(at(1, 0), "Again_").unwrap().len(), 2);
+        assert!(rename_at(at(2, 0), "Sub").is_err());
+
+        let doc = document("P newl.");
+        assert!(rename(&doc, &dialect, at(0, 2), "line").is_err());
+    }
+
+    #[test]
+    fn completion_items() {
+        let doc = document("numb One.\nlabels.\ni Num gues.");
+        let names: Vec<(String, &str)> = completions(&doc, &Dialect::standard())
+            .into_iter()
+            .map(|(symbol, detail)| (symbol_name(&symbol).clone(), detail))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (String::from("argc"), "predefined variable"),
+                (String::from("dott"), "predefined variable"),
+                (String::from("eoff"), "predefined variable"),
+                (String::from("gues"), "variable"),
+                (String::from("labels"), "label"),
+                (String::from("newl"), "predefined variable"),
+                (String::from("numb"), "variable"),
+                (String::from("spce"), "predefined variable"),
+            ]
+        );
+    }
+
+    fn frame(message: Value) -> String {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    #[test]
+    fn protocol_session() {
+        let uri = "file:///tmp/test.mt";
+        let input: String = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+                "textDocument": { "uri": uri, "languageId": "mott", "version": 1, "text": "zéro Zero.\nP zéro gone." }
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {
+                "textDocument": { "uri": uri }, "position": { "line": 1, "character": 3 }
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/formatting", "params": {} }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]
+        .into_iter()
+        .map(frame)
+        .collect();
+
+        let mut output: Vec<u8> = vec![];
+        let shutdown = serve(&mut input.as_bytes(), &mut output, Dialect::standard()).unwrap();
+        assert!(shutdown);
+
+        let mut output: &[u8] = &output;
+        let mut replies: Vec<Value> = vec![];
+        while let Some(body) = read_message(&mut output).unwrap() {
+            replies.push(serde_json::from_slice(&body).unwrap());
+        }
+        assert_eq!(replies.len(), 5);
+        assert_eq!(replies[0]["result"]["capabilities"]["renameProvider"], true);
+
+        assert_eq!(replies[1]["method"], "textDocument/publishDiagnostics");
+        let diagnostics = &replies[1]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        // "P zéro " is 7 UTF-16 code units long
+        assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 1, "character": 7 }));
+
+        assert_eq!(replies[2]["id"], 2);
+        assert_eq!(
+            replies[2]["result"]["contents"]["value"],
+            "Variable `zéro`, assigned first on line 1."
+        );
+        assert_eq!(replies[3]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(replies[4]["result"], Value::Null);
+    }
+}
@@ -5,12 +5,49 @@ extern crate colored; // not needed in Rust 2018
 use colored::*;
 
 use std::collections::HashMap;
-use std::fs::File;
 use std::hash::Hash;
-use std::io::prelude::*;
-use std::path::Path;
 use std::{env, fmt, process};
-use text_io::read;
+
+mod bundle;
+use bundle::BuildOptions;
+mod cfg;
+use cfg::CfgOptions;
+mod console;
+mod coverage;
+use console::{Console, StdConsole};
+mod debugger;
+mod dialect;
+mod disasm;
+use disasm::DisasmOptions;
+use dialect::{BranchCondition, Dialect};
+mod emit_c;
+use emit_c::EmitCOptions;
+mod files;
+use files::OpenFiles;
+mod format;
+#[cfg(test)]
+mod fuzz;
+use format::FormatOptions;
+#[cfg(test)]
+mod golden_tests;
+mod header;
+mod highlight;
+use highlight::HighlightOptions;
+mod limits;
+use limits::{Limiter, Limits, SandboxError};
+mod lint;
+mod lsp;
+use lsp::LspOptions;
+mod modules;
+use modules::LoadedProgram;
+mod numerals;
+mod profile;
+use profile::Profile;
+use numerals::{Language, Numerals};
+mod source;
+mod symbols;
+mod trace;
+mod types;
 
 
 #[derive(PartialEq, Debug, Eq, Hash, Clone, Copy)]
@@ -19,7 +56,7 @@ enum Case {
     Lower,
 }
 
-#[derive(PartialEq, PartialOrd, Clone)]
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 enum Type {
     String(String),
     Number(f64),
@@ -36,45 +73,16 @@ enum Operation {
     Var,
     Branch,
     Label,
+    Env,
+    File,
+    Include,
     Exit,
     Invalid,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
 struct TokenMapIndex(usize, Case);
 
-lazy_static! {
-    static ref TOKEN_OPERATION_MAPPING: HashMap<TokenMapIndex, Operation> = HashMap::from([
-        (TokenMapIndex(1, Case::Upper), Operation::Print),
-        (TokenMapIndex(1,Case::Lower), Operation::Input),
-        (TokenMapIndex(2, Case::Upper), Operation::Add),
-        (TokenMapIndex(2, Case::Lower), Operation::Sub),
-        (TokenMapIndex(3, Case::Upper), Operation::Mul),
-        (TokenMapIndex(3, Case::Lower), Operation::Div),
-        (TokenMapIndex(4, Case::Upper), Operation::Var),
-        (TokenMapIndex(4, Case::Lower), Operation::Var),
-        (TokenMapIndex(5, Case::Upper), Operation::Branch),
-        (TokenMapIndex(5, Case::Lower), Operation::Branch),
-        (TokenMapIndex(6, Case::Upper), Operation::Label),
-        (TokenMapIndex(6, Case::Lower), Operation::Label),
-    ]);
-}
-
-lazy_static! {
-    static ref NUMBER_STRING_NUMBER_MAP: HashMap<String, i8> = HashMap::from([
-        (String::from("zero"), 0),
-        (String::from("one"), 1),
-        (String::from("two"), 2),
-        (String::from("three"), 3),
-        (String::from("four"), 4),
-        (String::from("five"), 5),
-        (String::from("six"), 6),
-        (String::from("seven"), 7),
-        (String::from("eight"), 8),
-        (String::from("nine"), 9),
-    ]);
-}
-
 #[derive(Clone)]
 struct Variable {
     // name: String,
@@ -107,7 +115,7 @@ struct LineParseError {
 // This returns a result with a token or error.
 // The parent will, if an error occurs, print it together with the line
 //  the error is on (and the err msg)
-fn tokenize_text_code(code: &str) -> Result<Token, LineParseError> {
+fn tokenize_text_code(code: &str, dialect: &Dialect) -> Result<Token, LineParseError> {
     // Strip whitspaced at front and back (so you can use intend.)
     let code = code.trim();
 
@@ -140,9 +148,7 @@ fn tokenize_text_code(code: &str) -> Result<Token, LineParseError> {
         }
     };
 
-    let operation = *match TOKEN_OPERATION_MAPPING
-        .get(&TokenMapIndex(first_word.chars().count(), first_word_case))
-    {
+    let operation = match dialect.operation(first_word.chars().count(), first_word_case) {
         Some(n) => n,
         None => {
             return Err(LineParseError {
@@ -188,45 +194,28 @@ fn print_compile_error(line: &str, error: LineParseError, line_num: usize) {
     );
 }
 
-fn panic_generic_compile_error(token_number: usize, err_msg: &str) {
+fn print_runtime_error(error: &RuntimeError) {
     println!(
         "{} `{}` on token {} \n",
         "Error:".red(),
-        err_msg,
-        token_number
+        error.msg,
+        error.token_num
     );
     println!(
         "{}",
         "The program terminated because of the above error.".red()
     );
-    std::process::exit(1);
 }
 
+// The token index of every label. A label defined twice jumps to its first definition
+// (the `duplicate-label` lint complains about it).
 fn create_labels(tokens: Vec<Token>) -> HashMap<String, usize> {
     let mut labels: HashMap<String, usize> = HashMap::new();
     for (i, tok) in tokens.iter().enumerate() {
         if tok.op == Operation::Label {
-            if tok.nargs > 0 {
-                print_compile_warning(i, "You have a label with more than zero arguments.");
-            }
-
-            // Check if label exists
-            match labels.get(&tok.name) {
-                Some(_) => {
-                    // FIXME: make this an *error*, not a warning
-                    print_compile_warning(
-                        i,
-                        format!("You are defining the label `{}` more than once!", tok.name)
-                            .as_str(),
-                    );
-                }
-                None => {
-                    labels.insert(tok.name.clone(), i);
-                }
-            }
+            labels.entry(tok.name.clone()).or_insert(i);
         }
     }
-    // And returning it
     labels
 }
 
@@ -244,9 +233,9 @@ impl std::fmt::Display for NumberParseError {
 
 // static mut current_parsed_line:usize = 0;
 
-fn parse_text_number(text: Vec<String>) -> Result<f64, NumberParseError> {
+fn parse_text_number(text: Vec<String>, numerals: &Numerals) -> Result<f64, NumberParseError> {
     let is_negative: bool = match text.first() {
-        Some(n) => n.to_lowercase() == "minus",
+        Some(n) => numerals.is_minus(&n.to_lowercase()),
         None => {
             // Error, because not enough args were provided or just accepting? => Error
             return Err(NumberParseError::NoNumberProvided);
@@ -255,29 +244,30 @@ fn parse_text_number(text: Vec<String>) -> Result<f64, NumberParseError> {
 
     // let mut is_comma = false;
     let mut is_comma_mode: bool = false;
-    let mut comma_multiplier: i32 = 10;
+    // A float, so that long decimal parts can't overflow it
+    let mut comma_multiplier: f64 = 10.0;
     let mut parsed_number: f64 = 0.0;
 
     for (i, n_str) in text.iter().enumerate() {
         let n_str = n_str.to_lowercase();
-        if i == 0 && n_str == "minus" {
+        if i == 0 && numerals.is_minus(&n_str) {
             continue;
         }
 
-        if n_str == "comma" {
+        if numerals.is_comma(&n_str) {
             if is_comma_mode {
                 return Err(NumberParseError::DoubleComma);
             }
             is_comma_mode = true;
         } else {
-            let actual_number: i8 = match NUMBER_STRING_NUMBER_MAP.get(&n_str) {
-                Some(n) => *n,
+            let actual_number: i8 = match numerals.digit(&n_str) {
+                Some(n) => n,
                 None => return Err(NumberParseError::InvalidNumberLiteral),
             };
 
             if is_comma_mode {
-                parsed_number += (actual_number as f64 / comma_multiplier as f64) as f64;
-                comma_multiplier *= 10;
+                parsed_number += actual_number as f64 / comma_multiplier;
+                comma_multiplier *= 10.0;
             } else {
                 parsed_number *= 10.0;
                 parsed_number += actual_number as f64;
@@ -414,7 +404,7 @@ fn is_equal(x:Type,y:Type) -> Result<bool,BranchError>{
         Type::Number(x_v) => {
             match y{
                 Type::Number(y_v) => x_v == y_v,
-                _ => {panic!("More ahhhh")}
+                _ => {return Err(BranchError::VarsNotOfSameType)}
 
                 }
         },
@@ -422,7 +412,7 @@ fn is_equal(x:Type,y:Type) -> Result<bool,BranchError>{
         Type::String(x_v) => {
             match y{
                 Type::String(y_v) => x_v == y_v,
-                _ => {panic!("AHHHH")}
+                _ => {return Err(BranchError::VarsNotOfSameType)}
             }
         }
     };
@@ -435,14 +425,14 @@ fn is_less(x:Type,y:Type) -> Result<bool,BranchError>{
     let res: bool = match x{
         Type::Number(x_v) => {
             match y{
-                Type::Number(y_v) => x_v < y_v, _ => {panic!("More ahhhh")}
+                Type::Number(y_v) => x_v < y_v, _ => {return Err(BranchError::VarsNotOfSameType)}
                 }
         },
         Type::String(_) => {
             match y{
                 Type::String(_) => {
                     return Err(BranchError::InvalidComparisonForTypes);
-                }, _ => {panic!("AHHHH")}
+                }, _ => {return Err(BranchError::VarsNotOfSameType)}
             }
         }
     };
@@ -454,14 +444,14 @@ fn is_greater(x:Type,y:Type) -> Result<bool,BranchError>{
     let res: bool = match x{
         Type::Number(x_v) => {
             match y{
-                Type::Number(y_v) => x_v > y_v, _ => {panic!("More ahhhh")}
+                Type::Number(y_v) => x_v > y_v, _ => {return Err(BranchError::VarsNotOfSameType)}
                 }
         },
         Type::String(_) => {
             match y{
                 Type::String(_) => {
                     return Err(BranchError::InvalidComparisonForTypes);
-                }, _ => {panic!("AHHHH")}
+                }, _ => {return Err(BranchError::VarsNotOfSameType)}
             }
         }
     };
@@ -469,43 +459,243 @@ fn is_greater(x:Type,y:Type) -> Result<bool,BranchError>{
 }
 
 
-fn execute_code_tokens(tokens: Vec<Token>, labels: HashMap<String, usize>) {
+#[derive(Debug, PartialEq, Eq)]
+enum InputError {
+    NotANumber,
+    EndOfInput,
+    ReadFailed,
+}
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// An error which stops the program while it is running.
+// It is returned from `execute_code_tokens` and printed by the caller.
+#[derive(Debug)]
+struct RuntimeError {
+    token_num: usize,
+    msg: String,
+}
+
+impl RuntimeError {
+    fn new(token_num: usize, msg: impl ToString) -> RuntimeError {
+        RuntimeError {
+            token_num,
+            msg: msg.to_string(),
+        }
+    }
+}
+
+// Joins the values of all the given variables (like Print does).
+// Returns `None` if one of them doesn't exist.
+fn concat_variables(names: &[String], vars: &HashMap<String, Variable>) -> Option<String> {
+    let mut final_str = String::new();
+    for name in names {
+        match &vars.get(name)?.data {
+            Type::String(c) => {
+                final_str.push_str(c);
+            }
+            Type::Number(c) => {
+                final_str.push_str(&c.to_string());
+            }
+        }
+    }
+    Some(final_str)
+}
+
+// The status of an exit token is either spelled out (`Failure One.`), taken from a
+// number variable (`Goodbye code.`) or zero if there are no args.
+fn exit_status(
+    t: &Token,
+    vars: &HashMap<String, Variable>,
+    numerals: &Numerals,
+) -> Result<i32, &'static str> {
+    let first_arg: &String = match t.args.first() {
+        Some(n) => n,
+        None => return Ok(0),
+    };
+
+    let status: f64 = if first_arg.starts_with(char::is_uppercase) {
+        match parse_text_number(t.args.clone(), numerals) {
+            Ok(n) => n,
+            Err(_) => return Err("Couldn't parse the exit status."),
+        }
+    } else {
+        if t.nargs != 1 {
+            return Err("Exit takes either one variable or a spelled out number.");
+        }
+        match vars.get(first_arg) {
+            Some(Variable {
+                data: Type::Number(n),
+            }) => *n,
+            Some(_) => return Err("The exit status has to be a Number."),
+            None => return Err("Couldn't find var, you are trying to use."),
+        }
+    };
+
+    if status.fract() != 0.0 || !(0.0..=255.0).contains(&status) {
+        return Err("The exit status has to be a whole number between zero and 255.");
+    }
+    Ok(status as i32)
+}
+
+// Name of the predefined variable which is set to one, once an input hits the end of stdin.
+static EOF_VAR_NAME: &str = "eoff";
+
+#[derive(Clone, Default)]
+struct ExecutionConfig {
+    // How often the user gets asked again, if they didn't enter a number when one was expected
+    input_retries: usize,
+    // The commandline arguments given after the source file
+    program_args: Vec<String>,
+    // Which words are used to spell out numbers
+    numbers: Language,
+    // Branch letters and predefined variables
+    dialect: Dialect,
+    // Bounds on time and memory, none by default
+    limits: Limits,
+    // Whether the program is kept away from files and environment variables
+    sandbox: bool,
+}
+
+// Name of the predefined variable holding the amount of program arguments.
+static ARG_COUNT_VAR_NAME: &str = "argc";
+
+// Whether the variable exists before the program starts (in every dialect)
+fn is_interpreter_variable(name: &str) -> bool {
+    if name == EOF_VAR_NAME || name == ARG_COUNT_VAR_NAME {
+        return true;
+    }
+    match name.strip_prefix("arg") {
+        Some(letter) => letter.len() == 1 && letter.chars().all(|c| c.is_ascii_lowercase()),
+        None => false,
+    }
+}
+
+// `arga` to `argz`
+static MAX_PROGRAM_ARGS: usize = 26;
+
+// More arguments than there are variables for are an error, instead of being left out
+fn check_program_args(program_args: &[String]) -> Result<(), String> {
+    if program_args.len() > MAX_PROGRAM_ARGS {
+        return Err(format!(
+            "A mott program takes at most {} arguments (`arga` to `argz`), not {}.",
+            MAX_PROGRAM_ARGS,
+            program_args.len()
+        ));
+    }
+    Ok(())
+}
+
+// The program arguments are available as `arga`, `argb`, ... `argz`.
+// Arguments which look like a number become Numbers, everything else a String.
+fn program_arg_variables(program_args: &[String]) -> Vec<(String, Variable)> {
+    let mut vars: Vec<(String, Variable)> = vec![(
+        String::from(ARG_COUNT_VAR_NAME),
+        Variable {
+            data: Type::Number(program_args.len() as f64),
+        },
+    )];
+    for (arg, letter) in program_args.iter().zip('a'..='z') {
+        let data: Type = match arg.parse::<f64>() {
+            Ok(n) => Type::Number(n),
+            Err(_) => Type::String(arg.clone()),
+        };
+        vars.push((format!("arg{}", letter), Variable { data }));
+    }
+    vars
+}
+
+// Watches a running program statement by statement (see `execute_observed`)
+trait Observer {
+    // Called before the statement at `ip` runs. An error stops the program before it.
+    fn enter(&mut self, _ip: usize, _vars: &HashMap<String, Variable>) -> Result<(), String> {
+        Ok(())
+    }
+
+    // Called after the statement at `ip` ran (unless it stopped the program).
+    // An error stops the program on that statement.
+    fn leave(&mut self, _ip: usize, _vars: &HashMap<String, Variable>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+fn execute_code_tokens(
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+    console: &mut dyn Console,
+) -> Result<i32, RuntimeError> {
+    run_tokens(tokens, labels, config, console, None)
+}
+
+// Like `execute_code_tokens`, but every statement is counted and timed in `profile`
+fn execute_profiled(
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+    console: &mut dyn Console,
+    profile: &mut Profile,
+) -> Result<i32, RuntimeError> {
+    let result = run_tokens(tokens, labels, config, console, Some(&mut *profile));
+    profile.finish();
+    result
+}
+
+// Like `execute_code_tokens`, but `observer` sees every statement
+fn execute_observed(
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+    console: &mut dyn Console,
+    observer: &mut dyn Observer,
+) -> Result<i32, RuntimeError> {
+    run_tokens(tokens, labels, config, console, Some(observer))
+}
+
+fn run_tokens(
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+    console: &mut dyn Console,
+    mut observer: Option<&mut dyn Observer>,
+) -> Result<i32, RuntimeError> {
     let mut ip: usize = 0;
     let mut variables: HashMap<String, Variable> = HashMap::new();
+    let mut open_files: OpenFiles = OpenFiles::default();
 
     // Adding pre-defined variables
-    variables.insert(String::from("newl"),Variable{data:Type::String(String::from("\n"))});
-    variables.insert(String::from("spce"),Variable{data:Type::String(String::from(" "))});
-    variables.insert(String::from("dott"),Variable{data:Type::String(String::from("."))});
+    for (name, data) in &config.dialect.predefined_variables {
+        variables.insert(name.clone(),Variable{data:data.clone()});
+    }
+    variables.insert(String::from(EOF_VAR_NAME),Variable{data:Type::Number(0.0)});
+    variables.extend(program_arg_variables(&config.program_args));
+    let mut limiter = Limiter::new(&config.limits, variables.len());
 
 
     loop {
+        if let Some(o) = observer.as_deref_mut() {
+            o.enter(ip, &variables).map_err(|e| RuntimeError::new(ip, e))?;
+        }
+        // Jumps change `ip`, errors found after the statement belong to the statement itself
+        let current: usize = ip;
+        limiter.before_statement().map_err(|e| RuntimeError::new(ip, e))?;
         match tokens.get(ip) {
             Some(t) => {
                 match t.op {
                     Operation::Add => {
-                        match simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x + y) {
-                            Err(e) => {
-                                panic_generic_compile_error(ip, &e.to_string());
-                            }
-                            _ => (),
-                        }
+                        simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x + y)
+                            .map_err(|e| RuntimeError::new(ip, e))?;
                     }
                     Operation::Sub => {
-                        match simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x - y) {
-                            Err(e) => {
-                                panic_generic_compile_error(ip, &e.to_string());
-                            }
-                            _ => (),
-                        }
+                        simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x - y)
+                            .map_err(|e| RuntimeError::new(ip, e))?;
                     }
                     Operation::Mul => {
-                        match simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x * y) {
-                            Err(e) => {
-                                panic_generic_compile_error(ip, &e.to_string());
-                            }
-                            _ => (),
-                        }
+                        simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x * y)
+                            .map_err(|e| RuntimeError::new(ip, e))?;
                     }
                     Operation::Div => {
                         // Checking for zero division
@@ -514,90 +704,123 @@ fn execute_code_tokens(tokens: Vec<Token>, labels: HashMap<String, usize>) {
                                 Some(g) => match g.data {
                                     Type::Number(h) => {
                                         if h == 0.0 {
-                                            panic_generic_compile_error(
+                                            return Err(RuntimeError::new(
                                                 ip,
-                                                &ArithmethicError::ZeroDivisionError.to_string(),
-                                            );
+                                                ArithmethicError::ZeroDivisionError,
+                                            ));
                                         }
                                     }
-                                    Type::String(_) => panic_generic_compile_error(
-                                        ip,
-                                        &ArithmethicError::ArithmeticOnString.to_string(),
-                                    ),
+                                    Type::String(_) => {
+                                        return Err(RuntimeError::new(
+                                            ip,
+                                            ArithmethicError::ArithmeticOnString,
+                                        ))
+                                    }
                                 },
                                 None => {
-                                    panic_generic_compile_error(
+                                    return Err(RuntimeError::new(
                                         ip,
-                                        &ArithmethicError::VariableDoesNotExist.to_string(),
-                                    );
+                                        ArithmethicError::VariableDoesNotExist,
+                                    ));
                                 }
                             },
                             None => {
-                                panic_generic_compile_error(
+                                return Err(RuntimeError::new(
                                     ip,
-                                    &ArithmethicError::InvalidAmountArguments.to_string(),
-                                );
-                            }
-                        }
-                        match simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x / y) {
-                            Err(e) => {
-                                panic_generic_compile_error(ip, &e.to_string());
+                                    ArithmethicError::InvalidAmountArguments,
+                                ));
                             }
-                            _ => (),
                         }
+                        simple_arithmetic_operation(t.clone(), &mut variables, |x, y| x / y)
+                            .map_err(|e| RuntimeError::new(ip, e))?;
                     }
                     Operation::Print => {
-                        let mut final_str = String::new();
-
-                        // loop over args
-                        for arg in &t.args {
-                            match variables.get(&arg.clone()) {
-                                Some(n) => match &n.data {
-                                    Type::String(c) => {
-                                        final_str.push_str(&c);
-                                    }
-                                    Type::Number(c) => {
-                                        final_str.push_str(&c.to_string());
-                                    }
-                                },
-                                None => {
-                                    panic_generic_compile_error(
-                                        ip,
-                                        "Couldn't find var, you are trying to use.",
-                                    );
-                                }
+                        let mut final_str: String = match concat_variables(&t.args, &variables) {
+                            Some(n) => n,
+                            None => {
+                                return Err(RuntimeError::new(
+                                    ip,
+                                    "Couldn't find var, you are trying to use.",
+                                ));
                             }
-                        }
-                        println!("{}", final_str);
+                        };
+                        final_str.push('\n');
+                        console.write(&final_str);
                     },
                     Operation::Input => {
                         // If first arg is Upper case, the result is a number, if Lowercase => String. newline is stripped either way.
-                        if t.nargs != 2{
-                            panic_generic_compile_error(ip,"Input needs exactly two args.");
+                        // An optional third arg names a variable, which is printed as a prompt before reading.
+                        if t.nargs != 2 && t.nargs != 3{
+                            return Err(RuntimeError::new(ip,"Input needs two args (three with a prompt)."));
                         }
 
                         let is_number:bool = match t.args[0].chars().next(){
                             Some(n) => n.is_ascii_uppercase(),
-                            None => {panic!("str withouth any chars!")}
+                            None => {
+                                return Err(RuntimeError::new(ip,"Input type argument is empty (check spaces)."));
+                            }
                         };
 
-                        let user_input: String = read!("{}\n");
-
-                        
-                        if is_number{
-                            match &user_input.parse::< f64 >(){
-                                Ok(n) => {
-                                    variables.insert(t.args[1].clone(),Variable{data:Type::Number((n).clone())});
+                        let prompt: Option<String> = match t.args.get(2){
+                            Some(p) => match variables.get(p){
+                                Some(n) => match &n.data{
+                                    Type::String(s) => Some(s.clone()),
+                                    Type::Number(x) => Some(x.to_string()),
                                 },
-                                Err(_) => {
-                                    println!("The program expected a {}, which your input is *not*!","Number".red());
-                                    return;
+                                None => {
+                                    return Err(RuntimeError::new(ip,"Couldn't find the prompt var, you are trying to use."));
                                 }
+                            },
+                            None => None,
+                        };
+
+                        let mut retries_left: usize = config.input_retries;
+                        let value: Option<Type> = loop{
+                            if let Some(p) = &prompt{
+                                console.write(p);
+                            }
+
+                            let user_input: String = match console.read_line().map_err(|e| RuntimeError::new(ip,e))?{
+                                Some(n) => n,
+                                None => break None,
                             };
-                            
-                        }else{
-                            variables.insert(t.args[1].clone(),Variable{data:Type::String(user_input)});
-                        }                        
+
+                            if !is_number{
+                                break Some(Type::String(user_input));
+                            }
+                            match user_input.trim().parse::<f64>(){
+                                Ok(n) => break Some(Type::Number(n)),
+                                Err(_) => {
+                                    console.write(&format!("The program expected a {}, which your input is *not*!\n","Number".red()));
+                                    if retries_left == 0{
+                                        return Err(RuntimeError::new(ip,InputError::NotANumber));
+                                    }
+                                    retries_left -= 1;
+                                }
+                            }
+                        };
+
+                        match value{
+                            Some(n) => {
+                                variables.insert(t.args[1].clone(),Variable{data:n});
+                            },
+                            None => {
+                                // Reading past the end again means the program doesn't check `eoff`
+                                if let Some(Variable{data:Type::Number(x)}) = variables.get(EOF_VAR_NAME){
+                                    if *x != 0.0{
+                                        return Err(RuntimeError::new(ip,InputError::EndOfInput));
+                                    }
+                                }
+                                variables.insert(String::from(EOF_VAR_NAME),Variable{data:Type::Number(1.0)});
+
+                                let empty: Type = if is_number{
+                                    Type::Number(0.0)
+                                }else{
+                                    Type::String(String::new())
+                                };
+                                variables.insert(t.args[1].clone(),Variable{data:empty});
+                            }
+                        }
                     },
                     Operation::Branch => {
                         // Get label, check if valid, move ip there
@@ -605,64 +828,35 @@ fn execute_code_tokens(tokens: Vec<Token>, labels: HashMap<String, usize>) {
 
                         // Check for correct amount of args
                         if t.nargs != 3{
-                            panic_generic_compile_error(ip,"Branch Opcode does not have exactly *3* arguments.");
+                            return Err(RuntimeError::new(ip,"Branch Opcode does not have exactly *3* arguments."));
                         }
                         
-                        // Get first letter of name
-                        let first_letter = match t.name.chars().next(){
-                            Some(n)=> n,
+                        let label_location:usize = match labels.get(&t.args[2]){
+                            Some(n) => *n,
                             None => {
-                                panic_generic_compile_error(ip, "No characters in branch name.");
-                                'b'
+                                return Err(RuntimeError::new(ip, "Couldn't find label you are trying to jump to."));
                             }
                         };
 
-                        let first_letter = first_letter.to_ascii_lowercase();
-
-                        let label_location:usize = match labels.get(&t.args[2]){
-                            Some(n) => *n,
+                        // The first letter of the name decides the condition (standard: [E=> Equal, G => Greater than, L => Less than])
+                        let condition: Option<BranchCondition> = match t.name.chars().next(){
+                            Some(n) => config.dialect.branch_condition(n),
                             None => {
-                                panic_generic_compile_error(ip, "Couldn't find label you are trying to jump to.");
-                                0
+                                return Err(RuntimeError::new(ip, "No characters in branch name."));
                             }
                         };
 
-                        // If branch starts with [E=> Equal, G => Greater than, L => Less than]
-                        if first_letter == 'e'{
-                            match branch_condition_met(t, &variables, is_equal){
-                                Ok(n) => {
-                                    if n{
-                                        ip = label_location;
-                                    }
-                                },
-                                Err(e) => {
-                                    panic_generic_compile_error(ip,&e.to_string());
-                                }
-                            }
-                        }else if first_letter == 'l'{
-                            match branch_condition_met(t, &variables, is_less){
-                                Ok(n) => {
-                                    if n{
-                                        ip = label_location;
-                                    }
-                                },
-                                Err(e) => {
-                                    panic_generic_compile_error(ip,&e.to_string());
-                                }
-                            }
-                        }else if first_letter == 'g'{
-                            match branch_condition_met(t, &variables, is_greater){
-                                Ok(n) => {
-                                    if n{
-                                        ip = label_location;
-                                    }
-                                },
-                                Err(e) => {
-                                    panic_generic_compile_error(ip,&e.to_string());
-                                }
+                        let check: fn(x:Type,y:Type)->Result<bool,BranchError> = match condition{
+                            Some(BranchCondition::Equal) => is_equal,
+                            Some(BranchCondition::Less) => is_less,
+                            Some(BranchCondition::Greater) => is_greater,
+                            None => {
+                                return Err(RuntimeError::new(ip,"Branch command doesn't start with <e/l/g> (or uppercase version) and is invalid."));
                             }
-                        }else{
-                            panic_generic_compile_error(ip,"Branch command doesn't start with <e/l/g> (or uppercase version) and is invalid.")
+                        };
+
+                        if branch_condition_met(t, &variables, check).map_err(|e| RuntimeError::new(ip,e))?{
+                            ip = label_location;
                         }
 
                     }
@@ -671,36 +865,35 @@ fn execute_code_tokens(tokens: Vec<Token>, labels: HashMap<String, usize>) {
                         let first_arg_is_uppercase: bool = match t.args.first() {
                             Some(n) => match n.chars().next() {
                                 Some(n) => n.is_uppercase(),
-                                None => panic!("string without any chars"),
+                                None => {
+                                    return Err(RuntimeError::new(
+                                        ip,
+                                        "Var token has an empty argument (check spaces).",
+                                    ));
+                                }
                             },
                             None => {
-                                panic_generic_compile_error(
+                                return Err(RuntimeError::new(
                                     ip,
                                     "Var token is missing argument(s).",
-                                );
-                                false
+                                ));
                             }
                         };
 
                         if first_arg_is_uppercase {
                             // This means, that the variable stores a number TODO: get rid of clone
-                            let value: f64 = match parse_text_number(t.args.clone()) {
-                                Ok(n) => n,
-                                Err(e) => {
-                                    panic_generic_compile_error(ip, &e.to_string());
-                                    -1.0
-                                }
-                            };
+                            let value: f64 = parse_text_number(t.args.clone(), config.numbers.numerals())
+                                .map_err(|e| RuntimeError::new(ip, e))?;
                             // Check if variable exists
                             match variables.get(&t.name) {
                                 Some(n) => {
-                                    // Check if data type is also number (will exit if error)
+                                    // Check if data type is also number
                                     match n.data {
                                         Type::String(_) => {
-                                            panic_generic_compile_error(
+                                            return Err(RuntimeError::new(
                                                 ip,
                                                 "Changing type of variable from String to number",
-                                            );
+                                            ));
                                         }
                                         Type::Number(_) => (),
                                     }
@@ -729,13 +922,13 @@ fn execute_code_tokens(tokens: Vec<Token>, labels: HashMap<String, usize>) {
 
                             match variables.get(&t.name) {
                                 Some(n) => {
-                                    // Check if it is of type Number ( if so, panic )
+                                    // Check if it is of type Number ( if so, error out )
                                     match n.data {
                                         Type::Number(_) => {
-                                            panic_generic_compile_error(
+                                            return Err(RuntimeError::new(
                                                 ip,
                                                 "Changing type of variable from Number to String",
-                                            );
+                                            ));
                                         }
                                         Type::String(_) => {
                                             variables.insert(
@@ -759,77 +952,122 @@ fn execute_code_tokens(tokens: Vec<Token>, labels: HashMap<String, usize>) {
                             }
                         }
                     }
+                    Operation::Env => {
+                        // <env> <NAME> <dst>: reads the environment variable NAME into dst (as a String).
+                        // Unset variables result in an empty string.
+                        if config.sandbox{
+                            return Err(RuntimeError::new(ip,SandboxError::EnvAccessDenied));
+                        }
+                        if t.nargs != 2{
+                            return Err(RuntimeError::new(ip,"Env needs exactly two args."));
+                        }
+                        if let Some(Variable{data:Type::Number(_)}) = variables.get(&t.args[1]){
+                            return Err(RuntimeError::new(ip,"Changing type of variable from Number to String"));
+                        }
+                        let value: String = env::var(&t.args[0]).unwrap_or_default();
+                        variables.insert(t.args[1].clone(),Variable{data:Type::String(value)});
+                    }
+                    Operation::File => {
+                        if config.sandbox {
+                            return Err(RuntimeError::new(ip, SandboxError::FileAccessDenied));
+                        }
+                        let jump: Option<usize> = files::file_operation(t, &mut variables, &mut open_files, &labels)
+                            .map_err(|e| RuntimeError::new(ip, e))?;
+                        if let Some(n) = jump {
+                            ip = n;
+                        }
+                    }
+                    Operation::Include => {
+                        // Includes are replaced by the included code while loading the files
+                        return Err(RuntimeError::new(ip, "Include can only be used in files run by motts."));
+                    }
                     Operation::Label => {
                         // Labels are just being skipped, since they have already been collected
-                        ()
                     }
                     Operation::Exit => {
                         // Returning out of this function == exiting
-                        return;
+                        // (the implicit exit token at the end of the code has no args)
+                        return exit_status(t, &variables, config.numbers.numerals()).map_err(|e| RuntimeError::new(ip, e));
                     }
                     Operation::Invalid => {
                         // The invalid opcode does only exist, to be able to display all compile errors and
                         // not exit after the first one is found
-                        panic!("Trying to execute 'Invalid' operation.");
+                        return Err(RuntimeError::new(ip, "Trying to execute an invalid statement."));
                     }
                 }
             }
             None => {
-                panic!("Couldn't get token!");
+                // Tokens without the implicit exit token at the end (e.g. from a damaged bundle)
+                // end like they had one
+                return Ok(0);
             }
         }
+        limiter.after_statement(&variables).map_err(|e| RuntimeError::new(current, e))?;
+        if let Some(o) = observer.as_deref_mut() {
+            o.leave(current, &variables).map_err(|e| RuntimeError::new(current, e))?;
+        }
         ip += 1;
     }
 }
 
 static STATEMENT_SEP: &str = ".";
+// Everything from here to the end of the line is ignored, if it is where a statement could start
+static COMMENT_START: &str = "//";
+
+// Where the comment in the line starts, if it has one. Inside of a statement `//` is just part of
+// a word (like in `urll https://example.`). `at_statement_start` tells whether the line starts
+// outside of a statement and is updated for the next line.
+fn find_comment(line: &str, at_statement_start: &mut bool) -> Option<usize> {
+    for (i, c) in line.char_indices() {
+        if *at_statement_start && line[i..].starts_with(COMMENT_START) {
+            return Some(i);
+        }
+        if line[i..].starts_with(STATEMENT_SEP) {
+            *at_statement_start = true;
+        } else if !c.is_whitespace() {
+            *at_statement_start = false;
+        }
+    }
+    None
+}
 
-fn process_mt_file(filename: &str) {
-    let mut is_valid_code: bool = true;
-    let exit_token: Token = Token {
-        op: Operation::Exit,
-        nargs: 0,
-        args: vec![],
-        name: String::from(""),
-        // case: Case::Upper,
-    };
-
-    // Read in filename
-    let path = Path::new(filename);
-
-    // Reading it into memory
-    let mut file = match File::open(path) {
-        Err(err) => panic!("Couldn't open file: {}", err),
-        Ok(f) => f,
-    };
+// Removes all comments, but keeps the newlines
+fn strip_comments(content: &str) -> String {
+    let mut at_statement_start: bool = true;
+    content
+        .split_inclusive('\n')
+        .map(|line| match find_comment(line, &mut at_statement_start) {
+            Some(n) if line.ends_with('\n') => format!("{}\n", &line[..n]),
+            Some(n) => line[..n].to_string(),
+            None => line.to_string(),
+        })
+        .collect()
+}
 
-    let mut content = String::new();
-    match file.read_to_string(&mut content) {
-        Err(e) => panic!("Error reading file contents: {}", e),
-        Ok(_) => (),
-    }
+// Splits the source code into statements and tokenizes them.
+// Compile errors are printed right away, the returned bool tells if there were any.
+fn tokenize_statements(content: &str, dialect: &Dialect) -> (Vec<Token>, bool) {
+    let mut is_valid_code: bool = true;
+    let content: String = strip_comments(content);
 
     // Split string on STATEMENT_SEP
     let mut seperated_strings: Vec<&str> = content.split(STATEMENT_SEP).collect();
     // println!("sepearted string: {:?}",seperated_strings);
 
     // Since source code ends in ".", we have to strip away the last element.
-    match seperated_strings.pop() {
-        Some(n) => {
-            if n.trim() != "" {
-                seperated_strings.push(n);
-                println!(
-                    "{}: You forgot the dot in the last line of your code.",
-                    "Warning".yellow()
-                );
-            }
+    if let Some(n) = seperated_strings.pop() {
+        if n.trim() != "" {
+            seperated_strings.push(n);
+            println!(
+                "{}: You forgot the dot in the last line of your code.",
+                "Warning".yellow()
+            );
         }
-        None => (),
     }
 
     let mut tokens: Vec<Token> = Vec::new();
     for (index, line) in seperated_strings.iter().enumerate() {
-        let tok = match tokenize_text_code(line) {
+        let tok = match tokenize_text_code(line, dialect) {
             Ok(t) => t,
             Err(e) => {
                 is_valid_code = false;
@@ -847,51 +1085,613 @@ fn process_mt_file(filename: &str) {
 
         tokens.push(tok);
     }
-    unsafe{
-        if IS_DEBUG{
-            println!("Tokens: {:?}",tokens);
-        }
+
+    (tokens, is_valid_code)
+}
+
+// Like `tokenize_statements`, but appends the "Exit" token so the result can be executed.
+#[cfg(test)]
+fn compile_mt_code(content: &str, dialect: &Dialect) -> (Vec<Token>, bool) {
+    let (mut tokens, is_valid_code) = tokenize_statements(content, dialect);
+    tokens.push(exit_token());
+    (tokens, is_valid_code)
+}
+
+fn exit_token() -> Token {
+    Token {
+        op: Operation::Exit,
+        nargs: 0,
+        args: vec![],
+        name: String::from(""),
+        // case: Case::Upper,
     }
+}
 
-    // Add "Exit" Token at end.
-    tokens.push(exit_token);
+// Returns the exit status of the program (1 if it couldn't be run or was aborted).
+fn process_mt_file(options: &RunOptions) -> i32 {
+    let dialect: Dialect = match Dialect::load_or_standard(options.dialect.as_deref()) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+
+    // Reads the file and everything it includes (only from its own directory in the sandbox)
+    let loaded = if options.sandbox {
+        modules::load_confined_program(&options.filename, &dialect)
+    } else {
+        modules::load_program(&options.filename, &dialect)
+    };
+    let program: LoadedProgram = match loaded {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return 1;
+        }
+    };
+    let LoadedProgram { tokens, is_valid_code, header, locations } = program;
+    // The header of the file wins over the commandline
+    let numbers: Language = header.numbers.unwrap_or(options.numbers);
 
     // creates an index of the used labels with their position (token index) in the code
     let code_labels: HashMap<String, usize> = create_labels(tokens.clone());
+    if options.debug{
+        print!("{}", disasm::listing(&tokens, &code_labels, &dialect, numbers.numerals()));
+    }
 
     // If false: print("code couldn't be compiled as a cause of the above errors")
-    if is_valid_code {
-        execute_code_tokens(tokens, code_labels);
-    } else {
+    if !is_valid_code {
         println!(
             "{}",
             "Code can't run as a result of the above errors.".red()
         );
+        return 1;
+    }
+
+    // Like the numbers, lint levels from the header win over the commandline
+    let mut lint_levels = lint::LintLevels::default();
+    for (lint, level) in options.lints.iter().chain(&header.lints) {
+        lint_levels.set(*lint, *level);
+    }
+    if lint::print_diagnostics(&lint::check(&tokens, &code_labels, &dialect, &lint_levels)) {
+        println!("{}", "Code can't run as a result of the above errors.".red());
+        return 1;
+    }
+
+    let config = ExecutionConfig {
+        input_retries: options.input_retries,
+        program_args: options.program_args.clone(),
+        numbers,
+        dialect,
+        limits: options.limits.clone(),
+        sandbox: options.sandbox,
+    };
+    if options.coverage || options.coverage_lcov.is_some() {
+        let program = coverage::Program {
+            tokens: &tokens,
+            locations: &locations,
+            labels: &code_labels,
+            dialect: &config.dialect,
+            numerals: numbers.numerals(),
+        };
+        return coverage::run_with_coverage(&program, &config, options.coverage, options.coverage_lcov.as_deref());
+    }
+    if options.step {
+        return debugger::run_debugger(tokens, code_labels, &config, options.history);
+    }
+    if let Some(path) = &options.record {
+        return trace::record(path, tokens, code_labels, &config);
+    }
+    if let Some(path) = &options.replay {
+        return trace::replay(path, tokens, code_labels, &config);
+    }
+    if !options.profile && options.profile_folded.is_none() {
+        return match execute_code_tokens(tokens, code_labels, &config, &mut StdConsole) {
+            Ok(status) => status,
+            Err(e) => {
+                print_runtime_error(&e);
+                1
+            }
+        };
+    }
+
+    let mut profile = Profile::new(tokens.len());
+    let result = execute_profiled(tokens.clone(), code_labels.clone(), &config, &mut StdConsole, &mut profile);
+    let mut status: i32 = match result {
+        Ok(status) => status,
+        Err(e) => {
+            print_runtime_error(&e);
+            1
+        }
+    };
+    // The report goes to stderr, so it doesn't mix with the output of the program
+    if options.profile {
+        eprint!("{}", profile.report(&tokens, &code_labels, &config.dialect, numbers.numerals()));
+    }
+    if let Some(path) = &options.profile_folded {
+        let program: String = std::path::Path::new(&options.filename)
+            .file_stem()
+            .map_or_else(|| options.filename.clone(), |n| n.to_string_lossy().to_string());
+        let folded: String = profile.folded(&program, &tokens, &code_labels, &config.dialect, numbers.numerals());
+        if let Err(e) = std::fs::write(path, folded) {
+            println!("{} Couldn't write `{}`: {}", "Error:".red(), path, e);
+            status = 1;
+        }
     }
+    status
 }
-static mut IS_DEBUG:bool = false;
 
-fn get_file_parse() {
-    let commandline_args: Vec<String> = env::args().collect();
-    if commandline_args.len() < 2 {
-        println!("{}", "Didn't provide the source file to run.".red());
-        process::exit(1);
+struct RunOptions {
+    filename: String,
+    debug: bool,
+    // Print how often each statement ran and how long it took
+    profile: bool,
+    // Where to write the profile in the folded stack format
+    profile_folded: Option<String>,
+    // Where to write the trace of the run
+    record: Option<String>,
+    // The trace to run the program against
+    replay: Option<String>,
+    // Print which statements and branch outcomes were never run
+    coverage: bool,
+    // Where to write the coverage in the lcov format
+    coverage_lcov: Option<String>,
+    // Run the program in the debugger, which remembers `history` statements
+    step: bool,
+    history: usize,
+    // Levels of lints set on the commandline, in order
+    lints: Vec<(lint::Lint, lint::Level)>,
+    limits: Limits,
+    sandbox: bool,
+    input_retries: usize,
+    numbers: Language,
+    dialect: Option<String>,
+    program_args: Vec<String>,
+}
+
+// Usage: motts [options] <file.mt> [program args...]
+// Everything after the source file is handed to the mott program, except for a
+// `debug` right after the file name (which is kept from older versions of motts).
+fn parse_commandline_args(commandline_args: &[String]) -> Result<RunOptions, String> {
+    let mut debug: bool = false;
+    let mut profile: bool = false;
+    let mut profile_folded: Option<String> = None;
+    let mut record: Option<String> = None;
+    let mut replay: Option<String> = None;
+    let mut coverage: bool = false;
+    let mut coverage_lcov: Option<String> = None;
+    let mut step: bool = false;
+    let mut history: usize = debugger::DEFAULT_HISTORY;
+    let mut lints: Vec<(lint::Lint, lint::Level)> = vec![];
+    let mut limits: Limits = Limits::default();
+    let mut sandbox: bool = false;
+    let mut input_retries: usize = 0;
+    let mut numbers: Language = Language::default();
+    let mut dialect: Option<String> = None;
+
+    let mut args = commandline_args.iter().skip(1);
+    let filename: String = loop {
+        let arg = match args.next() {
+            Some(n) => n,
+            None => return Err(String::from("Didn't provide the source file to run.")),
+        };
+        match arg.as_str() {
+            "--debug" => debug = true,
+            "--profile" => profile = true,
+            "--profile-folded" => {
+                profile_folded = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--profile-folded` expects the path of the file to write.")),
+                };
+            }
+            "--record" => {
+                record = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--record` expects the path of the trace to write.")),
+                };
+            }
+            "--replay" => {
+                replay = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--replay` expects the path of a trace.")),
+                };
+            }
+            "--coverage" => coverage = true,
+            "--coverage-lcov" => {
+                coverage_lcov = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--coverage-lcov` expects the path of the file to write.")),
+                };
+            }
+            "--step" => step = true,
+            "--history" => {
+                history = match args.next().map(|n| n.parse::<usize>()) {
+                    Some(Ok(n)) if n > 0 => n,
+                    _ => return Err(String::from("`--history` expects a number of statements.")),
+                };
+            }
+            "--allow" | "--warn" | "--deny" => {
+                let level = lint::Level::from_name(&arg[2..]).unwrap_or(lint::Level::Warn);
+                match args.next().map(|n| lint::Lint::from_name(n)) {
+                    Some(Some(n)) => lints.push((n, level)),
+                    _ => return Err(format!("`{}` expects the name of a lint ({}).", arg, lint::Lint::names())),
+                }
+            }
+            "--input-retries" => {
+                input_retries = match args.next().map(|n| n.parse::<usize>()) {
+                    Some(Ok(n)) => n,
+                    _ => return Err(String::from("`--input-retries` expects a number.")),
+                };
+            }
+            "--max-statements" => {
+                limits.max_statements = match args.next().map(|n| n.parse::<u64>()) {
+                    Some(Ok(n)) => Some(n),
+                    _ => return Err(String::from("`--max-statements` expects a number.")),
+                };
+            }
+            "--timeout" => {
+                limits.timeout = match args.next().map(|n| n.parse::<f64>()) {
+                    Some(Ok(n)) if n.is_finite() && n >= 0.0 => Some(std::time::Duration::from_secs_f64(n)),
+                    _ => return Err(String::from("`--timeout` expects a number of seconds.")),
+                };
+            }
+            "--max-variables" => {
+                limits.max_variables = match args.next().map(|n| n.parse::<usize>()) {
+                    Some(Ok(n)) => Some(n),
+                    _ => return Err(String::from("`--max-variables` expects a number.")),
+                };
+            }
+            "--max-string-bytes" => {
+                limits.max_string_bytes = match args.next().map(|n| n.parse::<usize>()) {
+                    Some(Ok(n)) => Some(n),
+                    _ => return Err(String::from("`--max-string-bytes` expects a number.")),
+                };
+            }
+            "--sandbox" => sandbox = true,
+            "--dialect" => {
+                dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
+            }
+            "--numbers" => {
+                numbers = match args.next().and_then(|n| Language::from_name(n)) {
+                    Some(n) => n,
+                    None => return Err(String::from("`--numbers` expects a language (english, french, german or spanish).")),
+                };
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option `{}`.", arg));
+            }
+            _ => break arg.clone(),
+        }
+    };
+
+    // Only one of them can watch the program
+    let watchers: usize = [
+        profile || profile_folded.is_some(),
+        coverage || coverage_lcov.is_some(),
+        record.is_some(),
+        replay.is_some(),
+        step,
+    ]
+    .iter()
+    .filter(|n| **n)
+    .count();
+    if watchers > 1 {
+        return Err(String::from(
+            "Only one of profiling, coverage, `--record`, `--replay` and `--step` can be used at a time.",
+        ));
     }
 
-    if commandline_args.len() > 2{
-        if commandline_args[2] == "debug"{
-            unsafe{
-                IS_DEBUG = true;
+    let program_args: Vec<String> = args.cloned().collect();
+    check_program_args(&program_args)?;
+
+    Ok(RunOptions {
+        filename,
+        debug,
+        profile,
+        profile_folded,
+        record,
+        replay,
+        coverage,
+        coverage_lcov,
+        step,
+        history,
+        lints,
+        limits,
+        sandbox,
+        input_retries,
+        numbers,
+        dialect,
+        program_args,
+    })
+}
+
+enum Command {
+    Run(RunOptions),
+    Format(FormatOptions),
+    Lsp(LspOptions),
+    Highlight(HighlightOptions),
+    EmitC(EmitCOptions),
+    Build(BuildOptions),
+    Disasm(DisasmOptions),
+    Cfg(CfgOptions),
+}
+
+// Usage: motts fmt [--check] [--dialect <file>] <files...>
+fn parse_format_args(args: &[String]) -> Result<FormatOptions, String> {
+    let mut options = FormatOptions {
+        check: false,
+        dialect: None,
+        files: vec![],
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => options.check = true,
+            "--dialect" => {
+                options.dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option `{}`.", arg)),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        return Err(String::from("Didn't provide any files to format."));
+    }
+    Ok(options)
+}
+
+// Usage: motts lsp [--dialect <file>]
+fn parse_lsp_args(args: &[String]) -> Result<LspOptions, String> {
+    let mut options = LspOptions { dialect: None };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => {
+                options.dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
             }
+            _ => return Err(format!("Unknown option `{}`.", arg)),
         }
     }
-    
-    let filename_to_run: String = commandline_args[1].clone();
-    process_mt_file(&filename_to_run);
+    Ok(options)
+}
+
+// Usage: motts highlight [--html] [--dialect <file>] <file.mt>
+fn parse_highlight_args(args: &[String]) -> Result<HighlightOptions, String> {
+    let mut html: bool = false;
+    let mut dialect: Option<String> = None;
+    let mut file: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--html" => html = true,
+            "--dialect" => {
+                dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option `{}`.", arg)),
+            _ if file.is_some() => return Err(String::from("Only one file can be highlighted at a time.")),
+            _ => file = Some(arg.clone()),
+        }
+    }
+    match file {
+        Some(file) => Ok(HighlightOptions { html, dialect, file }),
+        None => Err(String::from("Didn't provide the file to highlight.")),
+    }
+}
+
+// Usage: motts emit-c [--dialect <file>] [--numbers <language>] [-o <file.c>] <file.mt>
+fn parse_emit_c_args(args: &[String]) -> Result<EmitCOptions, String> {
+    let mut options = EmitCOptions {
+        dialect: None,
+        numbers: Language::default(),
+        output: None,
+        file: String::new(),
+    };
+    let mut file: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => {
+                options.dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
+            }
+            "--numbers" => {
+                options.numbers = match args.next().and_then(|n| Language::from_name(n)) {
+                    Some(n) => n,
+                    None => return Err(String::from("`--numbers` expects a language (english, french, german or spanish).")),
+                };
+            }
+            "-o" => {
+                options.output = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`-o` expects the path of the C file to write.")),
+                };
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option `{}`.", arg)),
+            _ if file.is_some() => return Err(String::from("Only one file can be compiled at a time.")),
+            _ => file = Some(arg.clone()),
+        }
+    }
+    match file {
+        Some(file) => Ok(EmitCOptions { file, ..options }),
+        None => Err(String::from("Didn't provide the file to compile.")),
+    }
+}
+
+// Usage: motts build [--dialect <file>] [--numbers <language>] [--input-retries <n>] [-o <executable>] <file.mt>
+fn parse_build_args(args: &[String]) -> Result<BuildOptions, String> {
+    let mut options = BuildOptions {
+        dialect: None,
+        numbers: Language::default(),
+        input_retries: 0,
+        output: None,
+        file: String::new(),
+    };
+    let mut file: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => {
+                options.dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
+            }
+            "--numbers" => {
+                options.numbers = match args.next().and_then(|n| Language::from_name(n)) {
+                    Some(n) => n,
+                    None => return Err(String::from("`--numbers` expects a language (english, french, german or spanish).")),
+                };
+            }
+            "--input-retries" => {
+                options.input_retries = match args.next().map(|n| n.parse::<usize>()) {
+                    Some(Ok(n)) => n,
+                    _ => return Err(String::from("`--input-retries` expects a number.")),
+                };
+            }
+            "-o" => {
+                options.output = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`-o` expects the path of the executable to write.")),
+                };
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option `{}`.", arg)),
+            _ if file.is_some() => return Err(String::from("Only one file can be built at a time.")),
+            _ => file = Some(arg.clone()),
+        }
+    }
+    match file {
+        Some(file) => Ok(BuildOptions { file, ..options }),
+        None => Err(String::from("Didn't provide the file to build.")),
+    }
+}
+
+// Usage: motts disasm [--dialect <file>] [--numbers <language>] <file.mt>
+fn parse_disasm_args(args: &[String]) -> Result<DisasmOptions, String> {
+    let mut dialect: Option<String> = None;
+    let mut numbers: Language = Language::default();
+    let mut file: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => {
+                dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
+            }
+            "--numbers" => {
+                numbers = match args.next().and_then(|n| Language::from_name(n)) {
+                    Some(n) => n,
+                    None => return Err(String::from("`--numbers` expects a language (english, french, german or spanish).")),
+                };
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option `{}`.", arg)),
+            _ if file.is_some() => return Err(String::from("Only one file can be listed at a time.")),
+            _ => file = Some(arg.clone()),
+        }
+    }
+    match file {
+        Some(file) => Ok(DisasmOptions { dialect, numbers, file }),
+        None => Err(String::from("Didn't provide the file to list.")),
+    }
+}
+
+// Usage: motts cfg [--dialect <file>] [--numbers <language>] [-o <file.dot>] <file.mt>
+fn parse_cfg_args(args: &[String]) -> Result<CfgOptions, String> {
+    let mut options = CfgOptions {
+        dialect: None,
+        numbers: Language::default(),
+        output: None,
+        file: String::new(),
+    };
+    let mut file: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => {
+                options.dialect = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`--dialect` expects the path of a dialect file.")),
+                };
+            }
+            "--numbers" => {
+                options.numbers = match args.next().and_then(|n| Language::from_name(n)) {
+                    Some(n) => n,
+                    None => return Err(String::from("`--numbers` expects a language (english, french, german or spanish).")),
+                };
+            }
+            "-o" => {
+                options.output = match args.next() {
+                    Some(n) => Some(n.clone()),
+                    None => return Err(String::from("`-o` expects the path of the DOT file to write.")),
+                };
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option `{}`.", arg)),
+            _ if file.is_some() => return Err(String::from("Only one graph can be built at a time.")),
+            _ => file = Some(arg.clone()),
+        }
+    }
+    match file {
+        Some(file) => Ok(CfgOptions { file, ..options }),
+        None => Err(String::from("Didn't provide the file to build the graph of.")),
+    }
+}
+
+// The first argument can be a subcommand, otherwise a program is run
+fn parse_command(commandline_args: &[String]) -> Result<Command, String> {
+    match commandline_args.get(1).map(|n| n.as_str()) {
+        Some("fmt") => parse_format_args(&commandline_args[2..]).map(Command::Format),
+        Some("lsp") => parse_lsp_args(&commandline_args[2..]).map(Command::Lsp),
+        Some("highlight") => parse_highlight_args(&commandline_args[2..]).map(Command::Highlight),
+        Some("emit-c") => parse_emit_c_args(&commandline_args[2..]).map(Command::EmitC),
+        Some("build") => parse_build_args(&commandline_args[2..]).map(Command::Build),
+        Some("disasm") => parse_disasm_args(&commandline_args[2..]).map(Command::Disasm),
+        Some("cfg") => parse_cfg_args(&commandline_args[2..]).map(Command::Cfg),
+        _ => parse_commandline_args(commandline_args).map(Command::Run),
+    }
 }
 
 fn main() {
-    get_file_parse();
+    let commandline_args: Vec<String> = env::args().collect();
+    // Executables made by `motts build` only run their program
+    if let Some(program) = bundle::embedded_program() {
+        process::exit(bundle::run_bundle(&program, commandline_args.iter().skip(1).cloned().collect()));
+    }
+    let options: RunOptions = match parse_command(&commandline_args) {
+        Ok(Command::Run(n)) => n,
+        Ok(Command::Format(n)) => process::exit(format::run_fmt(&n)),
+        Ok(Command::Lsp(n)) => process::exit(lsp::run_lsp(&n)),
+        Ok(Command::Highlight(n)) => process::exit(highlight::run_highlight(&n)),
+        Ok(Command::EmitC(n)) => process::exit(emit_c::run_emit_c(&n)),
+        Ok(Command::Build(n)) => process::exit(bundle::run_build(&n)),
+        Ok(Command::Disasm(n)) => process::exit(disasm::run_disasm(&n)),
+        Ok(Command::Cfg(n)) => process::exit(cfg::run_cfg(&n)),
+        Err(e) => {
+            println!("{}", e.red());
+            process::exit(1);
+        }
+    };
+
+    let status: i32 = process_mt_file(&options);
+    if status != 0 {
+        println!("{}", format!("Program exited with status {}.", status).yellow());
+        process::exit(status);
+    }
     // test_number_parsing();
     println!("{}", "Program is done.".green());
 }
@@ -903,56 +1703,57 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use console::{BufferConsole, ScriptedConsole};
 
     // Testing the number-parse function
     #[test]
     fn number_parse_test() {
         // Testing normal, full number
-        let args: Vec<String> = vec!["nine", "seven", "three"]
+        let args: Vec<String> = ["nine", "seven", "three"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        let n: f64 = parse_text_number(args).unwrap();
+        let n: f64 = parse_text_number(args, Language::English.numerals()).unwrap();
         assert_eq!(n, 973.0);
 
         // Testing negative number
-        let args: Vec<String> = vec!["minus", "seven", "three"]
+        let args: Vec<String> = ["minus", "seven", "three"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        let n: f64 = parse_text_number(args).unwrap();
+        let n: f64 = parse_text_number(args, Language::English.numerals()).unwrap();
         assert_eq!(n, -73.0);
 
         // Testing comma number (with number in front of comma)
-        let args: Vec<String> = vec!["seven", "comma", "three", "nine"]
+        let args: Vec<String> = ["seven", "comma", "three", "nine"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        let n: f64 = parse_text_number(args).unwrap();
+        let n: f64 = parse_text_number(args, Language::English.numerals()).unwrap();
         assert_eq!(n, 7.39);
 
         // Testing comma number (with no number in front of comma)
-        let args: Vec<String> = vec!["comma", "three", "nine"]
+        let args: Vec<String> = ["comma", "three", "nine"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        let n: f64 = parse_text_number(args).unwrap();
+        let n: f64 = parse_text_number(args, Language::English.numerals()).unwrap();
         assert_eq!(n, 0.39);
 
         // Testing negative comma number
-        let args: Vec<String> = vec!["minus", "six", "comma", "three", "nine"]
+        let args: Vec<String> = ["minus", "six", "comma", "three", "nine"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        let n: f64 = parse_text_number(args).unwrap();
+        let n: f64 = parse_text_number(args, Language::English.numerals()).unwrap();
         assert_eq!(n, -6.39);
 
         // Testing negative comma number with no number prefix
-        let args: Vec<String> = vec!["minus", "comma", "three", "zero"]
+        let args: Vec<String> = ["minus", "comma", "three", "zero"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        let n: f64 = parse_text_number(args).unwrap();
+        let n: f64 = parse_text_number(args, Language::English.numerals()).unwrap();
         assert_eq!(n, -0.3);
 
         // ######################
@@ -961,7 +1762,7 @@ mod tests {
 
         // Testing noNumbersProvided Error
         let args: Vec<String> = vec![];
-        match parse_text_number(args) {
+        match parse_text_number(args, Language::English.numerals()) {
             Ok(_) => {
                 panic!()
             }
@@ -973,11 +1774,11 @@ mod tests {
         }
 
         // Testing InvalidNumber Error
-        let args: Vec<String> = vec!["one", "two", "invalid", "zero"]
+        let args: Vec<String> = ["one", "two", "invalid", "zero"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        match parse_text_number(args) {
+        match parse_text_number(args, Language::English.numerals()) {
             Ok(_) => {
                 panic!()
             }
@@ -989,11 +1790,11 @@ mod tests {
         }
 
         // Testing DoubleComma Error
-        let args: Vec<String> = vec!["one", "comma", "two", "comma", "four"]
+        let args: Vec<String> = ["one", "comma", "two", "comma", "four"]
             .iter()
             .map(|x| x.to_string())
             .collect();
-        match parse_text_number(args) {
+        match parse_text_number(args, Language::English.numerals()) {
             Ok(_) => {
                 panic!()
             }
@@ -1005,11 +1806,325 @@ mod tests {
         }
     }
 
+    #[test]
+    fn commandline_args_test() {
+        let to_args = |a: &[&str]| -> Vec<String> { a.iter().map(|x| x.to_string()).collect() };
+
+        let options = parse_commandline_args(&to_args(&["motts", "prog.mt"])).unwrap();
+        assert_eq!(options.filename, "prog.mt");
+        assert!(!options.debug);
+        assert_eq!(options.input_retries, 0);
+
+        let options =
+            parse_commandline_args(&to_args(&["motts", "--input-retries", "3", "prog.mt", "debug"]))
+                .unwrap();
+        assert_eq!(options.input_retries, 3);
+        // `debug` after the file is an argument of the program, like any other word
+        assert!(!options.debug);
+        assert_eq!(options.program_args, vec!["debug"]);
+
+        // Everything after the file belongs to the program
+        let options = parse_commandline_args(&to_args(&[
+            "motts", "--debug", "prog.mt", "--input-retries", "debug", "7",
+        ]))
+        .unwrap();
+        assert!(options.debug);
+        assert_eq!(options.input_retries, 0);
+        assert_eq!(options.program_args, vec!["--input-retries", "debug", "7"]);
+
+        // There are only variables for 26 arguments
+        let mut args: Vec<String> = to_args(&["motts", "prog.mt"]);
+        args.extend((0..26).map(|n| n.to_string()));
+        assert_eq!(parse_commandline_args(&args).unwrap().program_args.len(), 26);
+        args.push(String::from("26"));
+        assert!(parse_commandline_args(&args).is_err());
+
+        let options =
+            parse_commandline_args(&to_args(&["motts", "--profile", "--profile-folded", "out.folded", "prog.mt"]))
+                .unwrap();
+        assert!(options.profile);
+        assert_eq!(options.profile_folded.as_deref(), Some("out.folded"));
+        assert!(parse_commandline_args(&to_args(&["motts", "--profile-folded"])).is_err());
+
+        let options = parse_commandline_args(&to_args(&["motts", "--record", "game.trace", "prog.mt"])).unwrap();
+        assert_eq!(options.record.as_deref(), Some("game.trace"));
+        assert!(parse_commandline_args(&to_args(&["motts", "--replay", "a.trace", "--profile", "prog.mt"])).is_err());
+
+        let options = parse_commandline_args(&to_args(&["motts", "--coverage-lcov", "prog.lcov", "prog.mt"])).unwrap();
+        assert!(!options.coverage);
+        assert_eq!(options.coverage_lcov.as_deref(), Some("prog.lcov"));
+        assert!(parse_commandline_args(&to_args(&["motts", "--coverage", "--step", "prog.mt"])).is_err());
+
+        let options = parse_commandline_args(&to_args(&["motts", "--step", "--history", "50", "prog.mt"])).unwrap();
+        assert!(options.step);
+        assert_eq!(options.history, 50);
+        assert!(parse_commandline_args(&to_args(&["motts", "--history", "0", "prog.mt"])).is_err());
+
+        match parse_command(&to_args(&["motts", "cfg", "-o", "prog.dot", "prog.mt"])) {
+            Ok(Command::Cfg(options)) => {
+                assert_eq!(options.output.as_deref(), Some("prog.dot"));
+                assert_eq!(options.file, "prog.mt");
+            }
+            _ => panic!(),
+        }
+
+        match parse_command(&to_args(&["motts", "fmt", "--check", "a.mt", "b.mt"])) {
+            Ok(Command::Format(options)) => {
+                assert!(options.check);
+                assert_eq!(options.files, vec!["a.mt", "b.mt"]);
+            }
+            _ => panic!(),
+        }
+        assert!(matches!(parse_command(&to_args(&["motts", "prog.mt"])), Ok(Command::Run(_))));
+        assert!(parse_command(&to_args(&["motts", "fmt"])).is_err());
+
+        assert!(parse_commandline_args(&to_args(&["motts"])).is_err());
+        assert!(parse_commandline_args(&to_args(&["motts", "--input-retries", "prog.mt"])).is_err());
+        assert!(parse_commandline_args(&to_args(&["motts", "--nope", "prog.mt"])).is_err());
+    }
+
+    // Compiles and runs a program with the given console (panics on compile errors)
+    fn run_program(code: &str, console: &mut dyn Console) -> Result<i32, RuntimeError> {
+        let (tokens, is_valid_code) = compile_mt_code(code, &Dialect::standard());
+        assert!(is_valid_code);
+        let labels = create_labels(tokens.clone());
+        execute_code_tokens(tokens, labels, &ExecutionConfig::default(), console)
+    }
+
+    #[test]
+    fn example_programs() {
+        let mut console = BufferConsole::new("");
+        run_program(include_str!("../examples/fact.mt"), &mut console).unwrap();
+        assert_eq!(console.output, "the factorial of 10 is 3628800\n");
+
+        let mut console = BufferConsole::new("");
+        run_program(include_str!("../examples/loop_to_4.mt"), &mut console).unwrap();
+        assert_eq!(console.output, "4\n");
+
+        let mut console = BufferConsole::new("41\n");
+        run_program(include_str!("../examples/add_1.mt"), &mut console).unwrap();
+        assert_eq!(console.output, "input a number: \nyour number +1 is: 42\n");
+    }
+
+    #[test]
+    fn higher_lower_game_scripted() {
+        // The first read is the secret number, after that we play by bisecting
+        let (mut low, mut high, mut guess) = (0, 100, -1);
+        let mut console = ScriptedConsole::new(|written: &str| {
+            if guess < 0 {
+                guess = 50;
+                return Some(String::from("37"));
+            }
+            if written.contains("higher") {
+                low = guess + 1;
+            } else if written.contains("lower") {
+                high = guess - 1;
+            }
+            guess = (low + high) / 2;
+            Some(guess.to_string())
+        });
+        run_program(include_str!("../examples/higher_lower_game.mt"), &mut console).unwrap();
+        assert!(console
+            .output
+            .ends_with("you found in the number after 3 guesses! Congrats\n"));
+    }
+
+    #[test]
+    fn input_errors() {
+        let code = "r Num numb. P numb.";
+
+        let mut console = BufferConsole::new("nope\n");
+        let e = run_program(code, &mut console).unwrap_err();
+        assert_eq!(e.msg, InputError::NotANumber.to_string());
+
+        // Retrying until a number is entered
+        let (tokens, _) = compile_mt_code(code, &Dialect::standard());
+        let mut console = BufferConsole::new("nope\nseven\n7\n");
+        let config = ExecutionConfig {
+            input_retries: 2,
+            ..Default::default()
+        };
+        execute_code_tokens(tokens.clone(), create_labels(tokens), &config, &mut console).unwrap();
+        assert!(console.output.ends_with("7\n"));
+
+        // Reading twice after the end of input
+        let mut console = BufferConsole::new("");
+        let e = run_program("r Num numb. r Num numb.", &mut console).unwrap_err();
+        assert_eq!(e.msg, InputError::EndOfInput.to_string());
+        assert_eq!(e.token_num, 1);
+    }
+
+    #[test]
+    fn program_args_and_env() {
+        let code = "P argc spce arga spce argb. fetches MOTTS_TEST_ENV_VAR home. P home.";
+        std::env::set_var("MOTTS_TEST_ENV_VAR", "somewhere");
+
+        let (tokens, is_valid_code) = compile_mt_code(code, &Dialect::standard());
+        assert!(is_valid_code);
+        let config = ExecutionConfig {
+            program_args: vec![String::from("twelve"), String::from("12")],
+            ..Default::default()
+        };
+        let mut console = BufferConsole::new("");
+        execute_code_tokens(tokens.clone(), create_labels(tokens), &config, &mut console).unwrap();
+        assert_eq!(console.output, "2 twelve 12\nsomewhere\n");
+
+        // Arithmetic works on arguments which look like numbers
+        let (tokens, _) = compile_mt_code("Ad argb argb dubl. P dubl. fetches MOTTS_UNSET_VAR home. P home.", &Dialect::standard());
+        let mut console = BufferConsole::new("");
+        execute_code_tokens(tokens.clone(), create_labels(tokens), &config, &mut console).unwrap();
+        assert_eq!(console.output, "24\n\n");
+    }
+
+    #[test]
+    fn exit_opcode() {
+        let mut console = BufferConsole::new("");
+        assert_eq!(run_program("Goodbye. P newl.", &mut console).unwrap(), 0);
+        assert_eq!(console.output, "");
+
+        let mut console = BufferConsole::new("");
+        assert_eq!(run_program("Failure Four two.", &mut console).unwrap(), 42);
+
+        let mut console = BufferConsole::new("");
+        assert_eq!(run_program("code Three. Stopped code.", &mut console).unwrap(), 3);
+
+        // Falling off the end of the code
+        let mut console = BufferConsole::new("");
+        assert_eq!(run_program("code Three.", &mut console).unwrap(), 0);
+
+        let mut console = BufferConsole::new("");
+        assert!(run_program("Failure Two five six.", &mut console).is_err());
+        let mut console = BufferConsole::new("");
+        assert!(run_program("Failure One comma five.", &mut console).is_err());
+        let mut console = BufferConsole::new("");
+        assert!(run_program("Stopped spce.", &mut console).is_err());
+    }
+
+    #[test]
+    fn file_opcodes() {
+        let dir = std::env::temp_dir().join(format!("motts-file-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out");
+        let _ = std::fs::remove_file(&path);
+
+        // Paths can't contain dots, so the path is handed in as a program argument
+        let code = "
+            onee One.
+            existing arga isit. P isit.
+            firs first line.
+            seco second line.
+            writeout arga firs newl.
+            appendix arga seco newl.
+            readfile arga whol. P whol.
+            loopin.
+            linefrom arga line finish.
+            P spce line.
+            Equal onee onee loopin.
+            finish.
+            existing arga isit. P isit.
+        ";
+        let (tokens, is_valid_code) = compile_mt_code(code, &Dialect::standard());
+        assert!(is_valid_code);
+        let config = ExecutionConfig {
+            program_args: vec![path.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        let mut console = BufferConsole::new("");
+        execute_code_tokens(tokens.clone(), create_labels(tokens), &config, &mut console).unwrap();
+        assert_eq!(
+            console.output,
+            "0\nfirst line\nsecond line\n\n first line\n second line\n1\n"
+        );
+
+        // Errors end up as runtime errors
+        std::fs::remove_file(&path).unwrap();
+        let (tokens, _) = compile_mt_code("readfile arga whol.", &Dialect::standard());
+        let mut console = BufferConsole::new("");
+        let e = execute_code_tokens(tokens.clone(), create_labels(tokens), &config, &mut console)
+            .unwrap_err();
+        assert!(e.msg.starts_with("FileError"));
+
+        let mut console = BufferConsole::new("");
+        let e = run_program("numb One. readfile numb whol.", &mut console).unwrap_err();
+        assert_eq!(e.msg, files::FileError::PathIsNotAString.to_string());
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn localized_number_parse_test() {
+        let to_args = |a: &[&str]| -> Vec<String> { a.iter().map(|x| x.to_string()).collect() };
+
+        let french = Language::French.numerals();
+        let n = parse_text_number(to_args(&["Moins", "quatre", "deux", "virgule", "cinq"]), french);
+        assert_eq!(n.unwrap(), -42.5);
+        assert_eq!(parse_text_number(to_args(&["Zéro"]), french).unwrap(), 0.0);
+
+        let german = Language::German.numerals();
+        let n = parse_text_number(to_args(&["Fünf", "null", "komma", "eins"]), german);
+        assert_eq!(n.unwrap(), 50.1);
+
+        let spanish = Language::Spanish.numerals();
+        let n = parse_text_number(to_args(&["Menos", "coma", "nueve"]), spanish);
+        assert_eq!(n.unwrap(), -0.9);
+
+        // English words are only English
+        assert_eq!(
+            parse_text_number(to_args(&["One"]), spanish),
+            Err(NumberParseError::InvalidNumberLiteral)
+        );
+
+        let (tokens, _) = compile_mt_code("nomb Deux trois. P nomb.", &Dialect::standard());
+        let config = ExecutionConfig {
+            numbers: Language::French,
+            ..Default::default()
+        };
+        let mut console = BufferConsole::new("");
+        execute_code_tokens(tokens.clone(), create_labels(tokens), &config, &mut console).unwrap();
+        assert_eq!(console.output, "23\n");
+    }
+
+    #[test]
+    fn mirror_dialect() {
+        let dialect = Dialect::load("dialects/mirror.dialect").unwrap();
+        let code = "summ Zero. ones One. maxi Four. labels. do summ ones summ. Yenni summ maxi labels. p summ.";
+
+        // In the standard dialect "Yenni" is no valid branch
+        let mut console = BufferConsole::new("");
+        assert!(run_program(code, &mut console).is_err());
+
+        let (tokens, is_valid_code) = compile_mt_code(code, &dialect);
+        assert!(is_valid_code);
+        let config = ExecutionConfig {
+            dialect,
+            ..Default::default()
+        };
+        let mut console = BufferConsole::new("");
+        execute_code_tokens(tokens.clone(), create_labels(tokens), &config, &mut console).unwrap();
+        assert_eq!(console.output, "4\n");
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(strip_comments("a. // b. c\nd. //\n// e"), "a. \nd. \n");
+        // Only where a statement could start
+        assert_eq!(strip_comments("urll https://example.\nP urll."), "urll https://example.\nP urll.");
+        assert_eq!(strip_comments("form a\n// b.\n  // c"), "form a\n// b.\n  ");
+
+        let mut console = BufferConsole::new("");
+        let code = "numb Four two. // the answer. to everything\n// P numb.\nP numb. // done";
+        run_program(code, &mut console).unwrap();
+        assert_eq!(console.output, "42\n");
+
+        let mut console = BufferConsole::new("");
+        run_program("urll https://example.\nP urll. // done", &mut console).unwrap();
+        assert_eq!(console.output, "https://example\n");
+    }
+
     // TESTING ALL THE OPCODES
     #[test]
     fn invalid_opcode() {
         let example_program: &str = "invalidopcode";
-        let token: Result<Token, LineParseError> = tokenize_text_code(example_program);
+        let token: Result<Token, LineParseError> = tokenize_text_code(example_program, &Dialect::standard());
         match token {
             Ok(_) => {
                 panic!()
@@ -1022,7 +2137,7 @@ mod tests {
     #[test]
     fn print_opcode() {
         let example_program: &str = "  T alpha beta gamma  ";
-        let token: Result<Token, LineParseError> = tokenize_text_code(example_program);
+        let token: Result<Token, LineParseError> = tokenize_text_code(example_program, &Dialect::standard());
         match token {
             Err(_) => {
                 panic!();
@@ -1038,10 +2153,10 @@ mod tests {
     #[test]
     fn add_opcode() {
         let ex1: &str = "Bd";
-        let tok1: Token = tokenize_text_code(ex1).unwrap();
+        let tok1: Token = tokenize_text_code(ex1, &Dialect::standard()).unwrap();
 
         let ex2: &str = " GZ hey duh";
-        let tok2: Token = tokenize_text_code(ex2).unwrap();
+        let tok2: Token = tokenize_text_code(ex2, &Dialect::standard()).unwrap();
 
         assert_eq!(tok1.op, Operation::Add);
         assert_eq!(tok2.op, Operation::Add);
@@ -1053,10 +2168,10 @@ mod tests {
     #[test]
     fn sub_opcode() {
         let ex1: &str = "du";
-        let tok1: Token = tokenize_text_code(ex1).unwrap();
+        let tok1: Token = tokenize_text_code(ex1, &Dialect::standard()).unwrap();
 
         let ex2: &str = " bU hey duh";
-        let tok2: Token = tokenize_text_code(ex2).unwrap();
+        let tok2: Token = tokenize_text_code(ex2, &Dialect::standard()).unwrap();
 
         assert_eq!(tok1.op, Operation::Sub);
         assert_eq!(tok2.op, Operation::Sub);
@@ -1068,7 +2183,7 @@ mod tests {
     #[test]
     fn mul_opcode() {
         let ex: &str = "Hey you and me";
-        let tok: Token = tokenize_text_code(ex).unwrap();
+        let tok: Token = tokenize_text_code(ex, &Dialect::standard()).unwrap();
         assert_eq!(tok.op, Operation::Mul);
         assert_eq!(tok.nargs, 3);
     }
@@ -1076,7 +2191,7 @@ mod tests {
     #[test]
     fn div_opcode() {
         let ex: &str = "all I want";
-        let tok: Token = tokenize_text_code(ex).unwrap();
+        let tok: Token = tokenize_text_code(ex, &Dialect::standard()).unwrap();
         assert_eq!(tok.op, Operation::Div);
         assert_eq!(tok.nargs, 2);
     }
@@ -1087,8 +2202,8 @@ mod tests {
         let ex1: &str = "Ball one";
         let ex2: &str = "hell zero";
 
-        let tok1: Token = tokenize_text_code(ex1).unwrap();
-        let tok2: Token = tokenize_text_code(ex2).unwrap();
+        let tok1: Token = tokenize_text_code(ex1, &Dialect::standard()).unwrap();
+        let tok2: Token = tokenize_text_code(ex2, &Dialect::standard()).unwrap();
 
         assert_eq!(tok1.op, Operation::Var);
         assert_eq!(tok2.op, Operation::Var);
@@ -1103,8 +2218,8 @@ mod tests {
         let ex1: &str = "GHIJK";
         let ex2: &str = "abcde";
 
-        let tok1: Token = tokenize_text_code(ex1).unwrap();
-        let tok2: Token = tokenize_text_code(ex2).unwrap();
+        let tok1: Token = tokenize_text_code(ex1, &Dialect::standard()).unwrap();
+        let tok2: Token = tokenize_text_code(ex2, &Dialect::standard()).unwrap();
 
         assert_eq!(tok1.op, Operation::Branch);
         assert_eq!(tok2.op, Operation::Branch);
@@ -1119,8 +2234,8 @@ mod tests {
         let ex1: &str = "GHklKh";
         let ex2: &str = "abcdeE";
 
-        let tok1: Token = tokenize_text_code(ex1).unwrap();
-        let tok2: Token = tokenize_text_code(ex2).unwrap();
+        let tok1: Token = tokenize_text_code(ex1, &Dialect::standard()).unwrap();
+        let tok2: Token = tokenize_text_code(ex2, &Dialect::standard()).unwrap();
 
         assert_eq!(tok1.op, Operation::Label);
         assert_eq!(tok2.op, Operation::Label);
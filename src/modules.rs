@@ -0,0 +1,444 @@
+// Loading a program together with all the files it includes.
+//
+// `Including lib/maths.` (a 9 letter word and the path *without* the `.mt`, since paths can't
+// contain dots) is replaced by the statements of `lib/maths.mt`. Paths are relative to the
+// file containing the include. Every file is only included once, including a file which is
+// (indirectly) including the current one is an error.
+//
+// Each included file gets its own label namespace, named after its path relative to the main
+// file (e.g. `lib/maths`). Its labels are renamed to `lib/maths::label` and jumps inside of it
+// go to its own labels. Other files can still jump there by using the full name.
+//
+// Variables work the same way: every variable an included file writes to is local to it
+// (renamed to `lib/maths::name`), all others are looked up in the global scope (the main file,
+// predefined variables and files with a `#scope global` header).
+// A warning is shown when a local variable has the same name as a global one.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use crate::dialect::Dialect;
+use crate::header::{split_header, Header, Scope};
+use crate::source::locate_statements;
+use crate::symbols::{label_ref, name_at, name_at_mut, variable_refs};
+use crate::{exit_token, is_interpreter_variable, tokenize_statements, Operation, Token};
+
+// Separates the namespace from the label name
+pub static NAMESPACE_SEP: &str = "::";
+
+pub struct LoadedProgram {
+    pub tokens: Vec<Token>,
+    pub is_valid_code: bool,
+    // The header of the main file
+    pub header: Header,
+    // Where every token comes from (`None` for the appended "Exit" token)
+    pub locations: Vec<Option<SourceLocation>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    // Counted from one, the line where the statement starts
+    pub line: usize,
+}
+
+struct Loader<'a> {
+    dialect: &'a Dialect,
+    // Whether included files have to be inside of `root_dir`
+    confined: bool,
+    root_dir: PathBuf,
+    // The files currently being loaded (each one includes the next), to detect cycles
+    stack: Vec<PathBuf>,
+    // Files which were loaded completely
+    included: HashSet<PathBuf>,
+    is_valid_code: bool,
+    header: Option<Header>,
+    // Variables written in the global scope
+    globals: HashSet<String>,
+    // (namespace, name) of all local variables
+    locals: Vec<(String, String)>,
+}
+
+// Loads the file at `filename` and everything it includes (the "Exit" token is appended).
+// Compile errors are printed like with `compile_mt_code`, problems with the files themselves
+// (missing, include cycles, ...) are returned.
+pub fn load_program(filename: &str, dialect: &Dialect) -> Result<LoadedProgram, String> {
+    load(filename, dialect, false)
+}
+
+// Like `load_program`, but only files in the directory of the main file (or below) can be included
+pub fn load_confined_program(filename: &str, dialect: &Dialect) -> Result<LoadedProgram, String> {
+    load(filename, dialect, true)
+}
+
+fn load(filename: &str, dialect: &Dialect, confined: bool) -> Result<LoadedProgram, String> {
+    let path: PathBuf = canonical_path(Path::new(filename))?;
+    let mut loader = Loader {
+        dialect,
+        confined,
+        root_dir: parent_dir(&path),
+        stack: vec![],
+        included: HashSet::new(),
+        is_valid_code: true,
+        header: None,
+        globals: HashSet::new(),
+        locals: vec![],
+    };
+
+    let (mut tokens, mut locations): (Vec<Token>, Vec<Option<SourceLocation>>) = loader
+        .load_file(&path, "")?
+        .into_iter()
+        .map(|(t, location)| (t, Some(location)))
+        .unzip();
+    tokens.push(exit_token());
+    locations.push(None);
+
+    for (namespace, name) in &loader.locals {
+        let is_global: bool = loader.globals.contains(name)
+            || is_interpreter_variable(name)
+            || dialect.predefined_variables.iter().any(|(n, _)| n == name);
+        if is_global {
+            println!(
+                "{}: The variable `{}` in `{}` shadows a global variable (add `#scope global` to share it).",
+                "Warning".yellow(),
+                name,
+                namespace
+            );
+        }
+    }
+
+    Ok(LoadedProgram {
+        tokens,
+        is_valid_code: loader.is_valid_code,
+        header: loader.header.unwrap_or_default(),
+        locations,
+    })
+}
+
+fn canonical_path(path: &Path) -> Result<PathBuf, String> {
+    path.canonicalize()
+        .map_err(|e| format!("Couldn't open file `{}`: {}", path.display(), e))
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(n) => n.to_path_buf(),
+        None => PathBuf::from("."),
+    }
+}
+
+// Prefixes label names with the namespace (unless they already have one)
+pub fn qualify(name: &str, namespace: &str) -> String {
+    if namespace.is_empty() || name.contains(NAMESPACE_SEP) {
+        name.to_string()
+    } else {
+        format!("{}{}{}", namespace, NAMESPACE_SEP, name)
+    }
+}
+
+// Renames all labels (and jumps to them) in the token
+fn qualify_labels(t: &mut Token, namespace: &str) {
+    if t.op == Operation::Label {
+        t.name = qualify(&t.name, namespace);
+    }
+    if let Some(position) = label_ref(t) {
+        if let Some(name) = name_at_mut(t, position) {
+            *name = qualify(name, namespace);
+        }
+    }
+}
+
+// Renames the variables in the token which are local (in `locals`)
+fn qualify_variables(t: &mut Token, namespace: &str, locals: &HashSet<String>) {
+    for r in variable_refs(t) {
+        if let Some(name) = name_at_mut(t, r.position) {
+            if locals.contains(name) {
+                *name = qualify(name, namespace);
+            }
+        }
+    }
+}
+
+// All variables the tokens write to (which aren't in another namespace)
+fn written_variables(tokens: &[Token]) -> HashSet<String> {
+    let mut written: HashSet<String> = HashSet::new();
+    for t in tokens {
+        for r in variable_refs(t).into_iter().filter(|r| r.is_write) {
+            if let Some(name) = name_at(t, r.position) {
+                if !name.contains(NAMESPACE_SEP) {
+                    written.insert(name.clone());
+                }
+            }
+        }
+    }
+    written
+}
+
+impl Loader<'_> {
+    fn namespace_for(&self, path: &Path) -> String {
+        let relative: &Path = path.strip_prefix(&self.root_dir).unwrap_or(path);
+        relative
+            .with_extension("")
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+
+    fn load_file(&mut self, path: &Path, namespace: &str) -> Result<Vec<(Token, SourceLocation)>, String> {
+        let content: String = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read file `{}`: {}", path.display(), e))?;
+
+        let (header, content) =
+            split_header(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+        // The main file (without a namespace) is always global
+        let is_local_scope: bool = !namespace.is_empty() && header.scope != Some(Scope::Global);
+        match &self.header {
+            None => self.header = Some(header),
+            Some(main_header) => {
+                // Numbers are parsed while running, so there can only be one language for them
+                if header.numbers.is_some() && header.numbers != main_header.numbers {
+                    return Err(format!(
+                        "{}: Included files can't use other number words than the main file.",
+                        path.display()
+                    ));
+                }
+            }
+        }
+
+        let (file_tokens, is_valid_code) = tokenize_statements(&content, self.dialect);
+        self.is_valid_code &= is_valid_code;
+        // Both split the code the same way, so there is a statement for every token
+        let lines: Vec<usize> = locate_statements(&content, self.dialect)
+            .0
+            .iter()
+            .map(|n| n.start().line + 1)
+            .collect();
+
+        let written: HashSet<String> = written_variables(&file_tokens);
+        let locals: HashSet<String> = if is_local_scope {
+            let mut names: Vec<&String> = written.iter().collect();
+            names.sort();
+            self.locals
+                .extend(names.into_iter().map(|n| (namespace.to_string(), n.clone())));
+            written
+        } else {
+            self.globals.extend(written);
+            HashSet::new()
+        };
+
+        self.stack.push(path.to_path_buf());
+        let mut tokens: Vec<(Token, SourceLocation)> = Vec::new();
+        for (mut t, line) in file_tokens.into_iter().zip(lines) {
+            if t.op != Operation::Include {
+                qualify_labels(&mut t, namespace);
+                qualify_variables(&mut t, namespace, &locals);
+                let location = SourceLocation {
+                    file: path.to_path_buf(),
+                    line,
+                };
+                tokens.push((t, location));
+                continue;
+            }
+
+            if t.nargs != 1 {
+                return Err(format!(
+                    "{}: Include needs exactly one argument (the path without `.mt`).",
+                    path.display()
+                ));
+            }
+            let included_path: PathBuf =
+                canonical_path(&parent_dir(path).join(format!("{}.mt", t.args[0])))?;
+            if self.confined && !included_path.starts_with(&self.root_dir) {
+                return Err(format!(
+                    "{}: `{}` is outside of the program's directory, which the sandbox doesn't allow.",
+                    path.display(),
+                    t.args[0]
+                ));
+            }
+
+            if self.stack.contains(&included_path) {
+                let mut cycle: Vec<String> = self
+                    .stack
+                    .iter()
+                    .skip_while(|n| **n != included_path)
+                    .map(|n| n.display().to_string())
+                    .collect();
+                cycle.push(included_path.display().to_string());
+                return Err(format!("Include cycle: {}", cycle.join(" -> ")));
+            }
+            if self.included.contains(&included_path) {
+                continue;
+            }
+
+            let included_namespace: String = self.namespace_for(&included_path);
+            tokens.extend(self.load_file(&included_path, &included_namespace)?);
+        }
+        self.stack.pop();
+        self.included.insert(path.to_path_buf());
+
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_labels;
+    use std::fs;
+
+    // Creates a fresh directory with the given files in it
+    fn create_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("motts-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn includes_and_namespaces() {
+        let dir = create_files(
+            "includes",
+            &[
+                (
+                    "main.mt",
+                    "Including lib/first. Including lib/second. labels. Equal onee onee lib/first::labels.",
+                ),
+                ("lib/first.mt", "#scope global\nonee One. labels. Equal onee onee labels."),
+                // Includes `first` again, which is skipped
+                ("lib/second.mt", "including first. labels. Lower onee onee labels."),
+            ],
+        );
+        let program = load_program(dir.join("main.mt").to_str().unwrap(), &Dialect::standard()).unwrap();
+        assert!(program.is_valid_code);
+
+        let names: Vec<String> = program
+            .tokens
+            .iter()
+            .map(|t| format!("{} {}", t.name, t.args.join(" ")))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "onee One",
+                "lib/first::labels ",
+                "Equal onee onee lib/first::labels",
+                "lib/second::labels ",
+                "Lower onee onee lib/second::labels",
+                "labels ",
+                "Equal onee onee lib/first::labels",
+                " ",
+            ]
+        );
+
+        // Every token knows its file and line (the header line of `first` is counted as well)
+        let locations: Vec<Option<(String, usize)>> = program
+            .locations
+            .iter()
+            .map(|n| {
+                n.as_ref().map(|n| {
+                    let file = n.file.strip_prefix(fs::canonicalize(&dir).unwrap()).unwrap();
+                    (file.display().to_string(), n.line)
+                })
+            })
+            .collect();
+        let at = |file: &str, line: usize| Some((String::from(file), line));
+        assert_eq!(
+            locations,
+            vec![
+                at("lib/first.mt", 2),
+                at("lib/first.mt", 2),
+                at("lib/first.mt", 2),
+                at("lib/second.mt", 1),
+                at("lib/second.mt", 1),
+                at("main.mt", 1),
+                at("main.mt", 1),
+                None,
+            ]
+        );
+
+        let labels = create_labels(program.tokens);
+        assert_eq!(labels.len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn variable_scopes() {
+        let dir = create_files(
+            "scopes",
+            &[
+                ("main.mt", "coun Five. Including counter. P coun spce done spce counter::coun."),
+                // `coun` and `done` are local, `spce` and `onee` are global
+                ("counter.mt", "coun Zero. onee One. Ad coun onee coun. done Two. P coun spce onee."),
+                ("constants.mt", "#scope global\nonee One."),
+            ],
+        );
+        let program = load_program(dir.join("main.mt").to_str().unwrap(), &Dialect::standard()).unwrap();
+        let names: Vec<String> = program
+            .tokens
+            .iter()
+            .map(|t| format!("{} {}", t.name, t.args.join(" ")))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "coun Five",
+                "counter::coun Zero",
+                "counter::onee One",
+                "Ad counter::coun counter::onee counter::coun",
+                "counter::done Two",
+                "P counter::coun spce counter::onee",
+                "P coun spce done spce counter::coun",
+                " ",
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn include_errors() {
+        let dir = create_files(
+            "include-errors",
+            &[
+                ("cycle.mt", "Including other."),
+                ("other.mt", "Including cycle."),
+                ("missing.mt", "Including nowhere."),
+                ("lang.mt", "#numbers german\nIncluding french."),
+                ("french.mt", "#numbers french\n"),
+                ("outside.mt", "P newl."),
+                ("sandbox/inside.mt", "Including lib/helper."),
+                ("sandbox/lib/helper.mt", "P newl."),
+            ],
+        );
+        let load = |name: &str| load_program(dir.join(name).to_str().unwrap(), &Dialect::standard());
+
+        match load("cycle.mt") {
+            Ok(_) => panic!(),
+            Err(e) => assert!(e.starts_with("Include cycle:")),
+        }
+        assert!(load("missing.mt").is_err());
+        assert!(load("lang.mt").is_err());
+
+        // The sandbox only allows including files next to (or below) the main file.
+        // Paths can't contain dots, so the only way out is an absolute path.
+        fs::write(
+            dir.join("sandbox/escape.mt"),
+            format!("Including {}.", dir.join("outside").display()),
+        )
+        .unwrap();
+        let confined = |name: &str| load_confined_program(dir.join(name).to_str().unwrap(), &Dialect::standard());
+        assert!(load("sandbox/escape.mt").is_ok());
+        match confined("sandbox/escape.mt") {
+            Ok(_) => panic!(),
+            Err(e) => assert!(e.contains("outside of the program's directory")),
+        }
+        assert_eq!(confined("sandbox/inside.mt").unwrap().tokens.len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
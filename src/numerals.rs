@@ -0,0 +1,139 @@
+// The words which can be used to spell out numbers, for every supported language.
+// A language is picked with the `#numbers <language>` header or the `--numbers` option.
+
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Language {
+    #[default]
+    English,
+    French,
+    German,
+    Spanish,
+}
+
+pub struct Numerals {
+    digits: HashMap<&'static str, i8>,
+    minus: &'static [&'static str],
+    comma: &'static [&'static str],
+}
+
+impl Numerals {
+    // All words are expected in lowercase.
+    pub fn digit(&self, word: &str) -> Option<i8> {
+        self.digits.get(word).copied()
+    }
+
+    pub fn is_minus(&self, word: &str) -> bool {
+        self.minus.contains(&word)
+    }
+
+    pub fn is_comma(&self, word: &str) -> bool {
+        self.comma.contains(&word)
+    }
+}
+
+// Builds the digit map, every digit can have several spellings (e.g. with and without accents)
+fn digit_map(words: [&[&'static str]; 10]) -> HashMap<&'static str, i8> {
+    let mut digits = HashMap::new();
+    for (value, spellings) in words.iter().enumerate() {
+        for word in spellings.iter() {
+            digits.insert(*word, value as i8);
+        }
+    }
+    digits
+}
+
+lazy_static! {
+    static ref NUMERALS: HashMap<Language, Numerals> = HashMap::from([
+        (
+            Language::English,
+            Numerals {
+                digits: digit_map([
+                    &["zero"], &["one"], &["two"], &["three"], &["four"],
+                    &["five"], &["six"], &["seven"], &["eight"], &["nine"],
+                ]),
+                minus: &["minus"],
+                comma: &["comma"],
+            }
+        ),
+        (
+            Language::French,
+            Numerals {
+                digits: digit_map([
+                    &["zéro", "zero"], &["un", "une"], &["deux"], &["trois"], &["quatre"],
+                    &["cinq"], &["six"], &["sept"], &["huit"], &["neuf"],
+                ]),
+                minus: &["moins"],
+                comma: &["virgule"],
+            }
+        ),
+        (
+            Language::German,
+            Numerals {
+                digits: digit_map([
+                    &["null"], &["eins"], &["zwei"], &["drei"], &["vier"],
+                    &["fünf", "fuenf"], &["sechs"], &["sieben"], &["acht"], &["neun"],
+                ]),
+                minus: &["minus"],
+                comma: &["komma"],
+            }
+        ),
+        (
+            Language::Spanish,
+            Numerals {
+                digits: digit_map([
+                    &["cero"], &["uno", "una"], &["dos"], &["tres"], &["cuatro"],
+                    &["cinco"], &["seis"], &["siete"], &["ocho"], &["nueve"],
+                ]),
+                minus: &["menos"],
+                comma: &["coma"],
+            }
+        ),
+    ]);
+}
+
+impl Language {
+    pub fn from_name(name: &str) -> Option<Language> {
+        match name.to_lowercase().as_str() {
+            "en" | "english" => Some(Language::English),
+            "fr" | "french" | "français" | "francais" => Some(Language::French),
+            "de" | "german" | "deutsch" => Some(Language::German),
+            "es" | "spanish" | "español" | "espanol" => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+
+    pub fn numerals(self) -> &'static Numerals {
+        &NUMERALS[&self]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_names() {
+        assert_eq!(Language::from_name("fr"), Some(Language::French));
+        assert_eq!(Language::from_name("Deutsch"), Some(Language::German));
+        assert_eq!(Language::from_name("español"), Some(Language::Spanish));
+        assert_eq!(Language::from_name("klingon"), None);
+    }
+
+    #[test]
+    fn every_language_has_all_digits() {
+        for language in [
+            Language::English,
+            Language::French,
+            Language::German,
+            Language::Spanish,
+        ] {
+            let numerals = language.numerals();
+            let mut values: Vec<i8> = numerals.digits.values().copied().collect();
+            values.sort();
+            values.dedup();
+            assert_eq!(values, (0..10).collect::<Vec<i8>>());
+        }
+    }
+}
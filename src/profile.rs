@@ -0,0 +1,255 @@
+// `motts --profile`: counts how often every statement runs and how long it takes.
+//
+// The time of a statement is the wall time from its start to the start of the next statement,
+// so an Input includes the time spent waiting for the user. Statements are also summed up per
+// block: the statements from a label up to the next one (or from the start of the program).
+//
+// `--profile-folded <file>` writes the times in the folded stack format of flamegraph tools
+// (`program;block;statement microseconds`, one line per statement).
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::dialect::Dialect;
+use crate::disasm::decode;
+use crate::numerals::Numerals;
+use crate::{Observer, Operation, Token, Variable};
+
+pub struct Profile {
+    counts: Vec<u64>,
+    times: Vec<Duration>,
+    // The statement which is running right now and when it started
+    current: Option<(usize, Instant)>,
+}
+
+impl Observer for Profile {
+    fn enter(&mut self, ip: usize, _vars: &HashMap<String, Variable>) -> Result<(), String> {
+        let now: Instant = Instant::now();
+        self.stop(now);
+        if ip < self.counts.len() {
+            self.counts[ip] += 1;
+            self.current = Some((ip, now));
+        }
+        Ok(())
+    }
+}
+
+// Statements or blocks, summed up
+struct Entry {
+    name: String,
+    count: u64,
+    time: Duration,
+}
+
+impl Profile {
+    pub fn new(token_count: usize) -> Profile {
+        Profile {
+            counts: vec![0; token_count],
+            times: vec![Duration::ZERO; token_count],
+            current: None,
+        }
+    }
+
+    // Called once the program stopped
+    pub fn finish(&mut self) {
+        self.stop(Instant::now());
+    }
+
+    fn stop(&mut self, now: Instant) {
+        if let Some((ip, started)) = self.current.take() {
+            self.times[ip] += now - started;
+        }
+    }
+
+    fn total_time(&self) -> Duration {
+        self.times.iter().sum()
+    }
+
+    // The block every statement belongs to
+    fn blocks(tokens: &[Token]) -> Vec<String> {
+        let mut block = String::from("(start)");
+        tokens
+            .iter()
+            .map(|t| {
+                if t.op == Operation::Label {
+                    block = t.name.clone();
+                }
+                block.clone()
+            })
+            .collect()
+    }
+
+    // Statements and blocks which ran, the slowest first
+    fn entries(
+        &self,
+        tokens: &[Token],
+        labels: &HashMap<String, usize>,
+        dialect: &Dialect,
+        numerals: &Numerals,
+    ) -> (Vec<Entry>, Vec<Entry>) {
+        let mut statements: Vec<(usize, Entry)> = vec![];
+        let mut blocks: Vec<Entry> = vec![];
+        for ((ip, t), block) in tokens.iter().enumerate().zip(Profile::blocks(tokens)) {
+            let (count, time) = (self.counts[ip], self.times[ip]);
+            if count == 0 {
+                continue;
+            }
+            statements.push((
+                ip,
+                Entry {
+                    name: decode(ip, t, labels, dialect, numerals),
+                    count,
+                    time,
+                },
+            ));
+            // A label can start several blocks (when it is defined twice), they are summed up
+            match blocks.iter_mut().find(|b| b.name == block) {
+                Some(b) => {
+                    b.count += count;
+                    b.time += time;
+                }
+                None => blocks.push(Entry { name: block, count, time }),
+            }
+        }
+        statements.sort_by(|(a_ip, a), (b_ip, b)| {
+            b.time.cmp(&a.time).then(b.count.cmp(&a.count)).then(a_ip.cmp(b_ip))
+        });
+        blocks.sort_by(|a, b| b.time.cmp(&a.time).then(b.count.cmp(&a.count)));
+
+        let statements: Vec<Entry> = statements
+            .into_iter()
+            .map(|(ip, e)| Entry {
+                name: format!("{:>4}  {}", ip, e.name),
+                ..e
+            })
+            .collect();
+        (statements, blocks)
+    }
+
+    // The report printed after the program
+    pub fn report(
+        &self,
+        tokens: &[Token],
+        labels: &HashMap<String, usize>,
+        dialect: &Dialect,
+        numerals: &Numerals,
+    ) -> String {
+        let total: Duration = self.total_time();
+        let executed: u64 = self.counts.iter().sum();
+        let (statements, blocks) = self.entries(tokens, labels, dialect, numerals);
+
+        let mut out: String = format!(
+            "Profile: {} statements run in {}\n",
+            executed,
+            format_duration(total)
+        );
+        out.push_str(&table("statements", " idx  operation", &statements, total));
+        out.push_str(&table("blocks", "label", &blocks, total));
+        out
+    }
+
+    // One line per statement which ran: `program;block;idx operation microseconds`
+    pub fn folded(
+        &self,
+        program: &str,
+        tokens: &[Token],
+        labels: &HashMap<String, usize>,
+        dialect: &Dialect,
+        numerals: &Numerals,
+    ) -> String {
+        // `;` separates the frames
+        let frame = |name: &str| name.replace(';', ",");
+        let mut out = String::new();
+        for ((ip, t), block) in tokens.iter().enumerate().zip(Profile::blocks(tokens)) {
+            if self.counts[ip] == 0 {
+                continue;
+            }
+            out.push_str(&format!(
+                "{};{};{} {} {}\n",
+                frame(program),
+                frame(&block),
+                ip,
+                frame(&decode(ip, t, labels, dialect, numerals)),
+                self.times[ip].as_micros()
+            ));
+        }
+        out
+    }
+}
+
+fn format_duration(time: Duration) -> String {
+    let seconds: f64 = time.as_secs_f64();
+    if seconds >= 1.0 {
+        format!("{:.3}s", seconds)
+    } else if seconds >= 0.001 {
+        format!("{:.3}ms", seconds * 1e3)
+    } else {
+        format!("{:.1}µs", seconds * 1e6)
+    }
+}
+
+fn table(title: &str, heading: &str, entries: &[Entry], total: Duration) -> String {
+    let mut out: String = format!("\n{}:\n{:>10}  {:>10}  {:>6}  {}\n", title, "count", "time", "time%", heading);
+    for entry in entries {
+        let share: f64 = if total.is_zero() {
+            0.0
+        } else {
+            entry.time.as_secs_f64() / total.as_secs_f64() * 100.0
+        };
+        out.push_str(&format!(
+            "{:>10}  {:>10}  {:>5.1}%  {}\n",
+            entry.count,
+            format_duration(entry.time),
+            share,
+            entry.name
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+    use crate::{compile_mt_code, create_labels, execute_profiled, ExecutionConfig};
+
+    #[test]
+    fn counts_statements_and_blocks() {
+        let dialect = Dialect::standard();
+        let (tokens, _) = compile_mt_code(
+            "summ Zero. ones One. maxi Four. labels. Ad summ ones summ. Lower summ maxi labels. P summ.",
+            &dialect,
+        );
+        let labels = create_labels(tokens.clone());
+        let mut profile = Profile::new(tokens.len());
+        let mut console = BufferConsole::new("");
+        let status = execute_profiled(
+            tokens.clone(),
+            labels.clone(),
+            &ExecutionConfig::default(),
+            &mut console,
+            &mut profile,
+        );
+        assert_eq!(status.unwrap(), 0);
+        assert_eq!(console.output, "4\n");
+        // Jumps continue after the label, so it only runs once
+        assert_eq!(profile.counts, vec![1, 1, 1, 1, 4, 4, 1, 1]);
+        assert!(profile.current.is_none());
+
+        let numerals = crate::numerals::Language::English.numerals();
+        let (statements, blocks) = profile.entries(&tokens, &labels, &dialect, numerals);
+        assert_eq!(statements.len(), 8);
+        let block_counts: Vec<(&str, u64)> = {
+            let mut counts: Vec<(&str, u64)> = blocks.iter().map(|b| (b.name.as_str(), b.count)).collect();
+            counts.sort();
+            counts
+        };
+        assert_eq!(block_counts, vec![("(start)", 3), ("labels", 11)]);
+
+        let folded = profile.folded("loop", &tokens, &labels, &dialect, numerals);
+        let lines: Vec<&str> = folded.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(lines[4].starts_with("loop;labels;4 ADD summ ones -> summ "), "{}", lines[4]);
+        assert!(profile.report(&tokens, &labels, &dialect, numerals).starts_with("Profile: 14 statements run in "));
+    }
+}
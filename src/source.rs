@@ -0,0 +1,143 @@
+// Where the statements and their words are in the source code, for everything that has to
+// point back into the file (editor support, listings, ...).
+//
+// Statements are split exactly like `tokenize_statements` does, so the n-th statement here is
+// the n-th token there. The words of a statement are split like in `tokenize_text_code`:
+// `words[0]` is the operation word (the token's name), `words[i + 1]` is `args[i]`.
+
+use crate::dialect::Dialect;
+use crate::{strip_comments, tokenize_text_code, LineParseError, Token, STATEMENT_SEP};
+
+// Line and column (in characters) in the file, both starting at 0
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub struct Word {
+    pub text: String,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Word {
+    pub fn contains(&self, position: Position) -> bool {
+        self.start <= position && position <= self.end
+    }
+}
+
+#[derive(Debug)]
+pub struct Statement {
+    pub words: Vec<Word>,
+    pub token: Result<Token, LineParseError>,
+}
+
+impl Statement {
+    // From the first to the last word
+    pub fn start(&self) -> Position {
+        self.words[0].start
+    }
+
+    pub fn end(&self) -> Position {
+        self.words[self.words.len() - 1].end
+    }
+}
+
+// Byte offsets where the lines start
+struct Lines<'a> {
+    code: &'a str,
+    starts: Vec<usize>,
+}
+
+impl Lines<'_> {
+    fn new(code: &str) -> Lines<'_> {
+        let mut starts: Vec<usize> = vec![0];
+        starts.extend(code.match_indices('\n').map(|(i, _)| i + 1));
+        Lines { code, starts }
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let line: usize = self.starts.partition_point(|s| *s <= offset) - 1;
+        Position {
+            line,
+            column: self.code[self.starts[line]..offset].chars().count(),
+        }
+    }
+}
+
+// Finds the statements in the code (with the header already split off, see `split_header`).
+// Returns whether the last statement is missing its dot as well.
+pub fn locate_statements(code: &str, dialect: &Dialect) -> (Vec<Statement>, bool) {
+    // Comments are cut off at the end of their line, so everything else stays where it is
+    let code: String = strip_comments(code);
+    let lines = Lines::new(&code);
+
+    // (byte offset, text) of every statement
+    let mut pieces: Vec<(usize, &str)> = vec![];
+    let mut start: usize = 0;
+    for (end, _) in code.match_indices(STATEMENT_SEP) {
+        pieces.push((start, &code[start..end]));
+        start = end + STATEMENT_SEP.len();
+    }
+    // What comes after the last dot is only a statement if it isn't empty
+    let missing_dot: bool = !code[start..].trim().is_empty();
+    if missing_dot {
+        pieces.push((start, &code[start..]));
+    }
+
+    let statements = pieces
+        .into_iter()
+        .map(|(start, piece)| {
+            let trimmed: &str = piece.trim();
+            let mut offset: usize = start + (piece.len() - piece.trim_start().len());
+            let mut words: Vec<Word> = vec![];
+            for text in trimmed.split(' ') {
+                words.push(Word {
+                    text: text.to_string(),
+                    start: lines.position(offset),
+                    end: lines.position(offset + text.len()),
+                });
+                offset += text.len() + 1;
+            }
+            Statement {
+                words,
+                token: tokenize_text_code(piece, dialect),
+            }
+        })
+        .collect();
+    (statements, missing_dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Operation;
+
+    #[test]
+    fn statement_positions() {
+        let code = "numb One. // one.\n  P  numb.\n\nlabels.\nAd";
+        let (statements, missing_dot) = locate_statements(code, &Dialect::standard());
+        assert!(missing_dot);
+        assert_eq!(statements.len(), 4);
+
+        let print = &statements[1];
+        let texts: Vec<&str> = print.words.iter().map(|w| w.text.as_str()).collect();
+        // Like the args of the token, the empty word between the spaces is kept
+        assert_eq!(texts, vec!["P", "", "numb"]);
+        assert_eq!(print.token.as_ref().unwrap().args, vec!["", "numb"]);
+        assert_eq!(print.start(), Position { line: 1, column: 2 });
+        assert_eq!(print.words[2].start, Position { line: 1, column: 5 });
+        assert_eq!(print.end(), Position { line: 1, column: 9 });
+
+        assert_eq!(statements[2].token.as_ref().unwrap().op, Operation::Label);
+        assert_eq!(statements[2].start(), Position { line: 3, column: 0 });
+        assert!(statements[3].token.is_ok());
+        assert_eq!(statements[3].start(), Position { line: 4, column: 0 });
+
+        let (statements, missing_dot) = locate_statements("zéro Un.\n", &Dialect::standard());
+        assert!(!missing_dot);
+        assert_eq!(statements[0].words[1].start, Position { line: 0, column: 5 });
+    }
+}
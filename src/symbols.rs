@@ -0,0 +1,167 @@
+// Finds the variables and labels a token refers to, without running it.
+// Used by everything that needs to rename or look at names: module scopes, editor support, lints, ...
+
+use crate::{Operation, Token};
+
+// Where in a token a name is found
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NamePosition {
+    // The operation word itself (e.g. the variable name of a Var token)
+    Name,
+    Arg(usize),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct VariableRef {
+    pub position: NamePosition,
+    // Whether the variable is written (and created if needed) or only read
+    pub is_write: bool,
+}
+
+impl VariableRef {
+    fn read(i: usize) -> VariableRef {
+        VariableRef {
+            position: NamePosition::Arg(i),
+            is_write: false,
+        }
+    }
+
+    fn write(position: NamePosition) -> VariableRef {
+        VariableRef {
+            position,
+            is_write: true,
+        }
+    }
+}
+
+// Returns the word at the given position of the token (if the token is long enough)
+pub fn name_at(t: &Token, position: NamePosition) -> Option<&String> {
+    match position {
+        NamePosition::Name => Some(&t.name),
+        NamePosition::Arg(i) => t.args.get(i),
+    }
+}
+
+pub fn name_at_mut(t: &mut Token, position: NamePosition) -> Option<&mut String> {
+    match position {
+        NamePosition::Name => Some(&mut t.name),
+        NamePosition::Arg(i) => t.args.get_mut(i),
+    }
+}
+
+fn starts_with_lowercase(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| !c.is_uppercase())
+}
+
+// All the variables the token uses. Positions the token doesn't have are left out.
+pub fn variable_refs(t: &Token) -> Vec<VariableRef> {
+    let refs: Vec<VariableRef> = match t.op {
+        Operation::Print => (0..t.nargs).map(VariableRef::read).collect(),
+        // <in> <Type> <dst> [prompt]
+        Operation::Input => vec![
+            VariableRef::write(NamePosition::Arg(1)),
+            VariableRef::read(2),
+        ],
+        Operation::Add | Operation::Sub | Operation::Mul | Operation::Div => vec![
+            VariableRef::read(0),
+            VariableRef::read(1),
+            VariableRef::write(NamePosition::Arg(2)),
+        ],
+        Operation::Var => vec![VariableRef::write(NamePosition::Name)],
+        Operation::Branch => vec![VariableRef::read(0), VariableRef::read(1)],
+        // <env> NAME <dst>
+        Operation::Env => vec![VariableRef::write(NamePosition::Arg(1))],
+        Operation::File => match file_operation_letter(t) {
+            Some('r') | Some('l') | Some('e') => vec![
+                VariableRef::read(0),
+                VariableRef::write(NamePosition::Arg(1)),
+            ],
+            Some('w') | Some('a') => (0..t.nargs).map(VariableRef::read).collect(),
+            _ => vec![],
+        },
+        // Either a variable or a spelled out number
+        Operation::Exit => match t.args.first() {
+            Some(n) if starts_with_lowercase(n) => vec![VariableRef::read(0)],
+            _ => vec![],
+        },
+        Operation::Label | Operation::Include | Operation::Invalid => vec![],
+    };
+
+    refs.into_iter()
+        .filter(|r| name_at(t, r.position).is_some())
+        .collect()
+}
+
+// Where a jump target (label) is referenced in the token, if anywhere
+pub fn label_ref(t: &Token) -> Option<NamePosition> {
+    let position = match t.op {
+        Operation::Branch => NamePosition::Arg(2),
+        // Reading a file line by line jumps to a label at the end of the file
+        Operation::File if file_operation_letter(t) == Some('l') => NamePosition::Arg(2),
+        _ => return None,
+    };
+    name_at(t, position).map(|_| position)
+}
+
+fn file_operation_letter(t: &Token) -> Option<char> {
+    t.name.chars().next().map(|c| c.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Dialect;
+    use crate::tokenize_text_code;
+
+    fn written_and_read(code: &str) -> (Vec<String>, Vec<String>) {
+        let t = tokenize_text_code(code, &Dialect::standard()).unwrap();
+        let mut written = vec![];
+        let mut read = vec![];
+        for r in variable_refs(&t) {
+            let name = name_at(&t, r.position).unwrap().clone();
+            if r.is_write {
+                written.push(name);
+            } else {
+                read.push(name);
+            }
+        }
+        (written, read)
+    }
+
+    #[test]
+    fn variable_references() {
+        assert_eq!(
+            written_and_read("Ad onee twoo rslt"),
+            (vec!["rslt".to_string()], vec!["onee".to_string(), "twoo".to_string()])
+        );
+        assert_eq!(
+            written_and_read("numb One two"),
+            (vec!["numb".to_string()], vec![])
+        );
+        assert_eq!(
+            written_and_read("i Num gues text"),
+            (vec!["gues".to_string()], vec!["text".to_string()])
+        );
+        assert_eq!(
+            written_and_read("writeout path text newl"),
+            (vec![], vec!["path".to_string(), "text".to_string(), "newl".to_string()])
+        );
+        assert_eq!(written_and_read("Failure One"), (vec![], vec![]));
+        assert_eq!(written_and_read("Stopped code"), (vec![], vec!["code".to_string()]));
+        // Missing args are left out
+        assert_eq!(written_and_read("Ad onee"), (vec![], vec!["onee".to_string()]));
+    }
+
+    #[test]
+    fn label_references() {
+        let dialect = Dialect::standard();
+        let t = tokenize_text_code("Equal a b labels", &dialect).unwrap();
+        assert_eq!(label_ref(&t), Some(NamePosition::Arg(2)));
+        let t = tokenize_text_code("linefrom path line finish", &dialect).unwrap();
+        assert_eq!(label_ref(&t), Some(NamePosition::Arg(2)));
+        let t = tokenize_text_code("readfile path text", &dialect).unwrap();
+        assert_eq!(label_ref(&t), None);
+        let t = tokenize_text_code("Equal a b", &dialect).unwrap();
+        assert_eq!(label_ref(&t), None);
+    }
+}
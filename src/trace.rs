@@ -0,0 +1,540 @@
+// `motts --record <trace> prog.mt` writes what the program did to a trace file: the program
+// arguments, every line it read and every statement it ran with the variables it changed.
+//
+// `motts --replay <trace> prog.mt` runs the program again, reading the recorded lines instead of
+// stdin, and stops at the first statement which doesn't do what the trace says (e.g. after the
+// program was changed). The trace is JSON:
+//
+//   {"version": 1, "args": [], "inputs": ["50", "25"],
+//    "steps": [{"ip": 0, "set": [{"var": "numb", "number": 50}]}, {"ip": 1}, ...],
+//    "end": {"exit": 0}}
+//
+// The end is `{"error": "...", "token": 4}` if the program stopped with an error.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+
+use colored::*;
+use serde_json::{json, Map, Value};
+
+use crate::console::{Console, StdConsole};
+use crate::dialect::Dialect;
+use crate::disasm::decode;
+use crate::numerals::Numerals;
+use crate::{
+    execute_observed, print_runtime_error, ExecutionConfig, InputError, Observer, RuntimeError, Token, Type, Variable,
+};
+
+// Changed whenever the format of traces changes, older traces are refused
+const TRACE_VERSION: u64 = 1;
+
+// A statement which ran and the variables it set
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    ip: usize,
+    set: Vec<(String, Type)>,
+}
+
+// How the program stopped
+#[derive(Debug, Clone, PartialEq)]
+enum End {
+    Exit(i32),
+    Error { token: usize, msg: String },
+}
+
+impl End {
+    fn of(result: &Result<i32, RuntimeError>) -> End {
+        match result {
+            Ok(n) => End::Exit(*n),
+            Err(e) => End::Error {
+                token: e.token_num,
+                msg: e.msg.clone(),
+            },
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            End::Exit(n) => format!("exited with {}", n),
+            End::Error { token, msg } => format!("stopped with `{}` on token {}", msg, token),
+        }
+    }
+}
+
+struct Trace {
+    args: Vec<String>,
+    inputs: Vec<String>,
+    steps: Vec<Step>,
+    end: End,
+}
+
+// Numbers which JSON can't hold (inf, NaN) are written as strings
+fn encode_type(data: &Type) -> (&'static str, Value) {
+    match data {
+        Type::Number(n) if n.is_finite() => ("number", json!(n)),
+        Type::Number(n) => ("number", json!(n.to_string())),
+        Type::String(s) => ("string", json!(s)),
+    }
+}
+
+fn decode_type(value: &Map<String, Value>) -> Option<Type> {
+    if let Some(s) = value.get("string") {
+        return Some(Type::String(s.as_str()?.to_string()));
+    }
+    match value.get("number")? {
+        Value::String(n) => n.parse().ok().map(Type::Number),
+        n => n.as_f64().map(Type::Number),
+    }
+}
+
+fn encode(trace: &Trace) -> String {
+    let steps: Vec<Value> = trace
+        .steps
+        .iter()
+        .map(|step| {
+            let mut value = json!({"ip": step.ip});
+            if !step.set.is_empty() {
+                let set: Vec<Value> = step
+                    .set
+                    .iter()
+                    .map(|(name, data)| {
+                        let (kind, data) = encode_type(data);
+                        json!({"var": name, kind: data})
+                    })
+                    .collect();
+                value["set"] = json!(set);
+            }
+            value
+        })
+        .collect();
+    let end: Value = match &trace.end {
+        End::Exit(n) => json!({"exit": n}),
+        End::Error { token, msg } => json!({"error": msg, "token": token}),
+    };
+    json!({
+        "version": TRACE_VERSION,
+        "args": trace.args,
+        "inputs": trace.inputs,
+        "steps": steps,
+        "end": end,
+    })
+    .to_string()
+}
+
+fn decode_step(value: &Value) -> Option<Step> {
+    let ip: usize = value["ip"].as_u64()? as usize;
+    let set: Vec<(String, Type)> = match value.get("set") {
+        Some(set) => set
+            .as_array()?
+            .iter()
+            .map(|n| {
+                let n = n.as_object()?;
+                Some((n.get("var")?.as_str()?.to_string(), decode_type(n)?))
+            })
+            .collect::<Option<Vec<(String, Type)>>>()?,
+        None => vec![],
+    };
+    Some(Step { ip, set })
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|n| n.as_str().map(String::from))
+        .collect()
+}
+
+fn decode_trace(text: &str) -> Result<Trace, String> {
+    let invalid = || String::from("The trace is damaged.");
+    let value: Value = serde_json::from_str(text).map_err(|_| invalid())?;
+    if value["version"].as_u64() != Some(TRACE_VERSION) {
+        return Err(String::from("The trace was recorded by another version of motts."));
+    }
+    let steps: Vec<Step> = value["steps"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(decode_step)
+        .collect::<Option<Vec<Step>>>()
+        .ok_or_else(invalid)?;
+    let end: End = match (&value["end"]["exit"], &value["end"]["error"]) {
+        (Value::Number(n), _) => End::Exit(n.as_i64().ok_or_else(invalid)? as i32),
+        (_, Value::String(msg)) => End::Error {
+            token: value["end"]["token"].as_u64().ok_or_else(invalid)? as usize,
+            msg: msg.clone(),
+        },
+        _ => return Err(invalid()),
+    };
+    Ok(Trace {
+        args: strings(&value["args"]).ok_or_else(invalid)?,
+        inputs: strings(&value["inputs"]).ok_or_else(invalid)?,
+        steps,
+        end,
+    })
+}
+
+// NaN is the same as NaN here, otherwise a NaN would change with every statement
+pub fn same_value(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (Type::Number(x), Type::Number(y)) => x == y || (x.is_nan() && y.is_nan()),
+        _ => a == b,
+    }
+}
+
+// Collects the steps of a running program
+#[derive(Default)]
+struct Recorder {
+    steps: Vec<Step>,
+    // The variables before the current statement
+    before: HashMap<String, Type>,
+}
+
+impl Observer for Recorder {
+    fn enter(&mut self, _ip: usize, vars: &HashMap<String, Variable>) -> Result<(), String> {
+        // Only needed before the first statement, afterwards `leave` keeps it up to date
+        if self.steps.is_empty() {
+            self.before = vars.iter().map(|(k, v)| (k.clone(), v.data.clone())).collect();
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self, ip: usize, vars: &HashMap<String, Variable>) -> Result<(), String> {
+        let mut set: Vec<(String, Type)> = vars
+            .iter()
+            .filter(|(name, v)| !self.before.get(*name).is_some_and(|old| same_value(old, &v.data)))
+            .map(|(name, v)| (name.clone(), v.data.clone()))
+            .collect();
+        set.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, data) in &set {
+            self.before.insert(name.clone(), data.clone());
+        }
+        self.steps.push(Step { ip, set });
+        Ok(())
+    }
+}
+
+// Records the steps like `Recorder` and stops at the first one the trace doesn't have
+struct Replayer<'a> {
+    recorder: Recorder,
+    expected: &'a [Step],
+    tokens: &'a [Token],
+    labels: &'a HashMap<String, usize>,
+    dialect: &'a Dialect,
+    numerals: &'a Numerals,
+    // Why the replay was stopped
+    divergence: Option<String>,
+}
+
+// ` setting a = 1, b = "x"` (nothing if the step didn't set any variable)
+fn describe_changes(step: &Step) -> String {
+    let set: Vec<String> = step
+        .set
+        .iter()
+        .map(|(name, data)| match data {
+            Type::Number(n) => format!("{} = {}", name, n),
+            Type::String(s) => format!("{} = {:?}", name, s),
+        })
+        .collect();
+    if set.is_empty() {
+        String::new()
+    } else {
+        format!(" setting {}", set.join(", "))
+    }
+}
+
+// The statements of the trace aren't known anymore if the program changed, only their index
+fn describe_expected(step: &Step) -> String {
+    format!("statement {}{}", step.ip, describe_changes(step))
+}
+
+impl Replayer<'_> {
+    fn describe(&self, step: &Step) -> String {
+        let statement: String = match self.tokens.get(step.ip) {
+            Some(t) => decode(step.ip, t, self.labels, self.dialect, self.numerals),
+            None => String::from("(not in the program)"),
+        };
+        format!("statement {} ({}){}", step.ip, statement, describe_changes(step))
+    }
+}
+
+impl Observer for Replayer<'_> {
+    fn enter(&mut self, ip: usize, vars: &HashMap<String, Variable>) -> Result<(), String> {
+        self.recorder.enter(ip, vars)
+    }
+
+    fn leave(&mut self, ip: usize, vars: &HashMap<String, Variable>) -> Result<(), String> {
+        self.recorder.leave(ip, vars)?;
+        let index: usize = self.recorder.steps.len() - 1;
+        let step: &Step = &self.recorder.steps[index];
+        let matches: bool = self.expected.get(index).is_some_and(|expected| {
+            expected.ip == step.ip
+                && expected.set.len() == step.set.len()
+                && expected
+                    .set
+                    .iter()
+                    .zip(&step.set)
+                    .all(|(a, b)| a.0 == b.0 && same_value(&a.1, &b.1))
+        });
+        if matches {
+            return Ok(());
+        }
+        let expected: String = match self.expected.get(index) {
+            Some(n) => format!("the trace has {}", describe_expected(n)),
+            None => String::from("the trace has already ended"),
+        };
+        let divergence = format!("Step {}: {}, but {} ran.", index + 1, expected, self.describe(step));
+        self.divergence = Some(divergence.clone());
+        Err(divergence)
+    }
+}
+
+// Records the lines read through `inner`
+struct RecordingConsole<'a> {
+    inner: &'a mut dyn Console,
+    inputs: Vec<String>,
+}
+
+impl Console for RecordingConsole<'_> {
+    fn write(&mut self, text: &str) {
+        self.inner.write(text);
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, InputError> {
+        let line = self.inner.read_line()?;
+        if let Some(n) = &line {
+            self.inputs.push(n.clone());
+        }
+        Ok(line)
+    }
+}
+
+// Writes to stdout, but reads the recorded lines
+struct ReplayConsole {
+    inputs: VecDeque<String>,
+}
+
+impl Console for ReplayConsole {
+    fn write(&mut self, text: &str) {
+        StdConsole.write(text);
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, InputError> {
+        Ok(self.inputs.pop_front())
+    }
+}
+
+fn finish_run(result: Result<i32, RuntimeError>) -> i32 {
+    match result {
+        Ok(status) => status,
+        Err(e) => {
+            print_runtime_error(&e);
+            1
+        }
+    }
+}
+
+// Runs the program and writes the trace to `path`, returns the exit status
+pub fn record(
+    path: &str,
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+) -> i32 {
+    let mut recorder = Recorder::default();
+    let mut console = RecordingConsole {
+        inner: &mut StdConsole,
+        inputs: vec![],
+    };
+    let result = execute_observed(tokens, labels, config, &mut console, &mut recorder);
+    let trace = Trace {
+        args: config.program_args.clone(),
+        inputs: console.inputs,
+        steps: recorder.steps,
+        end: End::of(&result),
+    };
+    let status: i32 = finish_run(result);
+    if let Err(e) = fs::write(path, encode(&trace)) {
+        println!("{} Couldn't write `{}`: {}", "Error:".red(), path, e);
+        return 1;
+    }
+    status
+}
+
+// Runs the program with the arguments and inputs from the trace at `path`.
+// Returns the exit status, 1 if the program did something else than the trace says.
+pub fn replay(
+    path: &str,
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+) -> i32 {
+    let trace: Trace = match fs::read_to_string(path) {
+        Ok(n) => match decode_trace(&n) {
+            Ok(n) => n,
+            Err(e) => {
+                println!("{} {}", "Error:".red(), e);
+                return 1;
+            }
+        },
+        Err(e) => {
+            println!("{} Couldn't read `{}`: {}", "Error:".red(), path, e);
+            return 1;
+        }
+    };
+    let config = ExecutionConfig {
+        program_args: trace.args.clone(),
+        ..config.clone()
+    };
+    let mut console = ReplayConsole {
+        inputs: trace.inputs.iter().cloned().collect(),
+    };
+    let (divergence, result) = replay_trace(&trace, tokens, labels, &config, &mut console);
+
+    // Like the profile, the verdict goes to stderr
+    match divergence {
+        Some(divergence) => {
+            eprintln!("{} {}", "Replay diverged:".red(), divergence);
+            1
+        }
+        None => {
+            eprintln!(
+                "{}",
+                format!("Replay matches the trace ({} statements).", trace.steps.len()).green()
+            );
+            finish_run(result)
+        }
+    }
+}
+
+// The first difference to the trace (if any) and how the program ended
+fn replay_trace(
+    trace: &Trace,
+    tokens: Vec<Token>,
+    labels: HashMap<String, usize>,
+    config: &ExecutionConfig,
+    console: &mut dyn Console,
+) -> (Option<String>, Result<i32, RuntimeError>) {
+    let mut replayer = Replayer {
+        recorder: Recorder::default(),
+        expected: &trace.steps,
+        tokens: &tokens,
+        labels: &labels,
+        dialect: &config.dialect,
+        numerals: config.numbers.numerals(),
+        divergence: None,
+    };
+    let result = execute_observed(tokens.clone(), labels.clone(), config, console, &mut replayer);
+    if let Some(divergence) = replayer.divergence {
+        return (Some(divergence), result);
+    }
+
+    let ran: usize = replayer.recorder.steps.len();
+    let end: End = End::of(&result);
+    let divergence: Option<String> = if ran < trace.steps.len() {
+        Some(format!(
+            "Step {}: the trace has {}, but the program {}.",
+            ran + 1,
+            describe_expected(&trace.steps[ran]),
+            end.describe()
+        ))
+    } else if end != trace.end {
+        Some(format!(
+            "The program {}, but in the trace it {}.",
+            end.describe(),
+            trace.end.describe()
+        ))
+    } else {
+        None
+    };
+    (divergence, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+    use crate::{compile_mt_code, create_labels};
+
+    fn run(code: &str, input: &str) -> (Vec<Token>, HashMap<String, usize>, Trace) {
+        let (tokens, _) = compile_mt_code(code, &Dialect::standard());
+        let labels = create_labels(tokens.clone());
+        let mut recorder = Recorder::default();
+        let mut buffer = BufferConsole::new(input);
+        let mut console = RecordingConsole {
+            inner: &mut buffer,
+            inputs: vec![],
+        };
+        let result = execute_observed(
+            tokens.clone(),
+            labels.clone(),
+            &ExecutionConfig::default(),
+            &mut console,
+            &mut recorder,
+        );
+        let trace = Trace {
+            args: vec![],
+            inputs: console.inputs,
+            steps: recorder.steps,
+            end: End::of(&result),
+        };
+        (tokens, labels, trace)
+    }
+
+    fn replay_code(code: &str, trace: &Trace) -> Option<String> {
+        let (tokens, _) = compile_mt_code(code, &Dialect::standard());
+        let labels = create_labels(tokens.clone());
+        let mut console = BufferConsole::new(&trace.inputs.join("\n"));
+        replay_trace(trace, tokens, labels, &ExecutionConfig::default(), &mut console).0
+    }
+
+    #[test]
+    fn records_inputs_and_changes() {
+        let code = "i Numb numb. ones One. Ad numb ones numb. P numb.";
+        let (_, _, trace) = run(code, "41\n");
+        assert_eq!(trace.inputs, vec!["41"]);
+        assert_eq!(trace.end, End::Exit(0));
+        let steps: Vec<(usize, Vec<(String, Type)>)> = trace.steps.iter().map(|s| (s.ip, s.set.clone())).collect();
+        assert_eq!(
+            steps,
+            vec![
+                (0, vec![(String::from("numb"), Type::Number(41.0))]),
+                (1, vec![(String::from("ones"), Type::Number(1.0))]),
+                (2, vec![(String::from("numb"), Type::Number(42.0))]),
+                (3, vec![]),
+            ]
+        );
+
+        // The file keeps everything
+        let decoded = decode_trace(&encode(&trace)).unwrap();
+        assert_eq!((decoded.inputs, decoded.steps, decoded.end), (trace.inputs, trace.steps, trace.end));
+        assert!(decode_trace("{\"version\": 1}").is_err());
+
+        let (_, _, trace) = run("numb One. zero Zero. vid numb zero numb.", "");
+        let decoded = decode_trace(&encode(&trace)).unwrap();
+        assert_eq!(decoded.end, End::Error {
+            token: 2,
+            msg: String::from("ZeroDivisionError"),
+        });
+    }
+
+    #[test]
+    fn replay_finds_the_first_divergence() {
+        let code = "i Numb numb. ones One. Ad numb ones numb. P numb.";
+        let (_, _, trace) = run(code, "41\n");
+        assert_eq!(replay_code(code, &trace), None);
+
+        // Adds two instead of one
+        let changed = "i Numb numb. ones Two. Ad numb ones numb. P numb.";
+        let divergence = replay_code(changed, &trace).unwrap();
+        assert_eq!(
+            divergence,
+            "Step 2: the trace has statement 1 setting ones = 1, but statement 1 (VAR ones = 2) setting ones = 2 ran."
+        );
+
+        // Stops earlier
+        let divergence = replay_code("i Numb numb. ones One. Goodbye.", &trace).unwrap();
+        assert!(divergence.starts_with("Step 3: the trace has statement 2 "), "{}", divergence);
+        assert!(divergence.ends_with("but the program exited with 0."), "{}", divergence);
+    }
+}
@@ -0,0 +1,421 @@
+// Infers the type of every variable at every statement, without running the program.
+//
+// Whether a variable holds a Number or a String is only checked when a statement runs, so a
+// `StoringToString` or `VarsNotOfSameType` hides until the statement is reached. This pass
+// follows the control-flow graph and tracks the types every variable can have on the way:
+//
+//   Number, String   the variable always has this type here
+//   Mixed            a Number on one path and a String on another (e.g. after two different Inputs)
+//   Unknown          decided when the program runs (program arguments), never reported
+//
+// Variables which aren't set on any path yet are left out. The problems found are reported by
+// the `type-conflict`, `string-comparison` and `string-arithmetic` lints.
+
+use std::collections::HashMap;
+
+use crate::cfg::{Cfg, EdgeKind};
+use crate::dialect::{BranchCondition, Dialect};
+use crate::lint::Lint;
+use crate::{Operation, Token, Type, ARG_COUNT_VAR_NAME, EOF_VAR_NAME};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Inferred {
+    Number,
+    String,
+    Mixed,
+    Unknown,
+}
+
+impl Inferred {
+    fn join(self, other: Inferred) -> Inferred {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Inferred::Unknown, _) | (_, Inferred::Unknown) => Inferred::Unknown,
+            _ => Inferred::Mixed,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Inferred::Number => "Number",
+            Inferred::String => "String",
+            Inferred::Mixed | Inferred::Unknown => "anything",
+        }
+    }
+
+    fn other(self) -> Inferred {
+        match self {
+            Inferred::Number => Inferred::String,
+            _ => Inferred::Number,
+        }
+    }
+}
+
+// The types of the variables before a statement
+pub type Types = HashMap<String, Inferred>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Problem {
+    pub lint: Lint,
+    pub token: usize,
+    pub message: String,
+}
+
+pub struct Inference {
+    // Per token, `None` for statements which can't be reached
+    #[cfg(test)]
+    pub before: Vec<Option<Types>>,
+    pub problems: Vec<Problem>,
+}
+
+// Whether a variable has the type a statement needs
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Fit {
+    Yes,
+    Maybe,
+    No,
+}
+
+fn fit(types: &Types, name: &str, wanted: Inferred) -> Fit {
+    match types.get(name) {
+        // Variables which don't exist are a different error
+        None | Some(Inferred::Unknown) => Fit::Yes,
+        Some(Inferred::Mixed) => Fit::Maybe,
+        Some(t) if *t == wanted => Fit::Yes,
+        Some(_) => Fit::No,
+    }
+}
+
+// "`name` is a String" or "`name` might be a String", if the variable doesn't fit
+fn misfit(types: &Types, name: &str, wanted: Inferred) -> Option<String> {
+    let verb: &str = match fit(types, name, wanted) {
+        Fit::Yes => return None,
+        Fit::Maybe => "might be",
+        Fit::No => "is",
+    };
+    Some(format!("`{}` {} a {}", name, verb, wanted.other().name()))
+}
+
+// The types before the first statement
+fn initial_types(dialect: &Dialect) -> Types {
+    let mut types: Types = HashMap::new();
+    for (name, data) in &dialect.predefined_variables {
+        let inferred: Inferred = match data {
+            Type::Number(_) => Inferred::Number,
+            Type::String(_) => Inferred::String,
+        };
+        types.insert(name.clone(), inferred);
+    }
+    types.insert(String::from(EOF_VAR_NAME), Inferred::Number);
+    types.insert(String::from(ARG_COUNT_VAR_NAME), Inferred::Number);
+    for letter in 'a'..='z' {
+        types.insert(format!("arg{}", letter), Inferred::Unknown);
+    }
+    types
+}
+
+// Joins `incoming` into the types at the start of a block, returns whether they changed
+fn join_into(target: &mut Option<Types>, incoming: &Types) -> bool {
+    let types: &mut Types = match target {
+        Some(n) => n,
+        None => {
+            *target = Some(incoming.clone());
+            return true;
+        }
+    };
+    let mut changed: bool = false;
+    for (name, t) in incoming {
+        let joined: Inferred = types.get(name).map_or(*t, |n| n.join(*t));
+        if types.get(name) != Some(&joined) {
+            types.insert(name.clone(), joined);
+            changed = true;
+        }
+    }
+    changed
+}
+
+// Var stores a Number if its first argument starts uppercase, else a String. The same goes for
+// the argument of an Exit (a spelled out number or a variable). Input only looks at ASCII letters,
+// like it does when it runs.
+fn type_of_word(word: Option<&String>, is_uppercase: fn(&char) -> bool) -> Inferred {
+    match word.and_then(|n| n.chars().next()) {
+        Some(c) if is_uppercase(&c) => Inferred::Number,
+        _ => Inferred::String,
+    }
+}
+
+struct Pass<'a> {
+    tokens: &'a [Token],
+    dialect: &'a Dialect,
+}
+
+impl Pass<'_> {
+    // Storing a value of type `t` in a variable which already has another type stops the program.
+    // A statement which always stops leaves the type alone.
+    fn store(&self, types: &mut Types, name: Option<&String>, t: Inferred, problems: &mut Vec<(Lint, String)>) {
+        let name: &String = match name {
+            Some(n) => n,
+            None => return,
+        };
+        if let Some(subject) = misfit(types, name, t) {
+            problems.push((Lint::TypeConflict, format!("{}, it can't become a {}.", subject, t.name())));
+            if fit(types, name, t) == Fit::No {
+                return;
+            }
+        }
+        types.insert(name.clone(), t);
+    }
+
+    fn need(
+        &self,
+        types: &Types,
+        name: Option<&String>,
+        t: Inferred,
+        lint: Lint,
+        why: &str,
+        problems: &mut Vec<(Lint, String)>,
+    ) {
+        if let Some(subject) = name.and_then(|n| misfit(types, n, t)) {
+            problems.push((lint, format!("{}, {}", subject, why)));
+        }
+    }
+
+    fn branch(&self, t: &Token, types: &Types, problems: &mut Vec<(Lint, String)>) {
+        let (a, b) = match (t.args.first(), t.args.get(1)) {
+            (Some(a), Some(b)) if a != b => (a, b),
+            (Some(a), Some(_)) => (a, a),
+            _ => return,
+        };
+        let (ta, tb) = match (types.get(a), types.get(b)) {
+            (Some(ta), Some(tb)) => (*ta, *tb),
+            _ => return,
+        };
+        if ta == Inferred::Unknown || tb == Inferred::Unknown {
+            return;
+        }
+        if ta != tb && ta != Inferred::Mixed && tb != Inferred::Mixed {
+            problems.push((
+                Lint::TypeConflict,
+                format!("`{}` is a {} and `{}` is a {}, they can't be compared.", a, ta.name(), b, tb.name()),
+            ));
+            return;
+        }
+        if ta != tb || (ta == Inferred::Mixed && a != b) {
+            problems.push((Lint::TypeConflict, format!("`{}` and `{}` might not have the same type.", a, b)));
+            return;
+        }
+
+        let condition = t.name.chars().next().and_then(|c| self.dialect.branch_condition(c));
+        if matches!(condition, Some(BranchCondition::Less) | Some(BranchCondition::Greater)) {
+            let why: &str = "strings can only be compared for equality.";
+            self.need(types, Some(a), Inferred::Number, Lint::StringComparison, why, problems);
+        }
+    }
+
+    // Updates the types for the statement and returns the problems it has with them
+    fn apply(&self, t: &Token, types: &mut Types) -> Vec<(Lint, String)> {
+        let mut problems: Vec<(Lint, String)> = vec![];
+        match t.op {
+            Operation::Add | Operation::Sub | Operation::Mul | Operation::Div => {
+                let why: &str = "arithmetic only works on Numbers.";
+                // `Ad summ summ summ` only needs to hear about `summ` once
+                for (i, name) in t.args.iter().take(2).enumerate() {
+                    if i == 1 && t.args[0] == *name {
+                        continue;
+                    }
+                    self.need(types, Some(name), Inferred::Number, Lint::StringArithmetic, why, &mut problems);
+                }
+                self.store(types, t.args.get(2), Inferred::Number, &mut problems);
+            }
+            Operation::Var => {
+                self.store(types, Some(&t.name), type_of_word(t.args.first(), |c| c.is_uppercase()), &mut problems);
+            }
+            // An input replaces the variable, whatever it was before
+            Operation::Input => {
+                if let Some(name) = t.args.get(1) {
+                    types.insert(name.clone(), type_of_word(t.args.first(), char::is_ascii_uppercase));
+                }
+            }
+            Operation::Branch => self.branch(t, types, &mut problems),
+            Operation::Env => self.store(types, t.args.get(1), Inferred::String, &mut problems),
+            Operation::File => {
+                let why: &str = "paths have to be Strings.";
+                self.need(types, t.args.first(), Inferred::String, Lint::TypeConflict, why, &mut problems);
+                match t.name.chars().next().map(|c| c.to_ascii_lowercase()) {
+                    Some('r') | Some('l') => self.store(types, t.args.get(1), Inferred::String, &mut problems),
+                    Some('e') => self.store(types, t.args.get(1), Inferred::Number, &mut problems),
+                    _ => (),
+                }
+            }
+            Operation::Exit if type_of_word(t.args.first(), |c| c.is_uppercase()) == Inferred::String => {
+                let why: &str = "the exit status has to be a Number.";
+                self.need(types, t.args.first(), Inferred::Number, Lint::TypeConflict, why, &mut problems);
+            }
+            _ => (),
+        }
+        problems
+    }
+
+    // The types after the block when falling through and when jumping
+    fn run_block(&self, start: usize, end: usize, mut types: Types) -> (Types, Types) {
+        for t in &self.tokens[start..end - 1] {
+            self.apply(t, &mut types);
+        }
+        let last: &Token = &self.tokens[end - 1];
+        let before_last: Types = types.clone();
+        self.apply(last, &mut types);
+        // A line read only jumps at the end of the file, without storing anything
+        let jump: Types = if last.op == Operation::File && last.name.to_lowercase().starts_with('l') {
+            before_last
+        } else {
+            types.clone()
+        };
+        (types, jump)
+    }
+}
+
+// Runs the pass over the tokens, `labels` from `create_labels`
+pub fn infer(tokens: &[Token], labels: &HashMap<String, usize>, dialect: &Dialect) -> Inference {
+    let pass = Pass { tokens, dialect };
+    let cfg = Cfg::build(tokens, labels);
+    let mut entry: Vec<Option<Types>> = vec![None; cfg.blocks.len()];
+    let mut todo: Vec<usize> = vec![];
+    if !cfg.blocks.is_empty() {
+        entry[0] = Some(initial_types(dialect));
+        todo.push(0);
+    }
+
+    // Until nothing changes anymore: every variable can only go from Number or String to Mixed
+    // and Unknown, so this ends
+    while let Some(block) = todo.pop() {
+        let types: Types = entry[block].clone().unwrap_or_default();
+        let (fall, jump) = pass.run_block(cfg.blocks[block].start, cfg.blocks[block].end, types);
+        for edge in cfg.successors(block) {
+            let out: &Types = if edge.kind == EdgeKind::Jump { &jump } else { &fall };
+            if join_into(&mut entry[edge.to], out) && !todo.contains(&edge.to) {
+                todo.push(edge.to);
+            }
+        }
+    }
+
+    let mut inference = Inference {
+        #[cfg(test)]
+        before: vec![None; tokens.len()],
+        problems: vec![],
+    };
+    for (block, types) in cfg.blocks.iter().zip(entry) {
+        let mut types: Types = match types {
+            Some(n) => n,
+            None => continue,
+        };
+        for (ip, t) in (block.start..block.end).zip(&tokens[block.start..block.end]) {
+            #[cfg(test)]
+            {
+                inference.before[ip] = Some(types.clone());
+            }
+            for (lint, message) in pass.apply(t, &mut types) {
+                inference.problems.push(Problem { lint, token: ip, message });
+            }
+        }
+    }
+    inference.problems.sort_by_key(|p| p.token);
+    inference
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile_mt_code, create_labels};
+
+    fn infer_code(code: &str) -> (Vec<Token>, Inference) {
+        let dialect = Dialect::standard();
+        let (tokens, _) = compile_mt_code(code, &dialect);
+        let labels = create_labels(tokens.clone());
+        let inference = infer(&tokens, &labels, &dialect);
+        (tokens, inference)
+    }
+
+    fn problems(code: &str) -> Vec<(Lint, usize, String)> {
+        infer_code(code).1.problems.into_iter().map(|p| (p.lint, p.token, p.message)).collect()
+    }
+
+    #[test]
+    fn types_flow_through_branches() {
+        // `name` is a String or a Number, depending on which Input ran
+        let code = "onee One. Equal onee arga number. \
+                    i str name. Equal onee onee joined. \
+                    number. i Num name. \
+                    joined. P name.";
+        let (_, inference) = infer_code(code);
+        let type_of = |ip: usize, name: &str| inference.before[ip].as_ref().and_then(|n| n.get(name).copied());
+        assert_eq!(type_of(1, "onee"), Some(Inferred::Number));
+        assert_eq!(type_of(1, "name"), None);
+        assert_eq!(type_of(3, "name"), Some(Inferred::String));
+        assert_eq!(type_of(7, "name"), Some(Inferred::Mixed));
+        assert_eq!(type_of(7, "arga"), Some(Inferred::Unknown));
+        assert_eq!(inference.problems, vec![]);
+    }
+
+    #[test]
+    fn finds_type_problems() {
+        let code = "text hello. onee One. \
+                    Ad text onee sums. \
+                    text Two. \
+                    onee words. \
+                    Lower text text finish. \
+                    Equal text onee finish. \
+                    finish. Goodbye text.";
+        assert_eq!(
+            problems(code),
+            vec![
+                (Lint::StringArithmetic, 2, String::from("`text` is a String, arithmetic only works on Numbers.")),
+                (Lint::TypeConflict, 3, String::from("`text` is a String, it can't become a Number.")),
+                (Lint::TypeConflict, 4, String::from("`onee` is a Number, it can't become a String.")),
+                (
+                    Lint::StringComparison,
+                    5,
+                    String::from("`text` is a String, strings can only be compared for equality.")
+                ),
+                (
+                    Lint::TypeConflict,
+                    6,
+                    String::from("`text` is a String and `onee` is a Number, they can't be compared.")
+                ),
+                (Lint::TypeConflict, 8, String::from("`text` is a String, the exit status has to be a Number.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn mixed_types_might_be_wrong() {
+        let code = "i str valu. Equal eoff eoff number. i Num valu. number. Ad valu valu valu.";
+        assert_eq!(
+            problems(code),
+            vec![
+                (
+                    Lint::StringArithmetic,
+                    4,
+                    String::from("`valu` might be a String, arithmetic only works on Numbers.")
+                ),
+                (Lint::TypeConflict, 4, String::from("`valu` might be a String, it can't become a Number.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn input_types_follow_the_runtime() {
+        // Input only takes ASCII capitals for Numbers, Var takes every capital
+        let (_, inference) = infer_code("i Énorme valu. vari Élan. P valu vari.");
+        let types = inference.before[2].as_ref().unwrap();
+        assert_eq!(types.get("valu"), Some(&Inferred::String));
+        assert_eq!(types.get("vari"), Some(&Inferred::Number));
+    }
+
+    #[test]
+    fn line_reads_only_store_when_they_dont_jump() {
+        let code = "path data. readln. linefile path line finish. P line. Equal path path readln. finish. P line.";
+        let (_, inference) = infer_code(code);
+        assert_eq!(inference.before[5].as_ref().unwrap().get("line"), Some(&Inferred::String));
+        assert_eq!(inference.problems, vec![]);
+    }
+}